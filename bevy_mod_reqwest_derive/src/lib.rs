@@ -0,0 +1,297 @@
+//! Derive macros for [bevy_mod_reqwest](https://crates.io/crates/bevy_mod_reqwest), use them through its
+//! `derive` feature instead of depending on this crate directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, LitStr, Type};
+
+const METHODS: [&str; 7] = ["GET", "POST", "PUT", "PATCH", "DELETE", "HEAD", "OPTIONS"];
+
+/// Implements `HttpEndpoint` for a struct, see the documentation of the trait in `bevy_mod_reqwest` for the
+/// attributes it takes
+#[proc_macro_derive(HttpEndpoint, attributes(endpoint))]
+pub fn derive_http_endpoint(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// the `#[endpoint(..)]` attribute on the struct
+struct EndpointAttr {
+    method: Ident,
+    path: LitStr,
+    response: Option<Type>,
+    error: Option<Type>,
+}
+
+/// where the value of a field goes in the request
+enum FieldKind {
+    Path,
+    Query(String),
+    Header(String),
+    JsonBody,
+    FormBody,
+    Skip,
+}
+
+/// a piece of the path, either literal text or the name of a field
+enum Segment {
+    Text(String),
+    Field(String),
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let attr = parse_endpoint_attr(&input)?;
+    let segments = parse_path(&attr.path)?;
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter().collect(),
+            Fields::Unit => Vec::new(),
+            Fields::Unnamed(_) => {
+                return Err(syn::Error::new(
+                    input.ident.span(),
+                    "HttpEndpoint can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "HttpEndpoint can only be derived for structs",
+            ))
+        }
+    };
+
+    // every placeholder in the path has to name a field
+    for segment in &segments {
+        if let Segment::Field(name) = segment {
+            if !fields
+                .iter()
+                .any(|field| field.ident.as_ref().is_some_and(|ident| ident == name))
+            {
+                return Err(syn::Error::new(
+                    attr.path.span(),
+                    format!("the path uses `{{{name}}}`, but there is no field named `{name}`"),
+                ));
+            }
+        }
+    }
+
+    let mut parts = Vec::new();
+    let mut body = None;
+    for field in &fields {
+        let ident = field.ident.as_ref().expect("fields are named");
+        let in_path = segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Field(name) if ident == name));
+        let kind = parse_field_kind(field, in_path)?;
+        let part = match kind {
+            FieldKind::Path | FieldKind::Skip => continue,
+            FieldKind::Query(name) => quote! {
+                let request = request.query(&[(#name, &self.#ident)]);
+            },
+            FieldKind::Header(name) => quote! {
+                let request = request.header(#name, ::std::string::ToString::to_string(&self.#ident));
+            },
+            FieldKind::JsonBody | FieldKind::FormBody if body.is_some() => {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    "an endpoint can only have one body field",
+                ))
+            }
+            FieldKind::JsonBody => {
+                body = Some(ident);
+                quote! { let request = request.json(&self.#ident); }
+            }
+            FieldKind::FormBody => {
+                body = Some(ident);
+                quote! { let request = request.form(&self.#ident); }
+            }
+        };
+        parts.push(part);
+    }
+
+    let path = segments.iter().map(|segment| match segment {
+        Segment::Text(text) => quote! { path.push_str(#text); },
+        Segment::Field(name) => {
+            let ident = Ident::new(name, attr.path.span());
+            quote! { path.push_str(&::bevy_mod_reqwest::__private::encode_path_segment(&self.#ident)); }
+        }
+    });
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let method = &attr.method;
+    let error = attr
+        .error
+        .map(|error| quote! { #error })
+        .unwrap_or_else(|| quote! { ::bevy_mod_reqwest::serde_json::Value });
+    let (response, parse_response) = match attr.response {
+        Some(response) => (quote! { #response }, quote! {}),
+        // without a response type the body is not looked at, so an empty body is not an error
+        None => (
+            quote! { () },
+            quote! {
+                fn parse_response(_body: &[u8]) -> ::bevy_mod_reqwest::serde_json::Result<()> {
+                    ::std::result::Result::Ok(())
+                }
+            },
+        ),
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::bevy_mod_reqwest::HttpEndpoint for #name #ty_generics #where_clause {
+            type Response = #response;
+            type Error = #error;
+
+            fn request(
+                &self,
+                client: &::bevy_mod_reqwest::reqwest::Client,
+                base_url: ::std::option::Option<&str>,
+            ) -> ::bevy_mod_reqwest::reqwest::RequestBuilder {
+                let mut path = ::std::string::String::new();
+                #(#path)*
+                let request = client.request(
+                    ::bevy_mod_reqwest::reqwest::Method::#method,
+                    ::bevy_mod_reqwest::__private::endpoint_url(base_url, &path),
+                );
+                #(#parts)*
+                request
+            }
+
+            #parse_response
+        }
+    })
+}
+
+fn parse_endpoint_attr(input: &DeriveInput) -> syn::Result<EndpointAttr> {
+    let mut method = None;
+    let mut path: Option<LitStr> = None;
+    let mut response = None;
+    let mut error = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("endpoint"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("method") {
+                let lit: LitStr = meta.value()?.parse()?;
+                let value = lit.value().to_ascii_uppercase();
+                if !METHODS.contains(&value.as_str()) {
+                    return Err(syn::Error::new(
+                        lit.span(),
+                        format!("the method has to be one of {}", METHODS.join(", ")),
+                    ));
+                }
+                method = Some(Ident::new(&value, lit.span()));
+            } else if meta.path.is_ident("path") {
+                path = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("response") {
+                response = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("error") {
+                error = Some(meta.value()?.parse()?);
+            } else {
+                return Err(meta.error("expected `method`, `path`, `response` or `error`"));
+            }
+            Ok(())
+        })?;
+    }
+    let Some(path) = path else {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            "missing the path, add `#[endpoint(path = \"/..\")]`",
+        ));
+    };
+    Ok(EndpointAttr {
+        method: method.unwrap_or_else(|| Ident::new("GET", path.span())),
+        path,
+        response,
+        error,
+    })
+}
+
+fn parse_field_kind(field: &syn::Field, in_path: bool) -> syn::Result<FieldKind> {
+    let ident = field.ident.as_ref().expect("fields are named");
+    let mut kind = None;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("endpoint"))
+    {
+        attr.parse_nested_meta(|meta| {
+            let parsed = if meta.path.is_ident("query") {
+                match meta.input.peek(syn::Token![=]) {
+                    true => FieldKind::Query(meta.value()?.parse::<LitStr>()?.value()),
+                    false => FieldKind::Query(ident.to_string()),
+                }
+            } else if meta.path.is_ident("header") {
+                FieldKind::Header(meta.value()?.parse::<LitStr>()?.value())
+            } else if meta.path.is_ident("body") {
+                match meta.input.peek(syn::Token![=]) {
+                    true => {
+                        let lit: LitStr = meta.value()?.parse()?;
+                        match lit.value().as_str() {
+                            "json" => FieldKind::JsonBody,
+                            "form" => FieldKind::FormBody,
+                            _ => {
+                                return Err(syn::Error::new(
+                                    lit.span(),
+                                    "the body has to be \"json\" or \"form\"",
+                                ))
+                            }
+                        }
+                    }
+                    false => FieldKind::JsonBody,
+                }
+            } else if meta.path.is_ident("skip") {
+                FieldKind::Skip
+            } else {
+                return Err(meta.error("expected `query`, `header`, `body` or `skip`"));
+            };
+            if kind.replace(parsed).is_some() {
+                return Err(meta.error("a field can only go in one place of the request"));
+            }
+            Ok(())
+        })?;
+    }
+    match kind {
+        Some(_) if in_path => Err(syn::Error::new(
+            ident.span(),
+            format!("`{ident}` is already used in the path"),
+        )),
+        Some(kind) => Ok(kind),
+        None if in_path => Ok(FieldKind::Path),
+        None => Ok(FieldKind::Query(ident.to_string())),
+    }
+}
+
+fn parse_path(path: &LitStr) -> syn::Result<Vec<Segment>> {
+    let value = path.value();
+    let mut segments = Vec::new();
+    let mut rest = value.as_str();
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            return Err(syn::Error::new(path.span(), "unclosed `{` in the path"));
+        };
+        let name = &rest[start + 1..start + end];
+        if syn::parse_str::<Ident>(name).is_err() {
+            return Err(syn::Error::new(
+                path.span(),
+                format!("`{{{name}}}` in the path is not the name of a field"),
+            ));
+        }
+        if start > 0 {
+            segments.push(Segment::Text(rest[..start].to_owned()));
+        }
+        segments.push(Segment::Field(name.to_owned()));
+        rest = &rest[start + end + 1..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Text(rest.to_owned()));
+    }
+    Ok(segments)
+}
@@ -0,0 +1,6 @@
+//! The errors of the `HttpEndpoint` derive, the expected output is next to each case in `tests/ui`
+
+#[test]
+fn compile_fail() {
+    trybuild::TestCases::new().compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,10 @@
+use bevy_mod_reqwest_derive::HttpEndpoint;
+
+#[derive(HttpEndpoint)]
+#[endpoint(path = "/users/{id}")]
+struct User {
+    #[endpoint(query)]
+    id: u64,
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+use bevy_mod_reqwest_derive::HttpEndpoint;
+
+#[derive(HttpEndpoint)]
+#[endpoint(path = "/users")]
+struct Users(u32);
+
+fn main() {}
@@ -0,0 +1,12 @@
+use bevy_mod_reqwest_derive::HttpEndpoint;
+
+#[derive(HttpEndpoint)]
+#[endpoint(method = "POST", path = "/users")]
+struct CreateUser {
+    #[endpoint(body)]
+    user: String,
+    #[endpoint(body = "form")]
+    extra: String,
+}
+
+fn main() {}
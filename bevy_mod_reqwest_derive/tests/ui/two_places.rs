@@ -0,0 +1,10 @@
+use bevy_mod_reqwest_derive::HttpEndpoint;
+
+#[derive(HttpEndpoint)]
+#[endpoint(path = "/users")]
+struct Users {
+    #[endpoint(query, header = "X-Page")]
+    page: u32,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use bevy_mod_reqwest_derive::HttpEndpoint;
+
+#[derive(HttpEndpoint)]
+#[endpoint(path = "/users/{id")]
+struct User {
+    id: u64,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use bevy_mod_reqwest_derive::HttpEndpoint;
+
+#[derive(HttpEndpoint)]
+#[endpoint(method = "POST", path = "/users")]
+struct CreateUser {
+    #[endpoint(body = "xml")]
+    user: String,
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+use bevy_mod_reqwest_derive::HttpEndpoint;
+
+#[derive(HttpEndpoint)]
+#[endpoint(method = "FETCH", path = "/users")]
+struct Users;
+
+fn main() {}
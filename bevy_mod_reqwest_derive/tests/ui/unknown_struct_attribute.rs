@@ -0,0 +1,7 @@
+use bevy_mod_reqwest_derive::HttpEndpoint;
+
+#[derive(HttpEndpoint)]
+#[endpoint(path = "/users", timeout = "5s")]
+struct Users;
+
+fn main() {}
@@ -1,6 +1,6 @@
 use std::time::Duration;
 
-use bevy::{log::LogPlugin, prelude::*, time::common_conditions::on_timer};
+use bevy::{log::LogPlugin, prelude::*};
 use bevy_mod_reqwest::*;
 use serde::Deserialize;
 
@@ -18,8 +18,11 @@ fn send_requests(mut client: BevyReqwest) {
     let reqwest_request = client.get(url).build().unwrap();
 
     client
-        // Sends the created http request
-        .send(reqwest_request)
+        // Sends the created http request every five seconds, from the same entity
+        .send_periodic(PeriodicRequest::new(
+            reqwest_request,
+            Duration::from_secs(5),
+        ))
         // The response from the http request can be reached using an observersystem
         .on_json_response(|trigger: On<JsonResponse<Bored>>| {
             let data: &Bored = &trigger.event().data;
@@ -38,9 +41,6 @@ fn main() {
         .add_plugins(MinimalPlugins)
         .add_plugins(LogPlugin::default())
         .add_plugins(ReqwestPlugin::default())
-        .add_systems(
-            Update,
-            send_requests.run_if(on_timer(Duration::from_secs(5))),
-        )
+        .add_systems(Startup, send_requests)
         .run();
 }
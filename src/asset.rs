@@ -0,0 +1,518 @@
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_channel::{bounded, unbounded, Receiver, Sender};
+use bevy::{
+    asset::{
+        io::{
+            AssetReader, AssetReaderError, AssetSource, AssetSourceEvent, AssetWatcher, PathStream,
+            Reader, VecReader,
+        },
+        AssetApp,
+    },
+    prelude::*,
+};
+use reqwest::header::{
+    HeaderMap, HeaderValue, CACHE_CONTROL, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+
+use crate::{BevyReqwest, ReqwestClient, ReqwestErrorEvent, ReqwestResponseEvent, ReqwestSet};
+
+/// Registers the `http` and `https` asset sources, so assets can be loaded from a url using the
+/// [`AssetServer`], as in `asset_server.load("https://cdn.example.com/textures/rock.png")`.
+///
+/// The assets are requested through the [`ReqwestPlugin`](crate::ReqwestPlugin), using the same
+/// [`ReqwestClient`] with its default headers, and the same timeouts, retries, limits and cache as any other
+/// request. A `404 Not Found` is reported as [`AssetReaderError::NotFound`], and any other unsuccessful status as
+/// [`AssetReaderError::HttpError`].
+///
+/// The plugin has to be added before the [`AssetPlugin`], which is part of the `DefaultPlugins`.
+///
+/// When the [`AssetServer`] watches for changes, with bevy's `file_watcher` feature or
+/// [`AssetPlugin::watch_for_changes_override`], the loaded urls are polled every
+/// [`watch_interval`](Self::watch_interval) with conditional requests, and the assets whose content changed
+/// are hot-reloaded like local ones.
+///
+/// # Examples
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy_mod_reqwest::*;
+///
+/// App::new()
+///     .add_plugins(ReqwestAssetPlugin::default())
+///     .add_plugins(DefaultPlugins)
+///     .add_plugins(ReqwestPlugin::default())
+///     .run();
+/// ```
+pub struct ReqwestAssetPlugin {
+    /// lets the [`ReqwestCache`](crate::ReqwestCache) answer and store the requests for assets, if it is enabled
+    pub cache: bool,
+    /// how often the loaded urls are checked for changes, when the asset server watches for changes
+    pub watch_interval: Duration,
+}
+
+impl Default for ReqwestAssetPlugin {
+    fn default() -> Self {
+        Self {
+            cache: true,
+            watch_interval: Duration::from_secs(2),
+        }
+    }
+}
+
+impl Plugin for ReqwestAssetPlugin {
+    fn build(&self, app: &mut App) {
+        if app.is_plugin_added::<AssetPlugin>() {
+            warn!("ReqwestAssetPlugin has to be added before AssetPlugin for the http and https sources to work");
+        }
+        let (tx, requests) = unbounded();
+        let watched = Arc::new(Mutex::new(WatchedAssets::default()));
+        for scheme in ["http", "https"] {
+            let reader = ReqwestAssetReader {
+                scheme,
+                requests: tx.clone(),
+            };
+            let watched = watched.clone();
+            app.register_asset_source(
+                scheme,
+                AssetSource::build()
+                    .with_reader({
+                        let reader = reader.clone();
+                        move || Box::new(reader.clone())
+                    })
+                    .with_processed_reader(move || Box::new(reader.clone()))
+                    .with_watcher(move |events| {
+                        watched
+                            .lock()
+                            .unwrap()
+                            .events
+                            .insert(scheme, Box::new(move |event| drop(events.send(event))));
+                        Some(Box::new(ReqwestAssetWatcher {
+                            scheme,
+                            watched: watched.clone(),
+                        }))
+                    }),
+            );
+        }
+        app.insert_resource(AssetRequests {
+            requests,
+            cache: self.cache,
+            watched,
+            poll: Timer::new(self.watch_interval, TimerMode::Repeating),
+        });
+        app.add_systems(
+            PreUpdate,
+            (send_asset_requests, poll_watched_assets).in_set(ReqwestSet),
+        );
+    }
+
+    fn finish(&self, app: &mut App) {
+        if !app.world().contains_resource::<ReqwestClient>() {
+            warn!("ReqwestAssetPlugin needs the ReqwestPlugin to load assets");
+        }
+    }
+}
+
+/// Reads assets by sending their path, prefixed with the scheme, to [`send_asset_requests`]
+#[derive(Clone)]
+struct ReqwestAssetReader {
+    scheme: &'static str,
+    requests: Sender<AssetRequest>,
+}
+
+impl ReqwestAssetReader {
+    fn url(&self, path: &Path) -> String {
+        // asset paths use forward slashes on every platform, but the path might still have been joined on windows
+        let path = path.to_string_lossy().replace('\\', "/");
+        format!("{}://{path}", self.scheme)
+    }
+
+    async fn get(
+        &self,
+        path: &Path,
+        url: String,
+        meta: bool,
+    ) -> Result<VecReader, AssetReaderError> {
+        let (respond, response) = bounded(1);
+        let request = AssetRequest {
+            url,
+            scheme: self.scheme,
+            path: path.to_owned(),
+            meta,
+            respond,
+        };
+        if self.requests.send(request).await.is_err() {
+            return Err(interrupted(path));
+        }
+        // the request ended some other way, such as a timeout, if the entity was despawned without a response
+        let bytes = response.recv().await.map_err(|_| interrupted(path))??;
+        Ok(VecReader::new(bytes))
+    }
+}
+
+impl AssetReader for ReqwestAssetReader {
+    async fn read<'a>(&'a self, path: &'a Path) -> Result<impl Reader + 'a, AssetReaderError> {
+        self.get(path, self.url(path), false).await
+    }
+
+    async fn read_meta<'a>(&'a self, path: &'a Path) -> Result<impl Reader + 'a, AssetReaderError> {
+        // the meta file of `foo.png` is `foo.png.meta`
+        self.get(path, format!("{}.meta", self.url(path)), true)
+            .await
+    }
+
+    async fn read_directory<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> Result<Box<PathStream>, AssetReaderError> {
+        Err(AssetReaderError::NotFound(path.to_owned()))
+    }
+
+    async fn is_directory<'a>(&'a self, _path: &'a Path) -> Result<bool, AssetReaderError> {
+        Ok(false)
+    }
+}
+
+fn interrupted(path: &Path) -> AssetReaderError {
+    AssetReaderError::Io(
+        std::io::Error::new(
+            std::io::ErrorKind::Interrupted,
+            format!("the request for {} did not finish", path.display()),
+        )
+        .into(),
+    )
+}
+
+/// Keeps the asset source events of its scheme flowing for as long as the asset server watches it
+struct ReqwestAssetWatcher {
+    scheme: &'static str,
+    watched: Arc<Mutex<WatchedAssets>>,
+}
+
+impl AssetWatcher for ReqwestAssetWatcher {}
+
+impl Drop for ReqwestAssetWatcher {
+    fn drop(&mut self) {
+        let mut watched = self.watched.lock().unwrap();
+        watched.events.remove(self.scheme);
+        let scheme = self.scheme;
+        watched.assets.retain(|_, asset| asset.scheme != scheme);
+    }
+}
+
+/// The loaded urls of the watched schemes, with what is needed to tell whether they changed
+#[derive(Default)]
+struct WatchedAssets {
+    events: HashMap<&'static str, Box<dyn Fn(AssetSourceEvent) + Send + Sync>>,
+    assets: HashMap<String, WatchedAsset>,
+}
+
+struct WatchedAsset {
+    scheme: &'static str,
+    path: PathBuf,
+    meta: bool,
+    etag: Option<HeaderValue>,
+    last_modified: Option<HeaderValue>,
+    hash: u64,
+    polling: bool,
+}
+
+impl WatchedAssets {
+    /// remembers a loaded url, if its scheme is watched
+    fn record(&mut self, request: &AssetRequest, headers: &HeaderMap, body: &[u8]) {
+        if !self.events.contains_key(request.scheme) {
+            return;
+        }
+        self.assets.insert(
+            request.url.clone(),
+            WatchedAsset {
+                scheme: request.scheme,
+                path: request.path.clone(),
+                meta: request.meta,
+                etag: headers.get(ETAG).cloned(),
+                last_modified: headers.get(LAST_MODIFIED).cloned(),
+                hash: hash(body),
+                polling: false,
+            },
+        );
+    }
+
+    /// takes the response of a poll into account, and tells the asset server if the content changed
+    fn polled(&mut self, url: &str, headers: &HeaderMap, body: &[u8]) {
+        let Some(asset) = self.assets.get_mut(url) else {
+            return;
+        };
+        asset.polling = false;
+        asset.etag = headers.get(ETAG).cloned().or(asset.etag.take());
+        asset.last_modified = headers
+            .get(LAST_MODIFIED)
+            .cloned()
+            .or(asset.last_modified.take());
+        // a server might ignore the validators, so only a different body counts as a change
+        let hash = hash(body);
+        if hash == asset.hash {
+            return;
+        }
+        asset.hash = hash;
+        debug!("asset {url} changed");
+        let event = match asset.meta {
+            true => AssetSourceEvent::ModifiedMeta(asset.path.clone()),
+            false => AssetSourceEvent::ModifiedAsset(asset.path.clone()),
+        };
+        if let Some(send) = self.events.get(asset.scheme) {
+            send(event);
+        }
+    }
+}
+
+fn hash(body: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// An asset the [`AssetServer`] is waiting for
+struct AssetRequest {
+    url: String,
+    scheme: &'static str,
+    path: PathBuf,
+    meta: bool,
+    respond: Sender<Result<Vec<u8>, AssetReaderError>>,
+}
+
+#[derive(Resource)]
+struct AssetRequests {
+    requests: Receiver<AssetRequest>,
+    cache: bool,
+    watched: Arc<Mutex<WatchedAssets>>,
+    poll: Timer,
+}
+
+/// sends the requests of the asset readers, and hands the responses back to them
+fn send_asset_requests(mut client: BevyReqwest, requests: Res<AssetRequests>) {
+    while let Ok(asset) = requests.requests.try_recv() {
+        let mut request = client.get(&asset.url);
+        if !requests.cache {
+            request = request.header(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+        }
+        let request = match request.build() {
+            Ok(request) => request,
+            Err(e) => {
+                asset.respond.try_send(Err(io_error(e))).ok();
+                continue;
+            }
+        };
+        debug!("requesting asset {}", asset.url);
+        let on_error = asset.respond.clone();
+        let watched = requests.watched.clone();
+        client
+            .send(request)
+            // the status is turned into the error of the asset reader here
+            .error_for_status(false)
+            .on_response(move |trigger: On<ReqwestResponseEvent>| {
+                let response = trigger.event();
+                let result = match response.status().as_u16() {
+                    404 => Err(AssetReaderError::NotFound(asset.path.clone())),
+                    _ if response.status().is_success() => {
+                        watched.lock().unwrap().record(
+                            &asset,
+                            response.response_headers(),
+                            response.body(),
+                        );
+                        Ok(response.body().to_vec())
+                    }
+                    code => Err(AssetReaderError::HttpError(code)),
+                };
+                asset.respond.try_send(result).ok();
+            })
+            .on_error(move |trigger: On<ReqwestErrorEvent>| {
+                let error = io_error(&trigger.event().error);
+                on_error.try_send(Err(error)).ok();
+            });
+    }
+}
+
+/// checks the loaded urls of the watched schemes for changes, every [`ReqwestAssetPlugin::watch_interval`]
+fn poll_watched_assets(
+    mut client: BevyReqwest,
+    time: Res<Time<Real>>,
+    mut requests: ResMut<AssetRequests>,
+) {
+    if !requests.poll.tick(time.delta()).just_finished() {
+        return;
+    }
+    let mut watched = requests.watched.lock().unwrap();
+    for (url, asset) in watched
+        .assets
+        .iter_mut()
+        .filter(|(_, asset)| !asset.polling)
+    {
+        let mut request = client.get(url);
+        match (&asset.etag, &asset.last_modified) {
+            (Some(etag), _) => request = request.header(IF_NONE_MATCH, etag.clone()),
+            (None, Some(last_modified)) => {
+                request = request.header(IF_MODIFIED_SINCE, last_modified.clone())
+            }
+            // make sure the cache does not answer with what was loaded before
+            (None, None) => {
+                request = request.header(CACHE_CONTROL, HeaderValue::from_static("no-cache"))
+            }
+        }
+        if !requests.cache {
+            request = request.header(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+        }
+        let Ok(request) = request.build() else {
+            continue;
+        };
+        asset.polling = true;
+        let (on_response, on_error) = (requests.watched.clone(), requests.watched.clone());
+        let (url, error_url) = (url.clone(), url.clone());
+        client
+            .send(request)
+            .error_for_status(false)
+            .on_response(move |trigger: On<ReqwestResponseEvent>| {
+                let response = trigger.event();
+                let mut watched = on_response.lock().unwrap();
+                if response.status().is_success() {
+                    watched.polled(&url, response.response_headers(), response.body());
+                } else if let Some(asset) = watched.assets.get_mut(&url) {
+                    // not modified, or an error that is retried with the next poll
+                    asset.polling = false;
+                }
+            })
+            .on_error(move |_: On<ReqwestErrorEvent>| {
+                if let Some(asset) = on_error.lock().unwrap().assets.get_mut(&error_url) {
+                    asset.polling = false;
+                }
+            });
+    }
+}
+
+fn io_error(error: impl ToString) -> AssetReaderError {
+    AssetReaderError::Io(std::io::Error::other(error.to_string()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::{
+        MockResponse, MockRoute, ReqwestInflight, ReqwestMock, ReqwestMockPlugin, ReqwestPlugin,
+    };
+
+    const URL: &str = "https://example.com/hello.txt";
+
+    /// an app polling [`URL`], which was loaded as `hello` with `headers`, and the source events it emits.
+    /// The asset server is left out, as it cannot reload an asset on a single threaded task pool
+    fn app(
+        headers: &[(&'static str, &'static str)],
+    ) -> (App, ReqwestMock, Receiver<AssetSourceEvent>) {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, ReqwestPlugin::default(), ReqwestMockPlugin));
+        let mock = app.world().resource::<ReqwestMock>().clone();
+
+        let (tx, events) = unbounded();
+        let mut watched = WatchedAssets::default();
+        watched
+            .events
+            .insert("https", Box::new(move |event| drop(tx.try_send(event))));
+        let (respond, _) = bounded(1);
+        let request = AssetRequest {
+            url: URL.to_owned(),
+            scheme: "https",
+            path: "example.com/hello.txt".into(),
+            meta: false,
+            respond,
+        };
+        let mut loaded = HeaderMap::new();
+        for (name, value) in headers {
+            loaded.insert(*name, HeaderValue::from_static(value));
+        }
+        watched.record(&request, &loaded, b"hello");
+
+        app.insert_resource(AssetRequests {
+            requests: unbounded().1,
+            cache: true,
+            watched: Arc::new(Mutex::new(watched)),
+            poll: Timer::new(Duration::from_millis(10), TimerMode::Repeating),
+        });
+        app.add_systems(PreUpdate, poll_watched_assets.in_set(ReqwestSet));
+        (app, mock, events)
+    }
+
+    /// updates the app until the asset was polled `polls` times and no poll is in flight anymore
+    fn poll(app: &mut App, mock: &ReqwestMock, polls: usize) {
+        let start = Instant::now();
+        loop {
+            std::thread::sleep(Duration::from_millis(5));
+            app.update();
+            let mut inflight = app.world_mut().query::<&ReqwestInflight>();
+            if mock.requests().len() >= polls && inflight.iter(app.world()).next().is_none() {
+                return;
+            }
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "not polled {polls} times"
+            );
+        }
+    }
+
+    #[test]
+    fn polls_with_the_etag() {
+        let (mut app, mock, events) = app(&[("etag", "\"v1\"")]);
+        mock.route(MockRoute::get(URL), MockResponse::new(304));
+
+        poll(&mut app, &mock, 3);
+
+        assert!(mock
+            .requests()
+            .iter()
+            .all(|request| request.headers[IF_NONE_MATCH] == "\"v1\""));
+        assert_eq!(events.try_recv().ok(), None);
+    }
+
+    #[test]
+    fn polls_with_the_last_modified_date_without_an_etag() {
+        let date = "Wed, 21 Oct 2015 07:28:00 GMT";
+        let (mut app, mock, events) = app(&[("last-modified", date)]);
+        mock.route(MockRoute::get(URL), MockResponse::new(304));
+
+        poll(&mut app, &mock, 1);
+
+        let requests = mock.requests();
+        assert_eq!(requests[0].headers[IF_MODIFIED_SINCE], date);
+        assert!(!requests[0].headers.contains_key(IF_NONE_MATCH));
+        assert_eq!(events.try_recv().ok(), None);
+    }
+
+    #[test]
+    fn the_same_body_is_not_a_change() {
+        // the server ignores the validators
+        let (mut app, mock, events) = app(&[("etag", "\"v1\"")]);
+        mock.route(MockRoute::get(URL), MockResponse::ok().body("hello"));
+
+        poll(&mut app, &mock, 3);
+
+        assert_eq!(events.try_recv().ok(), None);
+    }
+
+    #[test]
+    fn another_body_is_a_change() {
+        let (mut app, mock, events) = app(&[]);
+        mock.route(MockRoute::get(URL), MockResponse::ok().body("goodbye"));
+
+        poll(&mut app, &mock, 3);
+
+        // only the first poll saw the change
+        let modified = AssetSourceEvent::ModifiedAsset("example.com/hello.txt".into());
+        assert_eq!(events.try_recv().ok(), Some(modified));
+        assert_eq!(events.try_recv().ok(), None);
+    }
+}
@@ -0,0 +1,952 @@
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use bytes::Bytes;
+use reqwest::{
+    header::{
+        HeaderMap, HeaderName, HeaderValue, AGE, CACHE_CONTROL, ETAG, IF_MODIFIED_SINCE,
+        IF_NONE_MATCH, LAST_MODIFIED, PRAGMA, VARY,
+    },
+    Method, StatusCode,
+};
+
+#[cfg(not(target_family = "wasm"))]
+mod disk;
+
+#[cfg(not(target_family = "wasm"))]
+pub(crate) use disk::{DiskLoad, LoadedEntry};
+
+/// The responses kept by the cache when [`ReqwestPlugin::cache`](crate::ReqwestPlugin::cache) is enabled, by
+/// method and url.
+///
+/// Responses to `GET` and `HEAD` requests are stored as long as their `Cache-Control` header allows it, and
+/// requests for them are answered right away while they are fresh. Once they are stale, the request is sent
+/// with `If-None-Match` or `If-Modified-Since`, and a `304 Not Modified` answer is delivered as the stored
+/// response. Within the `stale-while-revalidate` window of a response it is delivered right away, and
+/// revalidated in the background.
+///
+/// The least recently used responses are dropped once the cache is larger than
+/// [`ReqwestPlugin::cache_max_bytes`](crate::ReqwestPlugin::cache_max_bytes). On native, the responses are also
+/// written to [`ReqwestPlugin::cache_dir`](crate::ReqwestPlugin::cache_dir) if it is set, so they are still
+/// around after a restart. Times are measured using [`Time<Real>`], and the clock of the system for the
+/// responses on disk.
+///
+/// If the server cannot be reached or the request times out, a stale response is delivered in place of the
+/// error or the [`ReqwestTimeoutEvent`](crate::ReqwestTimeoutEvent), unless it was sent with `must-revalidate`
+#[derive(Resource, Debug, Default)]
+pub struct ReqwestCache {
+    enabled: bool,
+    max_bytes: usize,
+    // the variants of every method and url, which differ in the request headers named by Vary
+    entries: HashMap<String, Vec<CacheEntry>>,
+    size: usize,
+    #[cfg(not(target_family = "wasm"))]
+    disk: Option<disk::DiskCache>,
+    hits: AtomicU64,
+    revalidations: AtomicU64,
+    misses: AtomicU64,
+    // the real time of the app when the cache was last updated
+    now: Duration,
+}
+
+/// What the [`ReqwestCache`] holds and how often it was used, see [`ReqwestCache::stats`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReqwestCacheStats {
+    /// the number of responses kept in memory
+    pub entries: usize,
+    /// the size of the responses kept in memory in bytes
+    pub size: usize,
+    /// the number of responses in [`ReqwestPlugin::cache_dir`](crate::ReqwestPlugin::cache_dir)
+    pub disk_entries: usize,
+    /// the size of the files in [`ReqwestPlugin::cache_dir`](crate::ReqwestPlugin::cache_dir) in bytes
+    pub disk_size: u64,
+    /// the requests that were answered without asking the server
+    pub hits: u64,
+    /// the requests that asked the server whether the stored response is still valid
+    pub revalidations: u64,
+    /// the requests the cache had no response for
+    pub misses: u64,
+}
+
+impl ReqwestCache {
+    pub(crate) fn new(enabled: bool, max_bytes: usize) -> Self {
+        Self {
+            enabled,
+            max_bytes,
+            ..default()
+        }
+    }
+
+    /// keeps the responses in `dir` as well, up to `max_bytes`, picking up the ones that are already there
+    #[cfg(not(target_family = "wasm"))]
+    pub(crate) fn open_dir(&mut self, dir: &std::path::Path, max_bytes: u64) {
+        match disk::DiskCache::open(dir, max_bytes) {
+            Ok(disk) => self.disk = Some(disk),
+            Err(e) => warn!("could not open the cache directory {}: {e}", dir.display()),
+        }
+    }
+
+    /// true if responses are stored, see [`ReqwestPlugin::cache`](crate::ReqwestPlugin::cache)
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// the number of responses kept in memory
+    pub fn len(&self) -> usize {
+        self.entries.values().map(Vec::len).sum()
+    }
+
+    /// true if no responses are kept in memory
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// the size of the responses kept in memory in bytes, counting their bodies and headers
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// the number of responses kept and their size, in memory and on disk, and how often they were used
+    pub fn stats(&self) -> ReqwestCacheStats {
+        #[cfg(not(target_family = "wasm"))]
+        let (disk_entries, disk_size) = self
+            .disk
+            .as_ref()
+            .map_or((0, 0), |disk| (disk.len(), disk.size()));
+        #[cfg(target_family = "wasm")]
+        let (disk_entries, disk_size) = (0, 0);
+        ReqwestCacheStats {
+            entries: self.len(),
+            size: self.size,
+            disk_entries,
+            disk_size,
+            hits: self.hits.load(Ordering::Relaxed),
+            revalidations: self.revalidations.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// true if a response to a `GET` request for `url` is stored, fresh or not
+    pub fn contains(&self, url: &str) -> bool {
+        let key = key(&Method::GET, url);
+        #[cfg(not(target_family = "wasm"))]
+        if self.disk.as_ref().is_some_and(|disk| disk.contains(&key)) {
+            return true;
+        }
+        self.entries.contains_key(&key)
+    }
+
+    /// drops the stored responses for `url`, so the next request for it goes to the server
+    pub fn invalidate(&mut self, url: &str) {
+        for method in [Method::GET, Method::HEAD] {
+            let key = key(&method, url);
+            if let Some(variants) = self.entries.remove(&key) {
+                self.size -= variants.iter().map(|entry| entry.size).sum::<usize>();
+            }
+            #[cfg(not(target_family = "wasm"))]
+            if let Some(disk) = self.disk.as_mut() {
+                disk.remove(&key);
+            }
+        }
+    }
+
+    /// drops every stored response, including the ones on disk
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.size = 0;
+        #[cfg(not(target_family = "wasm"))]
+        if let Some(disk) = self.disk.as_mut() {
+            disk.clear();
+        }
+    }
+
+    /// updates the time the age of the responses is measured with
+    pub(crate) fn set_now(&mut self, now: Duration) {
+        self.now = now;
+    }
+
+    /// finds the stored response for `request`, and adds the validators to the request if it has to be
+    /// revalidated. A response that is only on disk has to be read first, and then looked up again once it
+    /// is in memory
+    pub(crate) fn lookup(&self, request: &mut reqwest::Request) -> CacheLookup {
+        if !self.enabled || !is_cacheable_method(request.method()) {
+            return CacheLookup::Miss;
+        }
+        let (directives, no_cache) = request_directives(request.headers());
+        // the caller asked the server for a conditional response itself
+        let conditional = request.headers().contains_key(IF_NONE_MATCH)
+            || request.headers().contains_key(IF_MODIFIED_SINCE);
+        if directives.no_store || conditional {
+            return CacheLookup::Miss;
+        }
+        let key = key(request.method(), request.url().as_str());
+        let lookup = match self.entries.get(&key).and_then(|variants| {
+            variants
+                .iter()
+                .find(|entry| entry.matches(request.headers()))
+        }) {
+            Some(entry) => {
+                entry.touch(self.now);
+                self.answer(entry, request, &directives, no_cache)
+            }
+            None => self
+                .find_on_disk(&key, request.headers())
+                .unwrap_or(CacheLookup::Miss),
+        };
+        self.count(lookup)
+    }
+
+    /// what the cache has for `request` once the response [`CacheLookup::Load`] waited for was read from
+    /// disk, the response is kept in memory from now on
+    #[cfg(not(target_family = "wasm"))]
+    pub(crate) fn lookup_loaded(
+        &mut self,
+        request: &mut reqwest::Request,
+        loaded: Option<LoadedEntry>,
+    ) -> CacheLookup {
+        let Some(LoadedEntry { key, entry }) = loaded else {
+            return self.count(CacheLookup::Miss);
+        };
+        let (directives, no_cache) = request_directives(request.headers());
+        let lookup = self.answer(&entry, request, &directives, no_cache);
+        self.insert_memory(key, entry);
+        self.count(lookup)
+    }
+
+    fn count(&self, lookup: CacheLookup) -> CacheLookup {
+        let counter = match lookup {
+            CacheLookup::Miss => &self.misses,
+            CacheLookup::Hit(..) => &self.hits,
+            CacheLookup::Revalidate { .. } => &self.revalidations,
+            // counted once it has been read
+            #[cfg(not(target_family = "wasm"))]
+            CacheLookup::Load(_) => return lookup,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        lookup
+    }
+
+    /// the response on disk for a request that is not in memory
+    #[cfg(not(target_family = "wasm"))]
+    fn find_on_disk(&self, key: &str, request_headers: &HeaderMap) -> Option<CacheLookup> {
+        let disk = self.disk.as_ref()?;
+        disk.find(key, request_headers, self.now)
+            .map(CacheLookup::Load)
+    }
+
+    #[cfg(target_family = "wasm")]
+    fn find_on_disk(&self, _key: &str, _request_headers: &HeaderMap) -> Option<CacheLookup> {
+        None
+    }
+
+    /// what to do with the stored response for `request`
+    fn answer(
+        &self,
+        entry: &CacheEntry,
+        request: &mut reqwest::Request,
+        directives: &CacheControl,
+        no_cache: bool,
+    ) -> CacheLookup {
+        let age = entry.age(self.now);
+        let max_age = directives
+            .max_age
+            .map_or(entry.freshness, |max_age| max_age.min(entry.freshness));
+        if !no_cache && age < max_age {
+            return CacheLookup::Hit(entry.response.clone(), None);
+        }
+        let validators = entry.validators();
+        let stale_for = age.saturating_sub(entry.freshness);
+        if validators.is_some()
+            && !no_cache
+            && !entry.must_revalidate
+            && stale_for < entry.stale_while_revalidate
+        {
+            // keep the request of the caller as it is, it is answered from the cache
+            if let Some(mut revalidate) = request.try_clone() {
+                revalidate
+                    .headers_mut()
+                    .extend(validators.clone().unwrap_or_default());
+                return CacheLookup::Hit(entry.response.clone(), Some(Box::new(revalidate)));
+            }
+        }
+        // without validators the server sends the whole response again, but the stored one is still good
+        // for when it cannot be reached
+        request.headers_mut().extend(validators.unwrap_or_default());
+        CacheLookup::Revalidate {
+            response: entry.response.clone(),
+            stale_if_error: !entry.must_revalidate,
+        }
+    }
+
+    /// stores the response to `request` if it can be, and drops the stored responses for its url if it was
+    /// an unsafe request such as `POST` that succeeded. A streamed response has no `body` to store
+    pub(crate) fn store(
+        &mut self,
+        request: Option<&reqwest::Request>,
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: Option<&Bytes>,
+    ) {
+        let Some(request) = request.filter(|_| self.enabled) else {
+            return;
+        };
+        let url = request.url().as_str();
+        if !is_cacheable_method(request.method()) {
+            // the stored response is most likely outdated now
+            if status.is_success() || status.is_redirection() {
+                self.invalidate(url);
+            }
+            return;
+        }
+        let Some(body) = body else {
+            return;
+        };
+        let directives = CacheControl::parse(headers);
+        let storable = is_cacheable_status(status)
+            && !directives.no_store
+            && !CacheControl::parse(request.headers()).no_store
+            && !vary(headers).any(|name| name == "*");
+        let response = CachedResponse {
+            status,
+            headers: headers.clone(),
+            body: body.clone(),
+        };
+        let entry = CacheEntry::new(response, request.headers(), self.now);
+        if !storable || (entry.freshness.is_zero() && entry.validators().is_none()) {
+            return;
+        }
+        self.insert(key(request.method(), url), entry);
+    }
+
+    /// updates the stored response after the server confirmed it with `304 Not Modified`, and returns the
+    /// response to deliver in place of the `304`
+    pub(crate) fn revalidated(
+        &mut self,
+        request: Option<&reqwest::Request>,
+        response: CachedResponse,
+        headers: &HeaderMap,
+    ) -> CachedResponse {
+        let mut response = response;
+        // the 304 carries the up to date headers of the response, apart from the ones describing the body
+        for (name, value) in headers {
+            if !is_body_header(name) {
+                response.headers.insert(name, value.clone());
+            }
+        }
+        if let Some(request) = request.filter(|_| self.enabled) {
+            let key = key(request.method(), request.url().as_str());
+            let entry = CacheEntry::new(response.clone(), request.headers(), self.now);
+            self.insert(key, entry);
+        }
+        response
+    }
+
+    /// stores `entry` in memory and on disk
+    fn insert(&mut self, key: String, entry: CacheEntry) {
+        #[cfg(not(target_family = "wasm"))]
+        if let Some(disk) = self.disk.as_mut() {
+            disk.store(&key, &entry);
+        }
+        self.insert_memory(key, entry);
+    }
+
+    /// keeps `entry` in memory in place of the variant it matches, and drops the least recently used responses
+    /// until the cache fits again
+    fn insert_memory(&mut self, key: String, entry: CacheEntry) {
+        if entry.size > self.max_bytes {
+            return;
+        }
+        let variants = self.entries.entry(key).or_default();
+        if let Some(i) = variants.iter().position(|old| old.vary == entry.vary) {
+            self.size -= variants.swap_remove(i).size;
+        }
+        self.size += entry.size;
+        variants.push(entry);
+
+        while self.size > self.max_bytes {
+            let Some((key, i)) =
+                self.entries
+                    .iter()
+                    .flat_map(|(key, variants)| {
+                        variants.iter().enumerate().map(move |(i, entry)| {
+                            (entry.last_used.load(Ordering::Relaxed), key, i)
+                        })
+                    })
+                    .min()
+                    .map(|(_, key, i)| (key.clone(), i))
+            else {
+                break;
+            };
+            let variants = self.entries.get_mut(&key).unwrap();
+            self.size -= variants.swap_remove(i).size;
+            if variants.is_empty() {
+                self.entries.remove(&key);
+            }
+        }
+    }
+}
+
+/// A response as it is kept in the cache
+#[derive(Clone, Debug)]
+pub(crate) struct CachedResponse {
+    pub(crate) status: StatusCode,
+    pub(crate) headers: HeaderMap,
+    pub(crate) body: Bytes,
+}
+
+/// What the cache has for a request
+pub(crate) enum CacheLookup {
+    /// nothing, the request is sent as it is
+    Miss,
+    /// a response that can be delivered without asking the server, along with the request revalidating it in
+    /// the background if it is stale
+    Hit(CachedResponse, Option<Box<reqwest::Request>>),
+    /// a stale response, the request asks the server whether it is still valid. It is delivered if the server
+    /// cannot be reached, unless it has to be revalidated
+    Revalidate {
+        response: CachedResponse,
+        stale_if_error: bool,
+    },
+    /// a response on disk, which is read before the request is sent
+    #[cfg(not(target_family = "wasm"))]
+    Load(DiskLoad),
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    response: CachedResponse,
+    // the request headers named by Vary, a request has to have the same ones to use the response
+    vary: Vec<(HeaderName, Option<HeaderValue>)>,
+    // the real time of the app when the response was received, and how old it already was at that time
+    stored_at: Duration,
+    initial_age: Duration,
+    freshness: Duration,
+    stale_while_revalidate: Duration,
+    must_revalidate: bool,
+    size: usize,
+    // in millis of the real time of the app, updated through a shared reference when the response is used
+    last_used: AtomicU64,
+}
+
+impl CacheEntry {
+    fn new(response: CachedResponse, request_headers: &HeaderMap, now: Duration) -> Self {
+        let directives = CacheControl::parse(&response.headers);
+        let vary = vary(&response.headers)
+            .filter_map(|name| HeaderName::from_bytes(name.as_bytes()).ok())
+            .map(|name| {
+                let value = request_headers.get(&name).cloned();
+                (name, value)
+            })
+            .collect();
+        let freshness = if directives.no_cache {
+            Duration::ZERO
+        } else {
+            directives
+                .max_age
+                .or_else(|| expires(&response.headers))
+                .unwrap_or_default()
+        };
+        let initial_age = response
+            .headers
+            .get(AGE)
+            .and_then(|age| age.to_str().ok()?.trim().parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or_default();
+        let size = response.body.len()
+            + response
+                .headers
+                .iter()
+                .map(|(name, value)| name.as_str().len() + value.len())
+                .sum::<usize>();
+        Self {
+            vary,
+            stored_at: now,
+            initial_age,
+            freshness,
+            stale_while_revalidate: directives.stale_while_revalidate.unwrap_or_default(),
+            must_revalidate: directives.must_revalidate,
+            size,
+            last_used: AtomicU64::new(now.as_millis() as u64),
+            response,
+        }
+    }
+
+    fn matches(&self, request_headers: &HeaderMap) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| request_headers.get(name) == value.as_ref())
+    }
+
+    fn age(&self, now: Duration) -> Duration {
+        self.initial_age + now.saturating_sub(self.stored_at)
+    }
+
+    fn touch(&self, now: Duration) {
+        self.last_used
+            .store(now.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// the headers asking the server whether the response is still valid
+    fn validators(&self) -> Option<HeaderMap> {
+        let mut validators = HeaderMap::new();
+        if let Some(etag) = self.response.headers.get(ETAG) {
+            validators.insert(IF_NONE_MATCH, etag.clone());
+        }
+        if let Some(last_modified) = self.response.headers.get(LAST_MODIFIED) {
+            validators.insert(IF_MODIFIED_SINCE, last_modified.clone());
+        }
+        Some(validators).filter(|validators| !validators.is_empty())
+    }
+}
+
+/// The directives of a `Cache-Control` header that the cache follows
+#[derive(Default)]
+struct CacheControl {
+    no_store: bool,
+    no_cache: bool,
+    must_revalidate: bool,
+    max_age: Option<Duration>,
+    stale_while_revalidate: Option<Duration>,
+}
+
+impl CacheControl {
+    fn parse(headers: &HeaderMap) -> Self {
+        let mut directives = Self::default();
+        let values = headers
+            .get_all(CACHE_CONTROL)
+            .iter()
+            .filter_map(|value| value.to_str().ok());
+        for directive in values.flat_map(|value| value.split(',')) {
+            let (name, value) = directive.split_once('=').unwrap_or((directive, ""));
+            let seconds = value
+                .trim()
+                .trim_matches('"')
+                .parse()
+                .ok()
+                .map(Duration::from_secs);
+            match name.trim().to_ascii_lowercase().as_str() {
+                "no-store" => directives.no_store = true,
+                // also with a list of headers, which is taken as applying to the whole response
+                "no-cache" => directives.no_cache = true,
+                "must-revalidate" => directives.must_revalidate = true,
+                "max-age" => directives.max_age = seconds,
+                "stale-while-revalidate" => directives.stale_while_revalidate = seconds,
+                _ => {}
+            }
+        }
+        directives
+    }
+}
+
+/// the `Cache-Control` of a request, and whether it asks for the stored response to be revalidated
+fn request_directives(headers: &HeaderMap) -> (CacheControl, bool) {
+    let directives = CacheControl::parse(headers);
+    let no_cache = directives.no_cache
+        || headers
+            .get(PRAGMA)
+            .is_some_and(|pragma| pragma.as_bytes().eq_ignore_ascii_case(b"no-cache"));
+    (directives, no_cache)
+}
+
+fn key(method: &Method, url: &str) -> String {
+    format!("{method} {url}")
+}
+
+fn is_cacheable_method(method: &Method) -> bool {
+    method == Method::GET || method == Method::HEAD
+}
+
+/// the status codes that can be stored without knowing what they mean to the application
+fn is_cacheable_status(status: StatusCode) -> bool {
+    matches!(
+        status.as_u16(),
+        200 | 203 | 204 | 300 | 301 | 308 | 404 | 405 | 410 | 414 | 501
+    )
+}
+
+/// the headers of a stored response that a `304 Not Modified` does not replace
+fn is_body_header(name: &HeaderName) -> bool {
+    use reqwest::header::{CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_RANGE, TRANSFER_ENCODING};
+    [
+        CONTENT_LENGTH,
+        CONTENT_ENCODING,
+        CONTENT_RANGE,
+        TRANSFER_ENCODING,
+    ]
+    .contains(name)
+}
+
+/// the names in the `Vary` headers of a response
+fn vary(headers: &HeaderMap) -> impl Iterator<Item = String> + '_ {
+    headers
+        .get_all(VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|name| name.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+}
+
+/// the freshness given by the `Expires` header, relative to the `Date` header of the response
+fn expires(headers: &HeaderMap) -> Option<Duration> {
+    // there is no system clock to compare the dates to on wasm
+    #[cfg(not(target_family = "wasm"))]
+    {
+        let date = |name| httpdate::parse_http_date(headers.get(name)?.to_str().ok()?).ok();
+        let expires = date(reqwest::header::EXPIRES)?;
+        let now = date(reqwest::header::DATE).unwrap_or_else(std::time::SystemTime::now);
+        Some(expires.duration_since(now).unwrap_or_default())
+    }
+    #[cfg(target_family = "wasm")]
+    {
+        let _ = headers;
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "https://example.com/data";
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(
+                HeaderName::from_static(name),
+                HeaderValue::from_str(value).unwrap(),
+            );
+        }
+        headers
+    }
+
+    fn request(method: Method, request_headers: &[(&'static str, &str)]) -> reqwest::Request {
+        let mut request = reqwest::Request::new(method, URL.parse().unwrap());
+        *request.headers_mut() = headers(request_headers);
+        request
+    }
+
+    /// a cache holding the response to a `GET` request with `request_headers`, received at `now`
+    fn cache_with(
+        request_headers: &[(&'static str, &str)],
+        response_headers: &[(&'static str, &str)],
+        now: Duration,
+    ) -> ReqwestCache {
+        let mut cache = ReqwestCache::new(true, 1 << 20);
+        cache.set_now(now);
+        cache.store(
+            Some(&request(Method::GET, request_headers)),
+            StatusCode::OK,
+            &headers(response_headers),
+            Some(&Bytes::from_static(b"body")),
+        );
+        cache
+    }
+
+    fn at(cache: &mut ReqwestCache, secs: u64) -> &mut ReqwestCache {
+        cache.set_now(Duration::from_secs(secs));
+        cache
+    }
+
+    #[test]
+    fn parses_cache_control() {
+        let directives = CacheControl::parse(&headers(&[
+            ("cache-control", "Max-Age=\"60\", must-revalidate"),
+            (
+                "cache-control",
+                "no-cache=\"Set-Cookie\", stale-while-revalidate=30, private",
+            ),
+        ]));
+        assert_eq!(directives.max_age, Some(Duration::from_secs(60)));
+        assert_eq!(
+            directives.stale_while_revalidate,
+            Some(Duration::from_secs(30))
+        );
+        assert!(directives.must_revalidate);
+        assert!(directives.no_cache);
+        assert!(!directives.no_store);
+
+        let directives = CacheControl::parse(&headers(&[("cache-control", "no-store, max-age=x")]));
+        assert!(directives.no_store);
+        assert_eq!(directives.max_age, None);
+    }
+
+    #[test]
+    fn fresh_responses_are_hits_until_they_are_stale() {
+        let mut cache = cache_with(
+            &[],
+            &[("cache-control", "max-age=60"), ("etag", "\"v1\"")],
+            Duration::ZERO,
+        );
+        let mut fresh = request(Method::GET, &[]);
+        assert!(matches!(
+            at(&mut cache, 59).lookup(&mut fresh),
+            CacheLookup::Hit(_, None)
+        ));
+        assert!(!fresh.headers().contains_key(IF_NONE_MATCH));
+
+        let mut stale = request(Method::GET, &[]);
+        let lookup = at(&mut cache, 60).lookup(&mut stale);
+        assert!(matches!(
+            lookup,
+            CacheLookup::Revalidate {
+                stale_if_error: true,
+                ..
+            }
+        ));
+        assert_eq!(stale.headers()[IF_NONE_MATCH], "\"v1\"");
+        // a HEAD request has its own entry
+        assert!(matches!(
+            cache.lookup(&mut request(Method::HEAD, &[])),
+            CacheLookup::Miss
+        ));
+    }
+
+    #[test]
+    fn age_counts_against_freshness() {
+        let mut cache = cache_with(
+            &[],
+            &[
+                ("cache-control", "max-age=60"),
+                ("age", "50"),
+                ("etag", "\"v1\""),
+            ],
+            Duration::ZERO,
+        );
+        let lookup = at(&mut cache, 10).lookup(&mut request(Method::GET, &[]));
+        assert!(matches!(lookup, CacheLookup::Revalidate { .. }));
+    }
+
+    #[test]
+    fn expires_is_relative_to_date() {
+        let mut cache = cache_with(
+            &[],
+            &[
+                ("date", "Sun, 06 Nov 1994 08:49:37 GMT"),
+                ("expires", "Sun, 06 Nov 1994 08:50:37 GMT"),
+            ],
+            Duration::ZERO,
+        );
+        assert!(matches!(
+            at(&mut cache, 30).lookup(&mut request(Method::GET, &[])),
+            CacheLookup::Hit(_, None)
+        ));
+        // without validators the whole response is asked for again
+        assert!(matches!(
+            at(&mut cache, 60).lookup(&mut request(Method::GET, &[])),
+            CacheLookup::Revalidate { .. }
+        ));
+    }
+
+    #[test]
+    fn request_directives() {
+        let mut cache = cache_with(&[], &[("cache-control", "max-age=60")], Duration::ZERO);
+        let mut no_cache = request(Method::GET, &[("cache-control", "no-cache")]);
+        assert!(matches!(
+            cache.lookup(&mut no_cache),
+            CacheLookup::Revalidate { .. }
+        ));
+        let mut pragma = request(Method::GET, &[("pragma", "no-cache")]);
+        assert!(matches!(
+            cache.lookup(&mut pragma),
+            CacheLookup::Revalidate { .. }
+        ));
+        let mut max_age = request(Method::GET, &[("cache-control", "max-age=5")]);
+        assert!(matches!(
+            at(&mut cache, 10).lookup(&mut max_age),
+            CacheLookup::Revalidate { .. }
+        ));
+        let mut no_store = request(Method::GET, &[("cache-control", "no-store")]);
+        assert!(matches!(cache.lookup(&mut no_store), CacheLookup::Miss));
+        let mut conditional = request(Method::GET, &[("if-none-match", "\"v0\"")]);
+        assert!(matches!(cache.lookup(&mut conditional), CacheLookup::Miss));
+    }
+
+    #[test]
+    fn responses_that_are_not_stored() {
+        for response_headers in [
+            &[("cache-control", "max-age=60, no-store")][..],
+            &[("cache-control", "max-age=60"), ("vary", "*")],
+            // nothing to revalidate it with, and never fresh
+            &[("cache-control", "no-cache")],
+            &[],
+        ] {
+            let cache = cache_with(&[], response_headers, Duration::ZERO);
+            assert!(cache.is_empty(), "{response_headers:?} was stored");
+        }
+        let cache = cache_with(
+            &[("cache-control", "no-store")],
+            &[("cache-control", "max-age=60")],
+            Duration::ZERO,
+        );
+        assert!(cache.is_empty());
+
+        let mut cache = ReqwestCache::new(true, 1 << 20);
+        cache.store(
+            Some(&request(Method::GET, &[])),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &headers(&[("cache-control", "max-age=60")]),
+            Some(&Bytes::new()),
+        );
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn vary_keeps_a_response_per_variant() {
+        let response_headers = [("cache-control", "max-age=60"), ("vary", "Accept-Language")];
+        let mut cache = cache_with(
+            &[("accept-language", "en")],
+            &response_headers,
+            Duration::ZERO,
+        );
+        cache.store(
+            Some(&request(Method::GET, &[("accept-language", "fr")])),
+            StatusCode::OK,
+            &headers(&response_headers),
+            Some(&Bytes::from_static(b"bonjour")),
+        );
+        assert_eq!(cache.len(), 2);
+
+        let body = |cache: &ReqwestCache, language| match cache
+            .lookup(&mut request(Method::GET, &[("accept-language", language)]))
+        {
+            CacheLookup::Hit(response, _) => Some(response.body),
+            _ => None,
+        };
+        assert_eq!(body(&cache, "en").unwrap(), "body");
+        assert_eq!(body(&cache, "fr").unwrap(), "bonjour");
+        assert_eq!(body(&cache, "de"), None);
+        // a request without the header is its own variant
+        assert!(matches!(
+            cache.lookup(&mut request(Method::GET, &[])),
+            CacheLookup::Miss
+        ));
+    }
+
+    #[test]
+    fn stale_while_revalidate() {
+        let mut cache = cache_with(
+            &[],
+            &[
+                ("cache-control", "max-age=10, stale-while-revalidate=20"),
+                ("last-modified", "Sun, 06 Nov 1994 08:49:37 GMT"),
+            ],
+            Duration::ZERO,
+        );
+        let mut caller = request(Method::GET, &[]);
+        let CacheLookup::Hit(_, Some(revalidate)) = at(&mut cache, 25).lookup(&mut caller) else {
+            panic!("a response within stale-while-revalidate is delivered right away");
+        };
+        assert!(revalidate.headers().contains_key(IF_MODIFIED_SINCE));
+        assert!(!caller.headers().contains_key(IF_MODIFIED_SINCE));
+        assert!(matches!(
+            at(&mut cache, 30).lookup(&mut request(Method::GET, &[])),
+            CacheLookup::Revalidate { .. }
+        ));
+    }
+
+    #[test]
+    fn must_revalidate_is_not_delivered_when_offline() {
+        let mut cache = cache_with(
+            &[],
+            &[
+                (
+                    "cache-control",
+                    "max-age=10, must-revalidate, stale-while-revalidate=20",
+                ),
+                ("etag", "\"v1\""),
+            ],
+            Duration::ZERO,
+        );
+        let lookup = at(&mut cache, 15).lookup(&mut request(Method::GET, &[]));
+        assert!(matches!(
+            lookup,
+            CacheLookup::Revalidate {
+                stale_if_error: false,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn not_modified_updates_the_headers() {
+        let mut cache = cache_with(
+            &[],
+            &[
+                ("cache-control", "max-age=10"),
+                ("etag", "\"v1\""),
+                ("content-length", "4"),
+            ],
+            Duration::ZERO,
+        );
+        let mut stale = request(Method::GET, &[]);
+        let CacheLookup::Revalidate { response, .. } = at(&mut cache, 20).lookup(&mut stale) else {
+            panic!("the response is stale");
+        };
+        let not_modified = headers(&[("cache-control", "max-age=100"), ("content-length", "0")]);
+        let response = cache.revalidated(Some(&stale), response, &not_modified);
+        assert_eq!(response.headers[CACHE_CONTROL], "max-age=100");
+        assert_eq!(response.headers["content-length"], "4");
+        assert_eq!(response.body, "body");
+        assert!(matches!(
+            at(&mut cache, 100).lookup(&mut request(Method::GET, &[])),
+            CacheLookup::Hit(_, None)
+        ));
+    }
+
+    #[test]
+    fn unsafe_requests_invalidate() {
+        let mut cache = cache_with(&[], &[("cache-control", "max-age=60")], Duration::ZERO);
+        let post = request(Method::POST, &[]);
+        cache.store(
+            Some(&post),
+            StatusCode::BAD_REQUEST,
+            &HeaderMap::new(),
+            None,
+        );
+        assert_eq!(cache.len(), 1);
+        cache.store(Some(&post), StatusCode::CREATED, &HeaderMap::new(), None);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn drops_the_least_recently_used_responses() {
+        let response_headers = headers(&[("cache-control", "max-age=60")]);
+        let entry_size = CacheEntry::new(
+            CachedResponse {
+                status: StatusCode::OK,
+                headers: response_headers.clone(),
+                body: Bytes::from_static(b"body"),
+            },
+            &HeaderMap::new(),
+            Duration::ZERO,
+        )
+        .size;
+        let mut cache = ReqwestCache::new(true, entry_size * 2);
+        let store = |cache: &mut ReqwestCache, url: &str| {
+            let request = reqwest::Request::new(Method::GET, url.parse().unwrap());
+            cache.store(
+                Some(&request),
+                StatusCode::OK,
+                &response_headers,
+                Some(&Bytes::from_static(b"body")),
+            );
+        };
+        store(at(&mut cache, 0), "https://example.com/a");
+        store(at(&mut cache, 1), "https://example.com/b");
+        // using a makes b the least recently used
+        let mut a = reqwest::Request::new(Method::GET, "https://example.com/a".parse().unwrap());
+        at(&mut cache, 2).lookup(&mut a);
+        store(at(&mut cache, 3), "https://example.com/c");
+        assert_eq!(cache.size(), entry_size * 2);
+        assert!(cache.contains("https://example.com/a"));
+        assert!(!cache.contains("https://example.com/b"));
+        assert!(cache.contains("https://example.com/c"));
+        assert_eq!(cache.stats().hits, 1);
+    }
+}
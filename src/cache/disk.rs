@@ -0,0 +1,645 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_channel::{unbounded, Sender};
+use bevy::{prelude::*, tasks::IoTaskPool};
+use bytes::Bytes;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    StatusCode,
+};
+
+use super::{CacheEntry, CachedResponse};
+
+/// the first line of every file, changed whenever the format changes so old files are dropped
+const VERSION: &[u8] = b"bevy_mod_reqwest cache 1";
+const EXTENSION: &str = "entry";
+
+/// The responses of the cache in a directory, one file for every variant of a method and url.
+///
+/// Only the index is kept in memory, the files are read on the [`IoTaskPool`] by the requests that need them. Writes and deletes happen
+/// in order on the [`IoTaskPool`], and a file is written next to its final name before it is renamed, so it is
+/// never read half written
+#[derive(Debug)]
+pub(crate) struct DiskCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    index: HashMap<String, Vec<DiskEntry>>,
+    size: u64,
+    // started on the first write, the task pools are not around yet when the plugin is built
+    ops: OnceLock<Sender<DiskOp>>,
+}
+
+#[derive(Debug)]
+struct DiskEntry {
+    file: PathBuf,
+    vary: Vec<(HeaderName, Option<HeaderValue>)>,
+    size: u64,
+    // in millis since the unix epoch, updated through a shared reference when the response is used
+    last_used: AtomicU64,
+}
+
+/// The changes to the directory, made in the order they were sent
+enum DiskOp {
+    Write(PathBuf, Vec<u8>),
+    Remove(PathBuf),
+    Touch(PathBuf),
+    Clear,
+}
+
+impl DiskCache {
+    /// reads the index of the responses in `dir`, creating it if needed
+    pub(crate) fn open(dir: &Path, max_bytes: u64) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let mut cache = Self {
+            dir: dir.to_owned(),
+            max_bytes,
+            index: HashMap::new(),
+            size: 0,
+            ops: OnceLock::new(),
+        };
+        for file in fs::read_dir(dir)? {
+            let path = file?.path();
+            if path.extension().is_some_and(|ext| ext == EXTENSION) {
+                match read_index_entry(&path) {
+                    Ok((key, entry)) => {
+                        cache.size += entry.size;
+                        cache.index.entry(key).or_default().push(entry);
+                    }
+                    Err(e) => {
+                        debug!("dropping unreadable cache file {}: {e}", path.display());
+                        fs::remove_file(&path).ok();
+                    }
+                }
+            } else if is_temporary(&path) {
+                // left behind by a write that was interrupted
+                fs::remove_file(&path).ok();
+            }
+        }
+        cache.evict();
+        Ok(cache)
+    }
+
+    /// the number of responses on disk
+    pub(crate) fn len(&self) -> usize {
+        self.index.values().map(Vec::len).sum()
+    }
+
+    /// the size of the files in bytes
+    pub(crate) fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub(crate) fn contains(&self, key: &str) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// finds the variant of `key` that matches the request headers, the file itself is read by
+    /// [`DiskLoad::read`] away from the main thread
+    pub(crate) fn find(
+        &self,
+        key: &str,
+        request_headers: &HeaderMap,
+        now: Duration,
+    ) -> Option<DiskLoad> {
+        let entry = self.index.get(key)?.iter().find(|entry| {
+            entry
+                .vary
+                .iter()
+                .all(|(name, value)| request_headers.get(name) == value.as_ref())
+        })?;
+        entry
+            .last_used
+            .store(unix_millis(SystemTime::now()), Ordering::Relaxed);
+        self.send(DiskOp::Touch(entry.file.clone()));
+        Some(DiskLoad {
+            key: key.to_owned(),
+            file: entry.file.clone(),
+            now,
+        })
+    }
+
+    /// writes `entry` in place of the variant it matches, and deletes the least recently used responses until
+    /// the directory fits again
+    pub(crate) fn store(&mut self, key: &str, entry: &CacheEntry) {
+        let file = self.dir.join(file_name(key, &entry.vary));
+        let bytes = encode(key, entry);
+        let size = bytes.len() as u64;
+
+        let variants = self.index.entry(key.to_owned()).or_default();
+        if let Some(i) = variants.iter().position(|old| old.vary == entry.vary) {
+            self.size -= variants.swap_remove(i).size;
+        }
+        if size > self.max_bytes {
+            if variants.is_empty() {
+                self.index.remove(key);
+            }
+            self.send(DiskOp::Remove(file));
+            return;
+        }
+        variants.push(DiskEntry {
+            file: file.clone(),
+            vary: entry.vary.clone(),
+            size,
+            last_used: AtomicU64::new(unix_millis(SystemTime::now())),
+        });
+        self.size += size;
+        self.send(DiskOp::Write(file, bytes));
+        self.evict();
+    }
+
+    /// deletes every variant of `key`
+    pub(crate) fn remove(&mut self, key: &str) {
+        for entry in self.index.remove(key).unwrap_or_default() {
+            self.size -= entry.size;
+            self.send(DiskOp::Remove(entry.file));
+        }
+    }
+
+    /// deletes every response
+    pub(crate) fn clear(&mut self) {
+        self.index.clear();
+        self.size = 0;
+        self.send(DiskOp::Clear);
+    }
+
+    fn evict(&mut self) {
+        while self.size > self.max_bytes {
+            let Some((key, i)) =
+                self.index
+                    .iter()
+                    .flat_map(|(key, variants)| {
+                        variants.iter().enumerate().map(move |(i, entry)| {
+                            (entry.last_used.load(Ordering::Relaxed), key, i)
+                        })
+                    })
+                    .min()
+                    .map(|(_, key, i)| (key.clone(), i))
+            else {
+                break;
+            };
+            let variants = self.index.get_mut(&key).unwrap();
+            let entry = variants.swap_remove(i);
+            if variants.is_empty() {
+                self.index.remove(&key);
+            }
+            self.size -= entry.size;
+            self.send(DiskOp::Remove(entry.file));
+        }
+    }
+
+    fn send(&self, op: DiskOp) {
+        let ops = self.ops.get_or_init(|| {
+            let (tx, rx) = unbounded::<DiskOp>();
+            let dir = self.dir.clone();
+            IoTaskPool::get()
+                .spawn(async move {
+                    while let Ok(op) = rx.recv().await {
+                        if let Err(e) = op.run(&dir) {
+                            warn!(
+                                "could not update the cache directory {}: {e}",
+                                dir.display()
+                            );
+                        }
+                    }
+                })
+                .detach();
+            tx
+        });
+        ops.try_send(op).ok();
+    }
+}
+
+/// A response on disk that a request is waiting for, see [`DiskCache::find`]
+pub(crate) struct DiskLoad {
+    key: String,
+    file: PathBuf,
+    // the real time of the app when it was looked up
+    now: Duration,
+}
+
+impl DiskLoad {
+    /// reads the response, with its age updated by the time that passed since it was written
+    pub(crate) fn read(self) -> Option<LoadedEntry> {
+        let (head, body) = match read_file(&self.file) {
+            Ok(file) => file,
+            Err(e) => {
+                debug!("could not read cache file {}: {e}", self.file.display());
+                return None;
+            }
+        };
+        // a different key with the same hash, which is never going to happen
+        if head.key != self.key {
+            return None;
+        }
+        let mut vary_headers = HeaderMap::new();
+        for (name, value) in head.vary {
+            if let Some(value) = value {
+                vary_headers.insert(name, value);
+            }
+        }
+        let response = CachedResponse {
+            status: head.status,
+            headers: head.headers,
+            body,
+        };
+        let mut entry = CacheEntry::new(response, &vary_headers, self.now);
+        let elapsed = SystemTime::now()
+            .duration_since(head.written)
+            .unwrap_or_default();
+        entry.initial_age = head.age + elapsed;
+        Some(LoadedEntry {
+            key: self.key,
+            entry,
+        })
+    }
+}
+
+/// A response read from disk, for [`ReqwestCache::lookup_loaded`](super::ReqwestCache::lookup_loaded)
+pub(crate) struct LoadedEntry {
+    pub(super) key: String,
+    pub(super) entry: CacheEntry,
+}
+
+impl DiskOp {
+    fn run(self, dir: &Path) -> io::Result<()> {
+        match self {
+            DiskOp::Write(path, bytes) => {
+                let temporary = path.with_extension(format!("tmp{}", fastrand::u32(..)));
+                let written =
+                    fs::write(&temporary, bytes).and_then(|_| fs::rename(&temporary, &path));
+                if written.is_err() {
+                    fs::remove_file(&temporary).ok();
+                }
+                written
+            }
+            DiskOp::Remove(path) => match fs::remove_file(path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            },
+            // the modification time is the last use when the index is read again
+            DiskOp::Touch(path) => File::options()
+                .write(true)
+                .open(path)?
+                .set_modified(SystemTime::now()),
+            DiskOp::Clear => {
+                for file in fs::read_dir(dir)? {
+                    let path = file?.path();
+                    if path.extension().is_some_and(|ext| ext == EXTENSION) || is_temporary(&path) {
+                        fs::remove_file(path)?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// What comes before the body in a file
+struct Head {
+    key: String,
+    status: StatusCode,
+    written: SystemTime,
+    // the age of the response when it was written
+    age: Duration,
+    vary: Vec<(HeaderName, Option<HeaderValue>)>,
+    headers: HeaderMap,
+}
+
+/// the file of a variant, named by a hash of the key and the request headers it varies on
+fn file_name(key: &str, vary: &[(HeaderName, Option<HeaderValue>)]) -> String {
+    let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+    context.update(key.as_bytes());
+    for (name, value) in vary {
+        context.update(b"\n");
+        context.update(name.as_str().as_bytes());
+        if let Some(value) = value {
+            context.update(b":");
+            context.update(value.as_bytes());
+        }
+    }
+    let hash: String = context.finish().as_ref()[..16]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    format!("{hash}.{EXTENSION}")
+}
+
+fn is_temporary(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.starts_with("tmp"))
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// the lines of the head, the counts of the vary and response headers tell where it ends and the body starts.
+/// Header values cannot contain line breaks, so they are written as they are
+fn encode(key: &str, entry: &CacheEntry) -> Vec<u8> {
+    let response = &entry.response;
+    let mut bytes = Vec::with_capacity(entry.size + 256);
+    let mut line = |parts: &[&[u8]]| {
+        for part in parts {
+            bytes.extend_from_slice(part);
+        }
+        bytes.push(b'\n');
+    };
+    line(&[VERSION]);
+    line(&[key.as_bytes()]);
+    line(&[response.status.as_str().as_bytes()]);
+    line(&[unix_millis(SystemTime::now()).to_string().as_bytes()]);
+    line(&[(entry.initial_age.as_millis() as u64)
+        .to_string()
+        .as_bytes()]);
+    line(&[entry.vary.len().to_string().as_bytes()]);
+    for (name, value) in &entry.vary {
+        match value {
+            Some(value) => line(&[name.as_str().as_bytes(), b":", value.as_bytes()]),
+            None => line(&[name.as_str().as_bytes()]),
+        }
+    }
+    line(&[response.headers.len().to_string().as_bytes()]);
+    for (name, value) in &response.headers {
+        line(&[name.as_str().as_bytes(), b":", value.as_bytes()]);
+    }
+    bytes.extend_from_slice(&response.body);
+    bytes
+}
+
+fn read_index_entry(path: &Path) -> io::Result<(String, DiskEntry)> {
+    let file = File::open(path)?;
+    let metadata = file.metadata()?;
+    let head = read_head(&mut BufReader::new(file))?;
+    let last_used = metadata.modified().unwrap_or(head.written);
+    let entry = DiskEntry {
+        file: path.to_owned(),
+        vary: head.vary,
+        size: metadata.len(),
+        last_used: AtomicU64::new(unix_millis(last_used)),
+    };
+    Ok((head.key, entry))
+}
+
+fn read_file(path: &Path) -> io::Result<(Head, Bytes)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let head = read_head(&mut reader)?;
+    let mut body = Vec::new();
+    reader.read_to_end(&mut body)?;
+    Ok((head, body.into()))
+}
+
+fn read_head(reader: &mut impl BufRead) -> io::Result<Head> {
+    if read_line(reader)? != VERSION {
+        return Err(invalid());
+    }
+    let key = String::from_utf8(read_line(reader)?).map_err(|_| invalid())?;
+    let status = StatusCode::from_u16(read_number(reader)? as u16).map_err(|_| invalid())?;
+    let written = UNIX_EPOCH + Duration::from_millis(read_number(reader)?);
+    let age = Duration::from_millis(read_number(reader)?);
+    let mut vary = Vec::new();
+    for _ in 0..read_number(reader)? {
+        vary.push(read_header(reader)?);
+    }
+    let mut headers = HeaderMap::new();
+    for _ in 0..read_number(reader)? {
+        let (name, value) = read_header(reader)?;
+        headers.append(name, value.ok_or_else(invalid)?);
+    }
+    Ok(Head {
+        key,
+        status,
+        written,
+        age,
+        vary,
+        headers,
+    })
+}
+
+fn invalid() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "not a cached response")
+}
+
+fn read_line(reader: &mut impl BufRead) -> io::Result<Vec<u8>> {
+    let mut line = Vec::new();
+    reader.read_until(b'\n', &mut line)?;
+    if line.pop() != Some(b'\n') {
+        return Err(invalid());
+    }
+    Ok(line)
+}
+
+fn read_number(reader: &mut impl BufRead) -> io::Result<u64> {
+    let line = read_line(reader)?;
+    std::str::from_utf8(&line)
+        .ok()
+        .and_then(|line| line.parse().ok())
+        .ok_or_else(invalid)
+}
+
+/// a header line, the value is missing for a header the request did not have
+fn read_header(reader: &mut impl BufRead) -> io::Result<(HeaderName, Option<HeaderValue>)> {
+    let line = read_line(reader)?;
+    let (name, value) = match line.iter().position(|&byte| byte == b':') {
+        Some(colon) => (&line[..colon], Some(&line[colon + 1..])),
+        None => (&line[..], None),
+    };
+    let name = HeaderName::from_bytes(name).map_err(|_| invalid())?;
+    let value = value
+        .map(HeaderValue::from_bytes)
+        .transpose()
+        .map_err(|_| invalid())?;
+    Ok((name, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::tasks::TaskPool;
+
+    use super::*;
+
+    /// an empty directory that is deleted again when dropped
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new() -> Self {
+            // the cache writes and deletes files on the task pool
+            IoTaskPool::get_or_init(TaskPool::new);
+            let dir = std::env::temp_dir().join(format!("bevy_mod_reqwest_{}", fastrand::u64(..)));
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            fs::remove_dir_all(&self.0).ok();
+        }
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(
+                HeaderName::from_static(name),
+                HeaderValue::from_str(value).unwrap(),
+            );
+        }
+        headers
+    }
+
+    fn entry(
+        response_headers: &[(&'static str, &str)],
+        request_headers: &[(&'static str, &str)],
+    ) -> CacheEntry {
+        let response = CachedResponse {
+            status: StatusCode::NOT_FOUND,
+            headers: headers(response_headers),
+            body: Bytes::from_static(b"line one\nline two\n"),
+        };
+        CacheEntry::new(response, &headers(request_headers), Duration::ZERO)
+    }
+
+    /// writes `entry` the way the cache does, and returns the size of the file
+    fn write(dir: &Path, key: &str, entry: &CacheEntry, last_used: SystemTime) -> u64 {
+        let path = dir.join(file_name(key, &entry.vary));
+        fs::write(&path, encode(key, entry)).unwrap();
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(last_used)
+            .unwrap();
+        fs::metadata(path).unwrap().len()
+    }
+
+    #[test]
+    fn encodes_and_reads_back() {
+        let dir = TestDir::new();
+        let mut entry = entry(
+            &[
+                ("vary", "Accept-Language, X-Missing"),
+                ("set-cookie", "a=1"),
+                ("set-cookie", "b=2"),
+                ("x-empty", ""),
+            ],
+            &[("accept-language", "en")],
+        );
+        entry.initial_age = Duration::from_secs(42);
+        let path = dir.0.join("response.entry");
+        fs::write(&path, encode("GET https://example.com/", &entry)).unwrap();
+
+        let (head, body) = read_file(&path).unwrap();
+        assert_eq!(head.key, "GET https://example.com/");
+        assert_eq!(head.status, StatusCode::NOT_FOUND);
+        assert_eq!(head.age, Duration::from_secs(42));
+        assert_eq!(head.vary, entry.vary);
+        assert_eq!(head.headers, entry.response.headers);
+        assert_eq!(body, entry.response.body);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let bytes = encode("GET https://example.com/", &entry(&[], &[]));
+        let mut other_version = b"bevy_mod_reqwest cache 0".to_vec();
+        other_version.extend_from_slice(&bytes[VERSION.len()..]);
+        assert!(read_head(&mut &other_version[..]).is_err());
+        // cut off in the middle of the head
+        let cut = bytes.len() - b"line one\nline two\n".len() - 5;
+        assert!(read_head(&mut &bytes[..cut]).is_err());
+        assert!(read_head(&mut &b"not a cached response"[..]).is_err());
+    }
+
+    #[test]
+    fn finds_and_reads_variants() {
+        let dir = TestDir::new();
+        let key = "GET https://example.com/greeting";
+        let english = entry(&[("vary", "accept-language")], &[("accept-language", "en")]);
+        let any = entry(&[("vary", "accept-language")], &[]);
+        write(&dir.0, key, &english, SystemTime::now());
+        write(&dir.0, key, &any, SystemTime::now());
+        fs::write(dir.0.join("broken.entry"), b"garbage").unwrap();
+        fs::write(dir.0.join("interrupted.tmp123"), b"half").unwrap();
+
+        let cache = DiskCache::open(&dir.0, u64::MAX).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert!(!dir.0.join("broken.entry").exists());
+        assert!(!dir.0.join("interrupted.tmp123").exists());
+
+        let request = headers(&[("accept-language", "en")]);
+        let loaded = cache
+            .find(key, &request, Duration::ZERO)
+            .unwrap()
+            .read()
+            .unwrap();
+        assert_eq!(loaded.key, key);
+        assert_eq!(loaded.entry.vary, english.vary);
+        assert_eq!(loaded.entry.response.body, english.response.body);
+        assert!(cache.find(key, &HeaderMap::new(), Duration::ZERO).is_some());
+        assert!(cache
+            .find(key, &headers(&[("accept-language", "fr")]), Duration::ZERO)
+            .is_none());
+        assert!(cache
+            .find("GET https://example.com/other", &request, Duration::ZERO)
+            .is_none());
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_when_opened() {
+        let dir = TestDir::new();
+        let now = SystemTime::now();
+        let entry = entry(&[], &[]);
+        let mut size = 0;
+        for (i, key) in ["GET a", "GET b", "GET c"].into_iter().enumerate() {
+            size = write(
+                &dir.0,
+                key,
+                &entry,
+                now - Duration::from_secs(10 - i as u64),
+            );
+        }
+        let cache = DiskCache::open(&dir.0, size * 2).unwrap();
+        assert_eq!(cache.size(), size * 2);
+        assert!(!cache.contains("GET a"));
+        assert!(cache.contains("GET b"));
+        assert!(cache.contains("GET c"));
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_when_storing() {
+        let dir = TestDir::new();
+        let entry = entry(&[], &[]);
+        let size = encode("GET a", &entry).len() as u64;
+        let mut cache = DiskCache::open(&dir.0, size * 2).unwrap();
+        cache.store("GET a", &entry);
+        std::thread::sleep(Duration::from_millis(2));
+        cache.store("GET b", &entry);
+        std::thread::sleep(Duration::from_millis(2));
+        // using a makes b the least recently used
+        cache.find("GET a", &HeaderMap::new(), Duration::ZERO);
+        std::thread::sleep(Duration::from_millis(2));
+        cache.store("GET c", &entry);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.size(), size * 2);
+        assert!(cache.contains("GET a"));
+        assert!(!cache.contains("GET b"));
+
+        // replacing a variant does not count it twice
+        cache.store("GET c", &entry);
+        assert_eq!(cache.size(), size * 2);
+        cache.remove("GET c");
+        assert_eq!(cache.size(), size);
+    }
+}
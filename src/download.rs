@@ -0,0 +1,224 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use async_channel::Sender;
+use bevy::prelude::*;
+use futures_lite::StreamExt;
+use reqwest::{
+    header::{HeaderMap, HeaderValue, CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE},
+    StatusCode,
+};
+
+use crate::{Parts, RequestTask, TaskMessage, Transport};
+
+#[derive(Clone, EntityEvent, Debug)]
+/// triggered when a request sent with [`BevyReqwest::download`](crate::BevyReqwest::download) has written the
+/// whole body to its file
+pub struct ReqwestDownloadEvent {
+    pub entity: Entity,
+    /// the file the body was written to
+    pub path: PathBuf,
+    /// the size of the file, including any part that was downloaded by an earlier attempt
+    pub size: u64,
+    /// the `StatusCode` of the response
+    pub status: StatusCode,
+    /// the headers of the response
+    pub headers: HeaderMap,
+}
+
+#[derive(EntityEvent, Debug)]
+/// triggered when a request sent with [`BevyReqwest::download`](crate::BevyReqwest::download) could not write
+/// the body to its file
+pub struct ReqwestDownloadErrorEvent {
+    pub entity: Entity,
+    pub error: io::Error,
+}
+
+/// the file the body is written to until it is complete
+fn part_path(path: &Path) -> PathBuf {
+    let mut part = path.as_os_str().to_owned();
+    part.push(".part");
+    part.into()
+}
+
+/// the file next to the part file, holding the `ETag` or `Last-Modified` of the response it came from
+fn validator_path(path: &Path) -> PathBuf {
+    let mut validator = path.as_os_str().to_owned();
+    validator.push(".part.validator");
+    validator.into()
+}
+
+/// the length of the part file and the validator to resume it with, if it can be resumed
+fn resumable(path: &Path) -> Option<(u64, HeaderValue)> {
+    let len = fs::metadata(part_path(path)).ok()?.len();
+    let validator = fs::read(validator_path(path)).ok()?;
+    let validator = HeaderValue::from_bytes(&validator).ok()?;
+    (len > 0).then_some((len, validator))
+}
+
+fn remove_partial(path: &Path) {
+    fs::remove_file(part_path(path)).ok();
+    fs::remove_file(validator_path(path)).ok();
+}
+
+/// sends the request and writes a successful response to `path`, continuing an earlier partial download if
+/// the server allows it
+pub(crate) async fn run(
+    transport: Transport,
+    request: reqwest::Request,
+    path: PathBuf,
+    tx: &Sender<TaskMessage>,
+) {
+    let mut resume = resumable(&path);
+    // a copy in case the partial file turns out to be useless
+    let mut restart = request.try_clone();
+    let mut request = Some(request);
+    let res = loop {
+        let Some(mut attempt) = request.take() else {
+            return;
+        };
+        if let Some((offset, validator)) = &resume {
+            let headers = attempt.headers_mut();
+            if let Ok(range) = HeaderValue::from_str(&format!("bytes={offset}-")) {
+                headers.insert(RANGE, range);
+                headers.insert(IF_RANGE, validator.clone());
+            }
+        }
+        let res = match transport.execute(attempt).await {
+            Ok(res) => res,
+            Err(e) => {
+                tx.try_send(TaskMessage::Failed(e)).ok();
+                return;
+            }
+        };
+        // the part file is bigger than the remote file, or the server sent another part of the file than the
+        // one that was asked for, start over
+        let useless = match &resume {
+            Some(_) if res.status() == StatusCode::RANGE_NOT_SATISFIABLE => true,
+            Some((offset, _)) if res.status() == StatusCode::PARTIAL_CONTENT => {
+                content_range_start(res.headers()) != Some(*offset)
+            }
+            _ => false,
+        };
+        if useless {
+            remove_partial(&path);
+            resume = None;
+            request = restart.take();
+            if request.is_some() {
+                continue;
+            }
+        }
+        break res;
+    };
+
+    let status = res.status();
+    if !status.is_success() {
+        RequestTask::read_response(res, tx).await;
+        return;
+    }
+    let headers = res.headers().clone();
+    // only a partial response continues the part file, with a 200 the server sent the whole file
+    let offset = match resume {
+        Some((offset, _)) if status == StatusCode::PARTIAL_CONTENT => offset,
+        _ => 0,
+    };
+    // a part that could not be asked for again, or that was never asked for, cannot be written to the file
+    if status == StatusCode::PARTIAL_CONTENT && content_range_start(&headers) != Some(offset) {
+        let error = io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the server sent another part of the file than the one that was asked for",
+        );
+        tx.try_send(TaskMessage::FileError(error)).ok();
+        return;
+    }
+    let file = match open_part_file(&path, offset, &headers) {
+        Ok(file) => file,
+        Err(e) => {
+            tx.try_send(TaskMessage::FileError(e)).ok();
+            return;
+        }
+    };
+    tx.try_send(TaskMessage::Head(Parts { status, headers }))
+        .ok();
+    if offset > 0 {
+        tx.try_send(TaskMessage::Transferred(offset)).ok();
+    }
+
+    let mut file = io::BufWriter::new(file);
+    let mut size = offset;
+    let mut body = res.bytes_stream();
+    while let Some(chunk) = body.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                // keep what was written, the next attempt continues from there
+                file.flush().ok();
+                tx.try_send(TaskMessage::Failed(e)).ok();
+                return;
+            }
+        };
+        if let Err(e) = file.write_all(&chunk) {
+            tx.try_send(TaskMessage::FileError(e)).ok();
+            return;
+        }
+        size += chunk.len() as u64;
+        // stop downloading if nobody is listening anymore
+        if tx
+            .try_send(TaskMessage::Transferred(chunk.len() as u64))
+            .is_err()
+        {
+            file.flush().ok();
+            return;
+        }
+    }
+
+    let message = match finish(file, &path) {
+        Ok(()) => TaskMessage::Downloaded(path, size),
+        Err(e) => TaskMessage::FileError(e),
+    };
+    tx.try_send(message).ok();
+}
+
+/// the first byte of a partial response, from its `Content-Range` header such as `bytes 100-199/1000`
+fn content_range_start(headers: &HeaderMap) -> Option<u64> {
+    let range = headers.get(CONTENT_RANGE)?.to_str().ok()?;
+    let (start, _) = range.trim().strip_prefix("bytes ")?.split_once('-')?;
+    start.trim().parse().ok()
+}
+
+/// opens the part file to continue at `offset`, or truncates it and remembers the validator of the response
+/// to be able to continue it later
+fn open_part_file(path: &Path, offset: u64, headers: &HeaderMap) -> io::Result<File> {
+    if offset > 0 {
+        return OpenOptions::new().append(true).open(part_path(path));
+    }
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let file = File::create(part_path(path))?;
+    // a weak etag does not guarantee the bytes are the same
+    let validator = headers
+        .get(ETAG)
+        .filter(|etag| !etag.as_bytes().starts_with(b"W/"))
+        .or_else(|| headers.get(LAST_MODIFIED));
+    match validator {
+        Some(validator) => fs::write(validator_path(path), validator.as_bytes())?,
+        None => {
+            fs::remove_file(validator_path(path)).ok();
+        }
+    }
+    Ok(file)
+}
+
+/// moves the complete part file in place of the target file
+fn finish(file: io::BufWriter<File>, path: &Path) -> io::Result<()> {
+    let file = file.into_inner().map_err(io::IntoInnerError::into_error)?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(part_path(path), path)?;
+    fs::remove_file(validator_path(path)).ok();
+    Ok(())
+}
@@ -0,0 +1,297 @@
+use std::{fmt, marker::PhantomData, time::Duration};
+
+use bevy::{ecs::system::IntoObserverSystem, prelude::*};
+use bytes::Bytes;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    BevyReqwest, BevyReqwestBuilder, RequestPriority, ReqwestCancelledEvent, ReqwestErrorEvent,
+    ReqwestErrorKind, ReqwestResponseEvent, ReqwestTimeoutEvent, RetryPolicy, StatusCode,
+};
+
+/// A typed API endpoint: the request it sends, and what its responses are deserialized into. Usually
+/// implemented with the `HttpEndpoint` derive of the `derive` feature, and sent with [`BevyReqwest::call`].
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// use bevy::prelude::*;
+/// use bevy_mod_reqwest::*;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// #[derive(HttpEndpoint)]
+/// #[endpoint(method = "GET", path = "/users/{id}", response = User)]
+/// struct GetUser {
+///     id: u64,
+///     // every field that is not part of the path is a query parameter, unless it is marked otherwise
+///     include_posts: Option<bool>,
+/// }
+///
+/// fn get_user(mut client: BevyReqwest) -> Result {
+///     client
+///         .call(GetUser { id: 7, include_posts: None })?
+///         .on_ok(|trigger: On<EndpointOk<User>>| {
+///             info!("user 7 is called {}", trigger.event().data.name);
+///         })
+///         .on_err(|trigger: On<EndpointErr<serde_json::Value>>| {
+///             warn!("could not get user 7: {}", trigger.event().error);
+///         });
+///     Ok(())
+/// }
+/// # }
+/// ```
+///
+/// The derive takes these attributes:
+///
+/// - on the struct, `#[endpoint(method = "POST", path = "/users/{id}/posts", response = Post, error = ApiError)]`.
+///   The method defaults to `GET`, the response to `()` and the error to [`serde_json::Value`]. A `{field}` in
+///   the path is replaced with the percent encoded value of that field, and a path that is not a full url is
+///   relative to [`ReqwestPlugin::base_url`](crate::ReqwestPlugin::base_url)
+/// - on a field, `#[endpoint(query)]` or `#[endpoint(query = "pageSize")]` to send it as a query parameter,
+///   which is what fields outside the path are by default. Parameters that are `None` are left out
+/// - `#[endpoint(body)]` sends the field as the json body, `#[endpoint(body = "form")]` as a form
+/// - `#[endpoint(header = "X-Request-Id")]` sends the field as a header
+/// - `#[endpoint(skip)]` leaves the field out of the request
+pub trait HttpEndpoint {
+    /// what the body of a successful response is deserialized into
+    type Response: DeserializeOwned + Send + Sync + 'static;
+    /// what the body of an unsuccessful response is deserialized into
+    type Error: DeserializeOwned + Send + Sync + 'static;
+
+    /// the request to the endpoint, with a path that is relative to `base_url` unless it is a full url
+    fn request(&self, client: &reqwest::Client, base_url: Option<&str>) -> reqwest::RequestBuilder;
+
+    /// deserializes the body of a successful response, as json by default
+    fn parse_response(body: &[u8]) -> serde_json::Result<Self::Response> {
+        serde_json::from_slice(body)
+    }
+}
+
+#[derive(EntityEvent, Debug)]
+/// triggered with the deserialized body of a successful response to a [`HttpEndpoint`]
+pub struct EndpointOk<T> {
+    pub entity: Entity,
+    /// the `StatusCode` of the response
+    pub status: StatusCode,
+    pub data: T,
+}
+
+#[derive(EntityEvent, Debug)]
+/// triggered when a request to a [`HttpEndpoint`] did not succeed
+pub struct EndpointErr<E> {
+    pub entity: Entity,
+    pub error: EndpointError<E>,
+}
+
+/// Why a request to a [`HttpEndpoint`] did not succeed
+#[derive(Debug)]
+pub enum EndpointError<E> {
+    /// the server responded with an unsuccessful status
+    Status {
+        status: StatusCode,
+        /// the body of the response, if it could be deserialized into the error of the endpoint
+        error: Option<E>,
+        /// the body of the response as it was received
+        body: Bytes,
+    },
+    /// the body of a successful response could not be deserialized
+    Deserialize(serde_json::Error),
+    /// the request failed without a response, with the kind and message of the [`ReqwestErrorEvent`]
+    Request {
+        kind: ReqwestErrorKind,
+        message: String,
+    },
+    /// the request did not finish within its [`ReqwestTimeout`](crate::ReqwestTimeout)
+    Timeout(Duration),
+    /// the request was aborted before it finished, see [`ReqwestCancelledEvent`]
+    Cancelled,
+}
+
+impl<E> fmt::Display for EndpointError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Status { status, .. } => write!(f, "the server responded with {status}"),
+            Self::Deserialize(e) => write!(f, "the response could not be deserialized: {e}"),
+            Self::Request { message, .. } => write!(f, "the request failed: {message}"),
+            Self::Timeout(timeout) => write!(f, "the request timed out after {timeout:?}"),
+            Self::Cancelled => write!(f, "the request was cancelled"),
+        }
+    }
+}
+
+impl<E: fmt::Debug> std::error::Error for EndpointError<E> {}
+
+impl<'w, 's> BevyReqwest<'w, 's> {
+    /// Sends the request of the [`HttpEndpoint`], then use the [`EndpointBuilder`] to add handlers for its
+    /// deserialized responses and errors. Fails if the request could not be built, such as when its url is
+    /// not valid
+    pub fn call<E: HttpEndpoint>(
+        &mut self,
+        endpoint: E,
+    ) -> Result<EndpointBuilder<'_, E>, reqwest::Error> {
+        let request = endpoint
+            .request(&self.client, self.defaults.base_url.as_deref())
+            .build()?;
+        // unsuccessful statuses are turned into an EndpointErr by the builder
+        let builder = self.send(request).error_for_status(false);
+        Ok(EndpointBuilder::new(builder))
+    }
+}
+
+/// Wrapper around the [`BevyReqwestBuilder`] of a request to a [`HttpEndpoint`], to add handlers for its
+/// deserialized responses and errors
+pub struct EndpointBuilder<'a, E> {
+    builder: BevyReqwestBuilder<'a>,
+    endpoint: PhantomData<fn() -> E>,
+}
+
+impl<'a, E: HttpEndpoint> EndpointBuilder<'a, E> {
+    fn new(mut builder: BevyReqwestBuilder<'a>) -> Self {
+        builder
+            .0
+            .observe(
+                |trigger: On<ReqwestResponseEvent>, mut commands: Commands| {
+                    let response = trigger.event();
+                    let entity = response.entity;
+                    let status = response.status();
+                    if !status.is_success() {
+                        let error = EndpointError::Status {
+                            status,
+                            error: serde_json::from_slice(response.body()).ok(),
+                            body: response.body().clone(),
+                        };
+                        commands.trigger(EndpointErr::<E::Error> { entity, error });
+                        return;
+                    }
+                    match E::parse_response(response.body()) {
+                        Ok(data) => commands.trigger(EndpointOk {
+                            entity,
+                            status,
+                            data,
+                        }),
+                        Err(e) => commands.trigger(EndpointErr::<E::Error> {
+                            entity,
+                            error: EndpointError::Deserialize(e),
+                        }),
+                    }
+                },
+            )
+            .observe(|trigger: On<ReqwestErrorEvent>, mut commands: Commands| {
+                commands.trigger(EndpointErr::<E::Error> {
+                    entity: trigger.event().entity,
+                    error: EndpointError::Request {
+                        kind: trigger.event().kind,
+                        message: trigger.event().error.to_string(),
+                    },
+                });
+            })
+            .observe(|trigger: On<ReqwestTimeoutEvent>, mut commands: Commands| {
+                commands.trigger(EndpointErr::<E::Error> {
+                    entity: trigger.event().entity,
+                    error: EndpointError::Timeout(trigger.event().timeout),
+                });
+            })
+            .observe(
+                |trigger: On<ReqwestCancelledEvent>, mut commands: Commands| {
+                    commands.trigger(EndpointErr::<E::Error> {
+                        entity: trigger.event().entity,
+                        error: EndpointError::Cancelled,
+                    });
+                },
+            );
+        Self {
+            builder,
+            endpoint: PhantomData,
+        }
+    }
+
+    /// Provide a system where the first argument is [`On`] [`EndpointOk`] that will run with the deserialized
+    /// body of a successful response
+    pub fn on_ok<OB: Bundle, OM, OO: IntoObserverSystem<EndpointOk<E::Response>, OB, OM>>(
+        mut self,
+        onok: OO,
+    ) -> Self {
+        self.builder.0.observe(onok);
+        self
+    }
+
+    /// Provide a system where the first argument is [`On`] [`EndpointErr`] that will run if the server
+    /// responded with an unsuccessful status, the response could not be deserialized, or the request failed,
+    /// timed out or was cancelled
+    pub fn on_err<EB: Bundle, EM, OE: IntoObserverSystem<EndpointErr<E::Error>, EB, EM>>(
+        mut self,
+        onerr: OE,
+    ) -> Self {
+        self.builder.0.observe(onerr);
+        self
+    }
+
+    /// see [`BevyReqwestBuilder::timeout`]
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.map(|builder| builder.timeout(timeout))
+    }
+
+    /// see [`BevyReqwestBuilder::retry`]
+    pub fn retry(self, policy: RetryPolicy) -> Self {
+        self.map(|builder| builder.retry(policy))
+    }
+
+    /// see [`BevyReqwestBuilder::priority`]
+    pub fn priority(self, priority: RequestPriority) -> Self {
+        self.map(|builder| builder.priority(priority))
+    }
+
+    /// the [`BevyReqwestBuilder`] of the request, for the handlers of the events that are not typed
+    pub fn into_builder(self) -> BevyReqwestBuilder<'a> {
+        self.builder
+    }
+
+    fn map(self, f: impl FnOnce(BevyReqwestBuilder<'a>) -> BevyReqwestBuilder<'a>) -> Self {
+        Self {
+            builder: f(self.builder),
+            endpoint: PhantomData,
+        }
+    }
+}
+
+/// What the code generated by the `HttpEndpoint` derive calls, not part of the public api
+#[doc(hidden)]
+pub mod __private {
+    use std::fmt;
+
+    /// the url of an endpoint, `path` is relative to `base_url` unless it is a full url
+    pub fn endpoint_url(base_url: Option<&str>, path: &str) -> String {
+        match base_url {
+            Some(base_url) if !path.contains("://") => {
+                let base_url = base_url.trim_end_matches('/');
+                match path.starts_with('/') {
+                    true => format!("{base_url}{path}"),
+                    false => format!("{base_url}/{path}"),
+                }
+            }
+            // a relative path without a base url fails to build
+            _ => path.to_owned(),
+        }
+    }
+
+    /// percent encodes a value for a segment of the path, everything but the unreserved characters is encoded
+    pub fn encode_path_segment(value: impl fmt::Display) -> String {
+        let value = value.to_string();
+        let mut encoded = String::with_capacity(value.len());
+        for byte in value.bytes() {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    encoded.push(byte as char)
+                }
+                _ => encoded.push_str(&format!("%{byte:02X}")),
+            }
+        }
+        encoded
+    }
+}
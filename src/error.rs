@@ -0,0 +1,218 @@
+use std::{error::Error, fmt, io};
+
+/// What went wrong in a request that failed without a response, attached to the
+/// [`ReqwestErrorEvent`](crate::ReqwestErrorEvent) so handlers do not have to take the [`reqwest::Error`] apart
+///
+/// # Examples
+///
+/// ```
+/// use bevy::prelude::*;
+/// use bevy_mod_reqwest::*;
+///
+/// fn on_error(trigger: On<ReqwestErrorEvent>) {
+///     let message = match trigger.event().kind {
+///         kind if kind.is_offline() => "You are offline",
+///         ReqwestErrorKind::Timeout => "The server took too long to answer",
+///         _ => "Something went wrong",
+///     };
+///     warn!("{message}: {}", trigger.event().error);
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReqwestErrorKind {
+    /// the request did not finish in time
+    Timeout,
+    /// the name of the host could not be resolved
+    Dns,
+    /// no connection could be made to the server
+    Connect,
+    /// the tls handshake failed, such as for a certificate that is not trusted
+    Tls,
+    /// the redirects could not be followed, because there were too many or they looped
+    Redirect,
+    /// the request body could not be sent, or the response body could not be received
+    Body,
+    /// the response body could not be decoded
+    Decode,
+    /// the request could not be built, such as for an invalid url
+    Builder,
+    /// the request failed in any other way
+    Request,
+}
+
+impl ReqwestErrorKind {
+    /// the kind of `error`, taken from the error itself and the errors that caused it.
+    ///
+    /// On wasm the browser does not say why a fetch failed, so there is no [`Dns`](Self::Dns),
+    /// [`Connect`](Self::Connect) or [`Tls`](Self::Tls) there, those errors are a [`Request`](Self::Request)
+    pub fn of(error: &reqwest::Error) -> Self {
+        if error.is_timeout() {
+            return Self::Timeout;
+        }
+        if error.is_builder() {
+            return Self::Builder;
+        }
+        if error.is_redirect() {
+            return Self::Redirect;
+        }
+        #[cfg(not(target_family = "wasm"))]
+        if error.is_connect() {
+            return Self::of_connect(error.source());
+        }
+        // reqwest reports a body that breaks off as a decode error
+        if is_broken_body(error) {
+            return Self::Body;
+        }
+        if error.is_decode() {
+            return Self::Decode;
+        }
+        if error.is_body() {
+            return Self::Body;
+        }
+        Self::Request
+    }
+
+    /// the kind of a connect error, from the first of its causes that tells what failed
+    #[cfg(not(target_family = "wasm"))]
+    fn of_connect(source: Option<&(dyn Error + 'static)>) -> Self {
+        source
+            .into_iter()
+            .flat_map(causes)
+            .find_map(Self::of_connect_cause)
+            .unwrap_or(Self::Connect)
+    }
+
+    /// the kind of a connect error, if `cause` tells what failed
+    #[cfg(not(target_family = "wasm"))]
+    fn of_connect_cause(cause: &(dyn Error + 'static)) -> Option<Self> {
+        #[cfg(feature = "rustls-tls")]
+        if cause.is::<rustls::Error>() {
+            return Some(Self::Tls);
+        }
+        #[cfg(feature = "default-tls")]
+        if cause.is::<native_tls::Error>() {
+            return Some(Self::Tls);
+        }
+        if let Some(error) = cause.downcast_ref::<io::Error>() {
+            match error.kind() {
+                io::ErrorKind::ConnectionRefused
+                | io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::NotConnected
+                | io::ErrorKind::AddrNotAvailable
+                | io::ErrorKind::HostUnreachable
+                | io::ErrorKind::NetworkUnreachable
+                | io::ErrorKind::NetworkDown => return Some(Self::Connect),
+                io::ErrorKind::TimedOut => return Some(Self::Timeout),
+                _ => {}
+            }
+        }
+        // hyper-util does not export the error of its connector, it only tells a failed lookup by its message
+        cause.to_string().contains("dns error").then_some(Self::Dns)
+    }
+
+    /// true if the server could not be reached at all, which usually means the device is offline. Never true on
+    /// wasm, see [`ReqwestErrorKind::of`]
+    pub fn is_offline(&self) -> bool {
+        matches!(self, Self::Dns | Self::Connect)
+    }
+}
+
+/// true if the response broke off while its body was received, which reqwest reports as a decode error
+pub(crate) fn is_broken_body(error: &reqwest::Error) -> bool {
+    error.is_decode()
+        && error
+            .source()
+            .into_iter()
+            .flat_map(causes)
+            .any(|cause| cause.is::<io::Error>())
+}
+
+/// `error` and the errors that caused it, including the ones wrapped by an io::Error, which are not its source
+fn causes<'a>(error: &'a (dyn Error + 'static)) -> impl Iterator<Item = &'a (dyn Error + 'static)> {
+    std::iter::successors(Some(error), |&cause| {
+        match cause
+            .downcast_ref::<io::Error>()
+            .and_then(io::Error::get_ref)
+        {
+            Some(inner) => Some(inner as &(dyn Error + 'static)),
+            None => cause.source(),
+        }
+    })
+}
+
+impl fmt::Display for ReqwestErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            Self::Timeout => "timed out",
+            Self::Dns => "the host could not be resolved",
+            Self::Connect => "could not connect",
+            Self::Tls => "the tls handshake failed",
+            Self::Redirect => "the redirects could not be followed",
+            Self::Body => "the body could not be transferred",
+            Self::Decode => "the response could not be decoded",
+            Self::Builder => "the request could not be built",
+            Self::Request => "the request failed",
+        };
+        f.write_str(message)
+    }
+}
+
+#[cfg(all(test, not(target_family = "wasm")))]
+mod tests {
+    use super::*;
+
+    /// an error like the ones of the connectors, with a message and a cause
+    #[derive(Debug)]
+    struct ConnectError(&'static str, io::Error);
+
+    impl fmt::Display for ConnectError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(self.0)
+        }
+    }
+
+    impl Error for ConnectError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.1)
+        }
+    }
+
+    fn of_connect(error: impl Error + 'static) -> ReqwestErrorKind {
+        ReqwestErrorKind::of_connect(Some(&error))
+    }
+
+    #[test]
+    fn connect_errors_by_io_kind() {
+        let refused = ConnectError("tcp connect error", io::ErrorKind::ConnectionRefused.into());
+        assert_eq!(of_connect(refused), ReqwestErrorKind::Connect);
+        let unreachable = io::Error::from(io::ErrorKind::NetworkUnreachable);
+        assert_eq!(of_connect(unreachable), ReqwestErrorKind::Connect);
+        let timed_out = io::Error::from(io::ErrorKind::TimedOut);
+        assert_eq!(of_connect(timed_out), ReqwestErrorKind::Timeout);
+        // nothing that tells what failed
+        assert_eq!(
+            of_connect(io::Error::other("oops")),
+            ReqwestErrorKind::Connect
+        );
+    }
+
+    #[test]
+    fn dns_errors_by_message() {
+        let lookup = io::Error::other("failed to lookup address information");
+        let kind = of_connect(ConnectError("dns error", lookup));
+        assert_eq!(kind, ReqwestErrorKind::Dns);
+        assert!(kind.is_offline());
+    }
+
+    #[cfg(feature = "rustls-tls")]
+    #[test]
+    fn rustls_errors_inside_io_errors() {
+        // how tokio-rustls and hyper-util wrap a failed handshake
+        let rustls = rustls::Error::InvalidCertificate(rustls::CertificateError::Expired);
+        let error = io::Error::other(io::Error::new(io::ErrorKind::InvalidData, rustls));
+        let kind = of_connect(error);
+        assert_eq!(kind, ReqwestErrorKind::Tls);
+        assert!(!kind.is_offline());
+    }
+}
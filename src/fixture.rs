@@ -0,0 +1,553 @@
+use std::{
+    fs,
+    future::Future,
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bevy::prelude::*;
+use bytes::Bytes;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, CONTENT_LENGTH, TRANSFER_ENCODING},
+    Method, ResponseBuilderExt,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{MockError, MockResponse, MockRoute, ReqwestDefaults, ReqwestMock};
+
+/// The headers left out of recordings by default, as they usually hold secrets
+pub const DEFAULT_REDACTED_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "x-api-key",
+];
+
+/// What a redacted header is replaced with in the fixture file
+const REDACTED: &str = "REDACTED";
+
+/// Records the requests sent through [`BevyReqwest`](crate::BevyReqwest) and their responses to a fixture file,
+/// or replays such a file instead of using the network, to turn a real session into a test that runs
+/// offline.
+///
+/// Replaying answers the requests with a [`ReqwestMock`], so the [`ReqwestMockPlugin`](crate::ReqwestMockPlugin)
+/// can be used alongside it to add routes of its own. When the same request was recorded more than once, the
+/// recordings are replayed in the order they were recorded, and the last one keeps answering after that.
+///
+/// Recording sends the requests over the network, or to the [`ReqwestMock`] if the
+/// [`ReqwestMockPlugin`](crate::ReqwestMockPlugin) is added as well. The streams of an
+/// [`SseSubscription`](crate::SseSubscription) are not recorded, since they are read for as long as they last.
+///
+/// The plugin has to be added after the [`ReqwestPlugin`](crate::ReqwestPlugin).
+///
+/// # Examples
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use bevy_mod_reqwest::*;
+///
+/// let fixture = "tests/fixtures/login.json";
+/// let plugin = match std::env::var("RECORD_FIXTURES") {
+///     Ok(_) => ReqwestFixturePlugin::record(fixture),
+///     Err(_) => ReqwestFixturePlugin::replay(fixture),
+/// };
+/// App::new()
+///     .add_plugins((MinimalPlugins, ReqwestPlugin::default(), plugin))
+///     .run();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReqwestFixturePlugin {
+    /// the fixture file
+    pub path: PathBuf,
+    pub mode: FixtureMode,
+    /// how the requests are matched to the recorded ones when replaying
+    pub matching: FixtureMatching,
+    /// the headers whose values are left out of the recordings, of the requests as well as the responses
+    pub redact_headers: Vec<String>,
+}
+
+/// Whether a [`ReqwestFixturePlugin`] writes or reads its fixture file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixtureMode {
+    /// sends the requests, and replaces the fixture file with them and their responses
+    Record,
+    /// answers the requests with the recorded responses
+    Replay,
+}
+
+/// Which parts of a request have to be the same as a recorded one for its response to be replayed. The
+/// method and the url always have to be
+#[derive(Debug, Clone, Default)]
+pub struct FixtureMatching {
+    /// the body of the request
+    pub body: bool,
+    /// these headers of the request, except the redacted ones
+    pub headers: Vec<String>,
+    /// the values of these query parameters, such as timestamps or nonces, may differ
+    pub ignore_query: Vec<String>,
+}
+
+impl ReqwestFixturePlugin {
+    /// records the requests to the fixture file at `path`
+    pub fn record(path: impl Into<PathBuf>) -> Self {
+        Self::new(path, FixtureMode::Record)
+    }
+
+    /// replays the requests recorded in the fixture file at `path`
+    pub fn replay(path: impl Into<PathBuf>) -> Self {
+        Self::new(path, FixtureMode::Replay)
+    }
+
+    fn new(path: impl Into<PathBuf>, mode: FixtureMode) -> Self {
+        Self {
+            path: path.into(),
+            mode,
+            matching: FixtureMatching::default(),
+            redact_headers: DEFAULT_REDACTED_HEADERS
+                .iter()
+                .map(|&header| header.to_owned())
+                .collect(),
+        }
+    }
+
+    /// sets how the requests are matched to the recorded ones when replaying
+    pub fn matching(mut self, matching: FixtureMatching) -> Self {
+        self.matching = matching;
+        self
+    }
+
+    /// leaves the value of this header out of the recordings as well
+    pub fn redact(mut self, header: impl Into<String>) -> Self {
+        self.redact_headers.push(header.into());
+        self
+    }
+}
+
+impl Plugin for ReqwestFixturePlugin {
+    fn build(&self, app: &mut App) {
+        if !app.world().contains_resource::<ReqwestDefaults>() {
+            warn!("ReqwestFixturePlugin has to be added after the ReqwestPlugin");
+            return;
+        }
+        match self.mode {
+            FixtureMode::Record => {
+                let recorder = ReqwestRecorder {
+                    recording: Arc::new(Recording {
+                        path: self.path.clone(),
+                        exchanges: Default::default(),
+                        unsaved: AtomicBool::new(false),
+                    }),
+                    redact: self.redact_headers.clone(),
+                };
+                // a new recording replaces the old one, even if no requests are sent
+                if let Err(e) = recorder.save() {
+                    warn!("could not write the fixture {}: {e}", self.path.display());
+                }
+                app.world_mut().resource_mut::<ReqwestDefaults>().recorder = Some(recorder.clone());
+                app.insert_resource(recorder)
+                    .add_systems(Last, save_recording_on_exit);
+            }
+            FixtureMode::Replay => {
+                let mock = match app.world().get_resource::<ReqwestMock>() {
+                    Some(mock) => mock.clone(),
+                    None => {
+                        let mock = ReqwestMock::default();
+                        app.world_mut().resource_mut::<ReqwestDefaults>().mock = Some(mock.clone());
+                        app.insert_resource(mock.clone());
+                        mock
+                    }
+                };
+                match load(&self.path) {
+                    Ok(exchanges) => self.replay_routes(&mock, &exchanges),
+                    Err(e) => warn!("could not read the fixture {}: {e}", self.path.display()),
+                }
+            }
+        }
+    }
+}
+
+impl ReqwestFixturePlugin {
+    /// adds a route answering once for every recording, followed by the routes that keep answering with the
+    /// last recording of a request
+    fn replay_routes(&self, mock: &ReqwestMock, exchanges: &[Exchange]) {
+        let routes: Vec<(MockRoute, MockResponse)> = exchanges
+            .iter()
+            .filter_map(|exchange| {
+                let route = self.route(&exchange.request)?;
+                Some((route, exchange.response()?))
+            })
+            .collect();
+        for (route, response) in &routes {
+            mock.route(route.clone().times(1), response.clone());
+        }
+        for (route, response) in routes.into_iter().rev() {
+            mock.route(route, response);
+        }
+    }
+
+    /// the route matching the recorded request, according to the [`FixtureMatching`]
+    fn route(&self, request: &RecordedRequest) -> Option<MockRoute> {
+        let method = Method::from_bytes(request.method.as_bytes()).ok()?;
+        let mut url = reqwest::Url::parse(&request.url).ok()?;
+        let ignored = &self.matching.ignore_query;
+        if url
+            .query_pairs()
+            .any(|(name, _)| ignored.iter().any(|i| *i == name))
+        {
+            // the value of an ignored parameter becomes a wildcard, which survives being encoded in the url
+            let pairs: Vec<(String, String)> = url
+                .query_pairs()
+                .map(|(name, value)| match ignored.iter().any(|i| *i == name) {
+                    true => (name.into_owned(), WILDCARD.to_owned()),
+                    false => (name.into_owned(), value.into_owned()),
+                })
+                .collect();
+            url.query_pairs_mut().clear().extend_pairs(pairs);
+        }
+        let url = url.as_str().replace(WILDCARD, "*");
+        let mut route = MockRoute::new(method, &url);
+        if self.matching.body {
+            route = match request.body.decode() {
+                Some(body) => route.body(body),
+                // a streamed body was not recorded, so it can not be compared
+                None => route,
+            };
+        }
+        for (name, value) in request
+            .headers
+            .iter()
+            .filter_map(|header| split_header(header))
+        {
+            let matched = self
+                .matching
+                .headers
+                .iter()
+                .any(|header| header.eq_ignore_ascii_case(name));
+            if matched && value != REDACTED {
+                route = route.header(name, value);
+            }
+        }
+        Some(route)
+    }
+}
+
+/// stands in for a `*` while the url is encoded
+const WILDCARD: &str = "__bevy_mod_reqwest_wildcard__";
+
+/// Records the requests sent through [`BevyReqwest`](crate::BevyReqwest) and their responses while the
+/// [`ReqwestFixturePlugin`] is recording. The fixture file is written when the app exits, or once the app is
+/// dropped, and whenever [`ReqwestRecorder::save`] is called
+#[derive(Resource, Clone)]
+pub struct ReqwestRecorder {
+    recording: Arc<Recording>,
+    redact: Vec<String>,
+}
+
+/// The exchanges recorded so far, shared by the requests that are being recorded
+struct Recording {
+    path: PathBuf,
+    exchanges: Mutex<Vec<Exchange>>,
+    // set when there are exchanges that are not in the file yet
+    unsaved: AtomicBool,
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        if *self.unsaved.get_mut() {
+            if let Err(e) = write_fixture(&self.path, self.exchanges.get_mut().unwrap()) {
+                warn!("could not write the fixture {}: {e}", self.path.display());
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for ReqwestRecorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReqwestRecorder")
+            .field("path", &self.recording.path)
+            .field("recorded", &self.len())
+            .finish()
+    }
+}
+
+impl ReqwestRecorder {
+    /// the fixture file the requests are recorded to
+    pub fn path(&self) -> &Path {
+        &self.recording.path
+    }
+
+    /// the number of requests recorded so far
+    pub fn len(&self) -> usize {
+        self.recording.exchanges.lock().unwrap().len()
+    }
+
+    /// true if no requests have been recorded yet
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// sends the request with `send` and records it, with its response or error. The body of the response is
+    /// read completely before it is passed on
+    pub(crate) async fn execute<F>(
+        &self,
+        request: reqwest::Request,
+        send: impl FnOnce(reqwest::Request) -> F,
+    ) -> reqwest::Result<reqwest::Response>
+    where
+        F: Future<Output = reqwest::Result<reqwest::Response>>,
+    {
+        let recorded = RecordedRequest {
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            headers: self.headers(request.headers()),
+            body: RecordedBody::new(request.body().and_then(reqwest::Body::as_bytes)),
+        };
+        let res = match send(request).await {
+            Ok(res) => res,
+            Err(e) => {
+                self.record(recorded, Err(&e));
+                return Err(e);
+            }
+        };
+        let (status, version, url) = (res.status(), res.version(), res.url().clone());
+        let headers = res.headers().clone();
+        let body = match res.bytes().await {
+            Ok(body) => body,
+            Err(e) => {
+                self.record(recorded, Err(&e));
+                return Err(e);
+            }
+        };
+        let response = RecordedResponse {
+            status: status.as_u16(),
+            headers: self.headers(&headers),
+            body: RecordedBody::new(Some(&body)),
+        };
+        self.record(recorded, Ok(response));
+
+        let mut rebuilt = http::Response::builder()
+            .status(status)
+            .version(version)
+            .url(url)
+            .body(reqwest::Body::from(body))
+            .expect("the parts of a valid response");
+        *rebuilt.headers_mut() = headers;
+        Ok(rebuilt.into())
+    }
+
+    fn record(
+        &self,
+        request: RecordedRequest,
+        response: Result<RecordedResponse, &reqwest::Error>,
+    ) {
+        let exchange = match response {
+            Ok(response) => Exchange {
+                request,
+                response: Some(response),
+                error: None,
+            },
+            Err(e) => Exchange {
+                request,
+                response: None,
+                error: Some(RecordedError::of(e)),
+            },
+        };
+        debug!(
+            "recording {} {}",
+            exchange.request.method, exchange.request.url
+        );
+        self.recording.exchanges.lock().unwrap().push(exchange);
+        self.recording.unsaved.store(true, Ordering::Relaxed);
+    }
+
+    /// the headers as they are recorded, with the values of the redacted ones left out
+    fn headers(&self, headers: &HeaderMap) -> Vec<String> {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let value = match self
+                    .redact
+                    .iter()
+                    .any(|r| r.eq_ignore_ascii_case(name.as_str()))
+                {
+                    true => REDACTED.to_owned(),
+                    false => String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                };
+                format!("{name}: {value}")
+            })
+            .collect()
+    }
+
+    /// writes the requests recorded so far to the fixture file, which otherwise happens when the app exits
+    pub fn save(&self) -> io::Result<()> {
+        // keep the lock while writing, so the file always ends up with every exchange
+        let exchanges = self.recording.exchanges.lock().unwrap();
+        write_fixture(&self.recording.path, &exchanges)?;
+        self.recording.unsaved.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+fn save_recording_on_exit(mut exits: MessageReader<AppExit>, recorder: Res<ReqwestRecorder>) {
+    if exits.read().next().is_none() {
+        return;
+    }
+    if let Err(e) = recorder.save() {
+        warn!(
+            "could not write the fixture {}: {e}",
+            recorder.path().display()
+        );
+    }
+}
+
+fn write_fixture(path: &Path, exchanges: &[Exchange]) -> io::Result<()> {
+    let fixture = Fixture {
+        exchanges: exchanges.to_vec(),
+    };
+    let json = serde_json::to_vec_pretty(&fixture).map_err(io::Error::other)?;
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, json)?;
+    fs::rename(&tmp, path)
+}
+
+/// the name and value of a header recorded as `name: value`
+fn split_header(header: &str) -> Option<(&str, &str)> {
+    header
+        .split_once(':')
+        .map(|(name, value)| (name.trim(), value.trim()))
+}
+
+fn load(path: &Path) -> io::Result<Vec<Exchange>> {
+    let fixture: Fixture = serde_json::from_slice(&fs::read(path)?).map_err(io::Error::other)?;
+    Ok(fixture.exchanges)
+}
+
+/// The contents of a fixture file
+#[derive(Serialize, Deserialize)]
+struct Fixture {
+    exchanges: Vec<Exchange>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Exchange {
+    request: RecordedRequest,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    response: Option<RecordedResponse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<RecordedError>,
+}
+
+impl Exchange {
+    /// the response or error to replay
+    fn response(&self) -> Option<MockResponse> {
+        if let Some(error) = self.error {
+            return Some(MockResponse::error(match error {
+                RecordedError::Timeout => MockError::Timeout,
+                RecordedError::Connection => MockError::Unreachable,
+                RecordedError::Reset => MockError::ConnectionReset,
+            }));
+        }
+        let recorded = self.response.as_ref()?;
+        let mut response = MockResponse::new(recorded.status);
+        for (name, value) in recorded
+            .headers
+            .iter()
+            .filter_map(|header| split_header(header))
+        {
+            // the body might have been edited by hand, it is sent whole either way
+            let framing = name.eq_ignore_ascii_case(CONTENT_LENGTH.as_str())
+                || name.eq_ignore_ascii_case(TRANSFER_ENCODING.as_str());
+            // a header that does not parse anymore was edited by hand as well, and is better left out
+            let valid = HeaderName::try_from(name).is_ok() && HeaderValue::try_from(value).is_ok();
+            if valid && !framing {
+                response = response.header(name, value);
+            }
+        }
+        Some(response.body(recorded.body.decode().unwrap_or_default()))
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct RecordedRequest {
+    method: String,
+    url: String,
+    #[serde(default)]
+    headers: Vec<String>,
+    #[serde(flatten)]
+    body: RecordedBody,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct RecordedResponse {
+    status: u16,
+    #[serde(default)]
+    headers: Vec<String>,
+    #[serde(flatten)]
+    body: RecordedBody,
+}
+
+/// A body as text when it is, so the fixture can be read and edited, or else in base64
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct RecordedBody {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body_base64: Option<String>,
+}
+
+impl RecordedBody {
+    fn new(body: Option<&[u8]>) -> Self {
+        match body.map(std::str::from_utf8) {
+            Some(Ok(text)) => Self {
+                body: Some(text.to_owned()),
+                body_base64: None,
+            },
+            Some(Err(_)) => Self {
+                body: None,
+                body_base64: body.map(|body| STANDARD.encode(body)),
+            },
+            None => Self::default(),
+        }
+    }
+
+    /// the bytes of the body, `None` if there was none or it was streamed
+    fn decode(&self) -> Option<Bytes> {
+        if let Some(text) = &self.body {
+            return Some(Bytes::from(text.clone()));
+        }
+        let body = STANDARD.decode(self.body_base64.as_ref()?).ok()?;
+        Some(body.into())
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RecordedError {
+    Timeout,
+    /// the connection could not be made
+    Connection,
+    /// the connection broke after it was made, such as while the response was read
+    Reset,
+}
+
+impl RecordedError {
+    fn of(error: &reqwest::Error) -> Self {
+        if error.is_timeout() {
+            return Self::Timeout;
+        }
+        if error.is_connect() {
+            return Self::Connection;
+        }
+        Self::Reset
+    }
+}
@@ -1,24 +1,103 @@
-use std::ops::{Deref, DerefMut};
+use std::{
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+    time::Duration,
+};
 
 use bevy::{
-    ecs::system::{EntityCommands, IntoObserverSystem, SystemParam},
+    ecs::{
+        lifecycle::HookContext,
+        system::{EntityCommands, IntoObserverSystem, SystemParam},
+        world::DeferredWorld,
+    },
+    platform::time::Instant,
     prelude::*,
     tasks::IoTaskPool,
 };
 
 pub use reqwest;
+#[cfg(feature = "json")]
+pub use serde_json;
 
-#[cfg(target_family = "wasm")]
-use crossbeam_channel::{bounded, Receiver};
+use async_channel::{unbounded, Receiver, Sender};
+use bytes::{Bytes, BytesMut};
 
 #[cfg(feature = "json")]
 pub use json::*;
 
+#[cfg(feature = "asset")]
+pub mod asset;
+#[cfg(feature = "asset")]
+pub use asset::*;
+
+pub mod cache;
+pub use cache::*;
+
+#[cfg(not(target_family = "wasm"))]
+pub mod download;
+#[cfg(not(target_family = "wasm"))]
+pub use download::*;
+
+pub mod error;
+pub use error::*;
+
+#[cfg(feature = "json")]
+pub mod endpoint;
+#[cfg(feature = "json")]
+pub use endpoint::*;
+
+#[cfg(feature = "derive")]
+pub use bevy_mod_reqwest_derive::HttpEndpoint;
+
+#[cfg(all(feature = "json", not(target_family = "wasm")))]
+pub mod fixture;
+#[cfg(all(feature = "json", not(target_family = "wasm")))]
+pub use fixture::*;
+
+#[cfg(not(target_family = "wasm"))]
+pub mod mock;
+#[cfg(not(target_family = "wasm"))]
+pub use mock::*;
+
+#[cfg(feature = "msgpack")]
+pub mod msgpack;
+#[cfg(feature = "msgpack")]
+pub use msgpack::*;
+
+pub mod progress;
+pub use progress::*;
+
+pub mod queue;
+pub use queue::*;
+
+pub mod rate_limit;
+pub use rate_limit::*;
+
+pub mod retry;
+pub use retry::*;
+
+pub mod schedule;
+pub use schedule::*;
+
+pub mod sse;
+pub use sse::*;
+
+pub mod status;
+pub use status::*;
+
+pub mod websocket;
+pub use websocket::*;
+
 pub use reqwest::header::HeaderMap;
 pub use reqwest::{StatusCode, Version};
 
+use futures_lite::StreamExt;
+
+#[cfg(target_family = "wasm")]
+use futures_lite::future;
+
 #[cfg(not(target_family = "wasm"))]
-use {bevy::tasks::Task, futures_lite::future};
+use bevy::tasks::Task;
 
 /// The [`SystemSet`] that Reqwest systems are added to.
 #[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
@@ -34,17 +113,101 @@ pub struct ReqwestPlugin {
     /// this enables the plugin to insert a new [`Name`] component onto the entity used to drive
     /// the http request to completion, if no such component already exists
     pub automatically_name_requests: bool,
+    /// the timeout used for requests that did not set one using [`BevyReqwestBuilder::timeout`],
+    /// `None` lets requests run until they finish
+    pub default_timeout: Option<Duration>,
+    /// the [`RetryPolicy`] used for requests that did not set one using [`BevyReqwestBuilder::retry`],
+    /// `None` means failed requests are not retried
+    pub default_retry: Option<RetryPolicy>,
+    /// the shortest time between two [`ReqwestProgressEvent`]s for the same request
+    pub progress_event_interval: Duration,
+    /// the most requests in flight at the same time, the rest wait in a queue with a [`ReqwestQueued`]
+    /// marker until there is room. `None` sends every request right away
+    pub max_inflight: Option<usize>,
+    /// the most requests in flight to the same host at the same time, the rest wait in the queue like for
+    /// [`ReqwestPlugin::max_inflight`]
+    pub max_inflight_per_host: Option<usize>,
+    /// the [`RateLimit`] for requests to a host, by the host of their url. Requests over the limit wait in the
+    /// queue like for [`ReqwestPlugin::max_inflight`]
+    pub rate_limits: HashMap<String, RateLimit>,
+    /// the number of times a request answered with `429 Too Many Requests` or `503 Service Unavailable` and a
+    /// `Retry-After` header is put back in the queue, before the response is delivered. No requests are sent to
+    /// the host until the `Retry-After` has passed, up to [`ReqwestPlugin::rate_limit_max_block`]
+    pub rate_limit_deferrals: u32,
+    /// the longest a `Retry-After` header of a `429 Too Many Requests` or `503 Service Unavailable` response
+    /// keeps requests to its host in the queue, so a server asking for a day does not park them for that long
+    pub rate_limit_max_block: Duration,
+    /// keeps the responses to `GET` and `HEAD` requests in the [`ReqwestCache`], and answers requests from it
+    /// as long as the `Cache-Control` headers allow it. Off by default
+    pub cache: bool,
+    /// the most bytes the [`ReqwestCache`] keeps in memory, the least recently used responses are dropped first
+    pub cache_max_bytes: usize,
+    /// the directory the [`ReqwestCache`] also writes the responses to, so they can be used after a restart
+    /// or while offline. Only used if [`ReqwestPlugin::cache`] is enabled, `None` keeps them in memory only
+    #[cfg(not(target_family = "wasm"))]
+    pub cache_dir: Option<std::path::PathBuf>,
+    /// the most bytes the [`ReqwestCache`] keeps in [`ReqwestPlugin::cache_dir`], the least recently used
+    /// responses are deleted first
+    #[cfg(not(target_family = "wasm"))]
+    pub cache_max_disk_bytes: u64,
+    /// treats responses with a `4xx` or `5xx` status as errors, which trigger a [`ReqwestStatusErrorEvent`]
+    /// with the status, headers and body instead of a [`ReqwestResponseEvent`]. Can be changed for a single
+    /// request with [`BevyReqwestBuilder::error_for_status`]. Off by default
+    pub error_for_status: bool,
+    /// the url the paths of the endpoints sent with `BevyReqwest::call` are relative to, such as
+    /// `https://api.example.com/v1`
+    pub base_url: Option<String>,
 }
 impl Default for ReqwestPlugin {
     fn default() -> Self {
         Self {
             automatically_name_requests: true,
+            default_timeout: None,
+            default_retry: None,
+            progress_event_interval: Duration::from_millis(100),
+            max_inflight: None,
+            max_inflight_per_host: None,
+            rate_limits: HashMap::new(),
+            rate_limit_deferrals: 3,
+            rate_limit_max_block: Duration::from_secs(60),
+            cache: false,
+            cache_max_bytes: 32 * 1024 * 1024,
+            #[cfg(not(target_family = "wasm"))]
+            cache_dir: None,
+            #[cfg(not(target_family = "wasm"))]
+            cache_max_disk_bytes: 256 * 1024 * 1024,
+            error_for_status: false,
+            base_url: None,
         }
     }
 }
 impl Plugin for ReqwestPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<ReqwestClient>();
+        app.insert_resource(ReqwestDefaults {
+            timeout: self.default_timeout,
+            retry: self.default_retry.clone(),
+            progress_event_interval: self.progress_event_interval,
+            max_inflight: self.max_inflight,
+            max_inflight_per_host: self.max_inflight_per_host,
+            rate_limits: self.rate_limits.clone(),
+            rate_limit_deferrals: self.rate_limit_deferrals,
+            rate_limit_max_block: self.rate_limit_max_block,
+            error_for_status: self.error_for_status,
+            base_url: self.base_url.clone(),
+            #[cfg(not(target_family = "wasm"))]
+            mock: None,
+            #[cfg(all(feature = "json", not(target_family = "wasm")))]
+            recorder: None,
+        });
+        app.init_resource::<ReqwestQueue>();
+        app.init_resource::<RateLimitState>();
+        let mut cache = ReqwestCache::new(self.cache, self.cache_max_bytes);
+        #[cfg(not(target_family = "wasm"))]
+        if let Some(dir) = self.cache_dir.as_ref().filter(|_| self.cache) {
+            cache.open_dir(dir, self.cache_max_disk_bytes);
+        }
+        app.insert_resource(cache);
 
         if self.automatically_name_requests {
             // register a hook on the component to add a name to the entity if it doesnt have one already
@@ -57,12 +220,27 @@ impl Plugin for ReqwestPlugin {
                         .url
                         .clone();
 
-                    if let None = world.get::<Name>(ctx.entity) {
+                    if world.get::<Name>(ctx.entity).is_none() {
                         let mut commands = world.commands();
                         let mut entity = commands.get_entity(ctx.entity).unwrap();
                         entity.insert(Name::new(format!("http: {url}")));
                     }
                 });
+            app.world_mut()
+                .register_component_hooks::<WebSocketConnection>()
+                .on_insert(|mut world, ctx| {
+                    let url = world
+                        .get::<WebSocketConnection>(ctx.entity)
+                        .unwrap()
+                        .url
+                        .clone();
+
+                    if world.get::<Name>(ctx.entity).is_none() {
+                        let mut commands = world.commands();
+                        let mut entity = commands.get_entity(ctx.entity).unwrap();
+                        entity.insert(Name::new(format!("ws: {url}")));
+                    }
+                });
         }
         //
         app.add_systems(
@@ -74,19 +252,39 @@ impl Plugin for ReqwestPlugin {
                 // the entity will be removed before the callback is triggered.
                 Self::remove_finished_requests,
                 Self::poll_inflight_requests_to_bytes,
+                Self::report_request_progress,
+                Self::abort_timed_out_requests,
+                Self::send_retried_requests,
+                schedule::send_scheduled_requests,
+                queue::dispatch_queued_requests,
             )
                 .chain()
                 .in_set(ReqwestSet),
         );
+        app.add_systems(
+            PreUpdate,
+            (sse::poll_sse_subscriptions, websocket::poll_websockets).in_set(ReqwestSet),
+        );
+        app.add_observer(status::route_response_by_status);
     }
 }
 
 //TODO: Make type generic, and we can create systems for JSON and TEXT requests
 impl ReqwestPlugin {
-    /// despawns finished reqwests if marked to be despawned and does not contain 'ReqwestInflight' component
+    /// despawns finished reqwests if marked to be despawned and does not contain 'ReqwestInflight',
+    /// 'WebSocketConnection' or 'DelayedRequest' component
+    #[allow(clippy::type_complexity)]
     fn remove_finished_requests(
         mut commands: Commands,
-        q: Query<Entity, (With<DespawnReqwestEntity>, Without<ReqwestInflight>)>,
+        q: Query<
+            Entity,
+            (
+                With<DespawnReqwestEntity>,
+                Without<ReqwestInflight>,
+                Without<WebSocketConnection>,
+                Without<DelayedRequest>,
+            ),
+        >,
     ) {
         for e in q.iter() {
             if let Ok(mut ec) = commands.get_entity(e) {
@@ -96,50 +294,472 @@ impl ReqwestPlugin {
     }
 
     /// Polls any requests in flight to completion, and then removes the 'ReqwestInflight' component.
+    /// Failed attempts are instead scheduled to be sent again if the entity has a [`RetryPolicy`]
+    #[allow(clippy::type_complexity)]
     fn poll_inflight_requests_to_bytes(
         mut commands: Commands,
-        mut requests: Query<(Entity, &mut ReqwestInflight)>,
+        time: Res<Time<Real>>,
+        defaults: Res<ReqwestDefaults>,
+        mut rate_limits: ResMut<RateLimitState>,
+        mut cache: ResMut<ReqwestCache>,
+        mut requests: Query<(
+            Entity,
+            &mut ReqwestInflight,
+            Option<&RetryPolicy>,
+            Option<&mut ReqwestProgress>,
+            Has<StreamReqwestBody>,
+            Option<&ErrorForStatus>,
+        )>,
     ) {
-        for (entity, mut request) in requests.iter_mut() {
+        rate_limits.set_now(time.elapsed());
+        cache.set_now(time.elapsed());
+        for (entity, mut request, policy, mut progress, streaming, error_for_status) in
+            requests.iter_mut()
+        {
             debug!("polling: {entity:?}");
-            if let Some((result, parts)) = request.poll() {
-                match result {
-                    Ok(body) => {
-                        // if the response is ok, the other values are already gotten, its safe to unwrap
-                        let parts = parts.unwrap();
-
-                        commands.trigger(ReqwestResponseEvent::new(
-                            entity,
-                            body.clone(),
+            let error_for_status = error_for_status.map_or(defaults.error_for_status, |e| e.0);
+            while let Some(message) = request.task.as_ref().and_then(RequestTask::try_recv) {
+                match message {
+                    TaskMessage::Head(mut parts) => {
+                        let host = request.host.clone();
+                        let limited = rate_limits.update(
+                            &host,
                             parts.status,
-                            parts.headers,
-                        ));
+                            &parts.headers,
+                            defaults.rate_limit_max_block,
+                        );
+                        // wait in the queue until the host can be sent to again
+                        if let Some(delay) = limited.filter(|_| {
+                            request.deferrals < defaults.rate_limit_deferrals && request.defer()
+                        }) {
+                            debug!("{entity:?} was rate limited by {host}, waiting {delay:?}");
+                            commands.trigger(ReqwestRateLimitedEvent {
+                                entity,
+                                host,
+                                delay,
+                            });
+                            if let Ok(mut ec) = commands.get_entity(entity) {
+                                ec.insert(ReqwestQueued::new());
+                            }
+                            continue;
+                        }
+                        // the server confirmed the stored response, which is delivered in place of the 304
+                        if parts.status == StatusCode::NOT_MODIFIED {
+                            if let Some(response) = request.take_revalidated() {
+                                let response = cache.revalidated(
+                                    request.request.as_ref(),
+                                    response,
+                                    &parts.headers,
+                                );
+                                parts = Parts {
+                                    status: response.status,
+                                    headers: response.headers,
+                                };
+                                request.cached_body = Some(response.body);
+                            }
+                        }
+                        // no need to wait for the body of a response that will be retried anyway
+                        let delay = policy.and_then(|policy| {
+                            policy.status_retry_delay(request.attempt, parts.status, &parts.headers)
+                        });
+                        if let Some(progress) = progress.as_mut() {
+                            if progress.direction() == TransferDirection::Upload {
+                                #[cfg(target_family = "wasm")]
+                                progress.complete();
+                                // the server might answer before the whole body was sent
+                                if progress.take_event() {
+                                    commands.trigger(ReqwestProgressEvent {
+                                        entity,
+                                        progress: progress.clone(),
+                                    });
+                                }
+                            }
+                            progress.start_response(&parts.headers);
+                        }
+                        request.parts = Some(parts);
+                        if let Some(delay) = delay {
+                            Self::retry(&mut commands, entity, &mut request, delay);
+                        }
+                    }
+                    TaskMessage::Chunk(chunk) => {
+                        if let Some(progress) = progress.as_mut() {
+                            progress.add(chunk.len() as u64);
+                        }
+                        if streaming {
+                            request.streamed = true;
+                            commands.trigger(ReqwestChunkEvent { entity, chunk });
+                        } else {
+                            request.body.extend_from_slice(&chunk);
+                        }
+                    }
+                    TaskMessage::Finished => {
+                        request.finish();
+                        // always report the completed transfer, even if it is sooner than the interval
+                        if let Some(progress) = progress.as_mut() {
+                            if progress.take_event() {
+                                commands.trigger(ReqwestProgressEvent {
+                                    entity,
+                                    progress: progress.clone(),
+                                });
+                            }
+                        }
+                        // the head is always sent before the body
+                        let parts = request.parts.take().unwrap();
+                        let from_cache = request.cached_body.is_some();
+                        let body = match request.cached_body.take() {
+                            Some(body) => body,
+                            None if streaming => Bytes::new(),
+                            None => request.body.split().freeze(),
+                        };
+                        if !from_cache {
+                            let body = Some(&body).filter(|_| !streaming);
+                            cache.store(
+                                request.request.as_ref(),
+                                parts.status,
+                                &parts.headers,
+                                body,
+                            );
+                        }
+                        let response = CachedResponse {
+                            status: parts.status,
+                            headers: parts.headers,
+                            body,
+                        };
+                        Self::deliver(
+                            &mut commands,
+                            entity,
+                            streaming,
+                            error_for_status,
+                            response,
+                            from_cache,
+                        );
+                        if let Ok(mut ec) = commands.get_entity(entity) {
+                            ec.remove::<ReqwestInflight>();
+                        }
                     }
-                    Err(err) => {
-                        commands.trigger(ReqwestErrorEvent { entity, error: err });
+                    #[cfg(not(target_family = "wasm"))]
+                    TaskMessage::Transferred(bytes) => {
+                        if let Some(progress) = progress.as_mut() {
+                            progress.add(bytes);
+                        }
+                    }
+                    #[cfg(not(target_family = "wasm"))]
+                    TaskMessage::Downloaded(path, size) => {
+                        request.finish();
+                        if let Some(progress) = progress.as_mut() {
+                            if progress.take_event() {
+                                commands.trigger(ReqwestProgressEvent {
+                                    entity,
+                                    progress: progress.clone(),
+                                });
+                            }
+                        }
+                        // the head is always sent before the body
+                        let parts = request.parts.take().unwrap();
+                        commands.trigger(ReqwestDownloadEvent {
+                            entity,
+                            path,
+                            size,
+                            status: parts.status,
+                            headers: parts.headers,
+                        });
+                        if let Ok(mut ec) = commands.get_entity(entity) {
+                            ec.remove::<ReqwestInflight>();
+                        }
+                    }
+                    #[cfg(not(target_family = "wasm"))]
+                    TaskMessage::FileError(error) => {
+                        request.finish();
+                        commands.trigger(ReqwestDownloadErrorEvent { entity, error });
+                        if let Ok(mut ec) = commands.get_entity(entity) {
+                            ec.remove::<ReqwestInflight>();
+                        }
+                    }
+                    #[cfg(not(target_family = "wasm"))]
+                    TaskMessage::Loaded(loaded) => {
+                        let (loaded, mut next) = *loaded;
+                        request.task = None;
+                        request.cache = cache.lookup_loaded(&mut next, loaded);
+                        if matches!(request.cache, CacheLookup::Hit(..)) {
+                            continue;
+                        }
+                        // so the retries revalidate as well
+                        if let Some(copy) = next.try_clone() {
+                            request.request = Some(copy);
+                        }
+                        request.queued = Some(next);
+                        if let Ok(mut ec) = commands.get_entity(entity) {
+                            ec.insert(ReqwestQueued::new());
+                        }
+                    }
+                    TaskMessage::Failed(error) => {
+                        // chunks that were already handed out cannot be taken back
+                        let delay = policy.filter(|_| !request.streamed).and_then(|policy| {
+                            policy.error_retry_delay(request.attempt, ReqwestErrorKind::of(&error))
+                        });
+                        if delay.is_some_and(|delay| {
+                            Self::retry(&mut commands, entity, &mut request, delay)
+                        }) {
+                            continue;
+                        }
+                        request.finish();
+                        let offline = error.is_connect() || error.is_timeout();
+                        if let Some(response) = request.take_stale_if_error().filter(|_| offline) {
+                            debug!(
+                                "{entity:?} could not reach the server, answering from the cache"
+                            );
+                            Self::deliver(
+                                &mut commands,
+                                entity,
+                                streaming,
+                                error_for_status,
+                                response,
+                                true,
+                            );
+                        } else {
+                            commands.trigger(ReqwestErrorEvent {
+                                entity,
+                                kind: ReqwestErrorKind::of(&error),
+                                url: request.url.clone(),
+                                method: request.method.clone(),
+                                attempt: request.attempt,
+                                elapsed: request.elapsed(),
+                                error,
+                            });
+                        }
+                        if let Ok(mut ec) = commands.get_entity(entity) {
+                            ec.remove::<ReqwestInflight>();
+                        }
+                    }
+                }
+            }
+            // answers the requests the cache has a response for, including the ones that were just read from disk
+            if let Some((response, revalidate)) = request.take_cache_hit() {
+                debug!("{entity:?} was answered from the cache");
+                request.finish();
+                if let Some(revalidate) = revalidate {
+                    // nobody listens to the response, it only updates the cache
+                    let mut ec = commands.spawn(DespawnReqwestEntity);
+                    ReqwestInflight::revalidation(*revalidate, response.clone(), &defaults)
+                        .insert_into(&mut ec);
+                }
+                if let Some(progress) = progress.as_mut() {
+                    progress.start_response(&response.headers);
+                    progress.add(response.body.len() as u64);
+                    if progress.take_event() {
+                        commands.trigger(ReqwestProgressEvent {
+                            entity,
+                            progress: progress.clone(),
+                        });
                     }
                 }
+                Self::deliver(
+                    &mut commands,
+                    entity,
+                    streaming,
+                    error_for_status,
+                    response,
+                    true,
+                );
                 if let Ok(mut ec) = commands.get_entity(entity) {
                     ec.remove::<ReqwestInflight>();
                 }
             }
         }
     }
+
+    /// Triggers the events for a complete response, a streamed response that came from the cache is delivered
+    /// as a single chunk. With `error_for_status`, a `4xx` or `5xx` response is delivered as an error
+    fn deliver(
+        commands: &mut Commands,
+        entity: Entity,
+        streaming: bool,
+        error_for_status: bool,
+        response: CachedResponse,
+        from_cache: bool,
+    ) {
+        if streaming {
+            if from_cache && !response.body.is_empty() {
+                commands.trigger(ReqwestChunkEvent {
+                    entity,
+                    chunk: response.body,
+                });
+            }
+            commands.trigger(ReqwestStreamEndEvent {
+                entity,
+                status: response.status,
+                headers: response.headers,
+                from_cache,
+            });
+        } else {
+            let mut event =
+                ReqwestResponseEvent::new(entity, response.body, response.status, response.headers);
+            event.from_cache = from_cache;
+            if error_for_status
+                && (event.status.is_client_error() || event.status.is_server_error())
+            {
+                commands.trigger(ReqwestStatusErrorEvent {
+                    entity,
+                    response: event,
+                });
+            } else {
+                commands.trigger(event);
+            }
+        }
+    }
+
+    /// Schedules the request to be sent again after `delay`, returns false if it cannot be sent again
+    fn retry(
+        commands: &mut Commands,
+        entity: Entity,
+        request: &mut ReqwestInflight,
+        delay: Duration,
+    ) -> bool {
+        if !request.retry_after(delay) {
+            return false;
+        }
+        debug!("retrying {entity:?} in {delay:?}");
+        commands.trigger(ReqwestRetryEvent {
+            entity,
+            attempt: request.attempt + 1,
+            delay,
+        });
+        true
+    }
+
+    /// Updates the transfer rate of requests in flight, and triggers their [`ReqwestProgressEvent`]s
+    fn report_request_progress(
+        mut commands: Commands,
+        time: Res<Time<Real>>,
+        defaults: Res<ReqwestDefaults>,
+        mut requests: Query<(Entity, &ReqwestInflight, &mut ReqwestProgress)>,
+    ) {
+        for (entity, request, mut progress) in requests.iter_mut() {
+            if request.task.is_none() {
+                continue;
+            }
+            if progress.tick(time.delta(), defaults.progress_event_interval)
+                && progress.take_event()
+            {
+                commands.trigger(ReqwestProgressEvent {
+                    entity,
+                    progress: progress.clone(),
+                });
+            }
+        }
+    }
+
+    /// Ticks the timeouts of the requests still in flight, and aborts the ones that ran out of time. A stale
+    /// response from the cache is delivered in place of the timeout, as the server could not be reached
+    #[allow(clippy::type_complexity)]
+    fn abort_timed_out_requests(
+        mut commands: Commands,
+        time: Res<Time<Real>>,
+        defaults: Res<ReqwestDefaults>,
+        mut requests: Query<(
+            Entity,
+            &mut ReqwestInflight,
+            &mut ReqwestTimeout,
+            Option<&RetryPolicy>,
+            Has<StreamReqwestBody>,
+            Option<&ErrorForStatus>,
+        )>,
+    ) {
+        for (entity, mut request, mut timeout, policy, streaming, error_for_status) in
+            requests.iter_mut()
+        {
+            // the request might have finished this frame already, or is waiting to be sent
+            if request.task.is_none() || !timeout.timer.tick(time.delta()).is_finished() {
+                continue;
+            }
+            debug!("timed out: {entity:?}");
+            // chunks that were already handed out cannot be taken back
+            let delay = policy
+                .filter(|_| !request.streamed)
+                .and_then(|policy| policy.timeout_retry_delay(request.attempt));
+            if delay.is_some_and(|delay| Self::retry(&mut commands, entity, &mut request, delay)) {
+                continue;
+            }
+            // mark it as handled, so removing it is not reported as a cancellation
+            request.finish();
+            if let Some(response) = request.take_stale_if_error() {
+                debug!("{entity:?} timed out, answering from the cache");
+                let error_for_status = error_for_status.map_or(defaults.error_for_status, |e| e.0);
+                Self::deliver(
+                    &mut commands,
+                    entity,
+                    streaming,
+                    error_for_status,
+                    response,
+                    true,
+                );
+            } else {
+                commands.trigger(ReqwestTimeoutEvent {
+                    entity,
+                    timeout: timeout.duration(),
+                });
+            }
+            if let Ok(mut ec) = commands.get_entity(entity) {
+                ec.remove::<ReqwestInflight>();
+            }
+        }
+    }
+
+    /// Sends the requests that are done waiting for their next attempt, or queues them if the requests in
+    /// flight are limited
+    fn send_retried_requests(
+        mut commands: Commands,
+        client: Res<ReqwestClient>,
+        defaults: Res<ReqwestDefaults>,
+        mut queue: ResMut<ReqwestQueue>,
+        mut rate_limits: ResMut<RateLimitState>,
+        time: Res<Time<Real>>,
+        mut requests: Query<(
+            Entity,
+            &mut ReqwestInflight,
+            Option<&mut ReqwestTimeout>,
+            Option<&mut ReqwestProgress>,
+        )>,
+    ) {
+        for (entity, mut request, timeout, progress) in requests.iter_mut() {
+            let Some(backoff) = request.backoff.as_mut() else {
+                continue;
+            };
+            if !backoff.tick(time.delta()).is_finished() {
+                continue;
+            }
+            // checked when the retry was scheduled
+            let Some(copy) = request.request.as_ref().and_then(|r| r.try_clone()) else {
+                continue;
+            };
+            debug!("sending attempt {} of {entity:?}", request.attempt + 1);
+            request.backoff = None;
+            request.attempt += 1;
+            request.queued = Some(copy);
+            if !queue.try_reserve_new(&request.host, &defaults, &mut rate_limits) {
+                if let Ok(mut ec) = commands.get_entity(entity) {
+                    ec.insert(ReqwestQueued::new());
+                }
+                continue;
+            }
+            request.dispatch(&client, timeout, progress);
+        }
+    }
 }
 
 /// Wrapper around EntityCommands to create the on_response and on_error
 pub struct BevyReqwestBuilder<'a>(EntityCommands<'a>);
 
 impl<'a> BevyReqwestBuilder<'a> {
-    /// Provide a system where the first argument is [`Trigger`] [`ReqwestResponseEvent`] that will run on the
+    /// Provide a system where the first argument is [`On`] [`ReqwestResponseEvent`] that will run on the
     /// response from the http request
     ///
     /// # Examples
     ///
     /// ```
-    /// use bevy::prelude::Trigger;
+    /// use bevy::prelude::On;
     /// use bevy_mod_reqwest::ReqwestResponseEvent;
-    /// |trigger: Trigger<ReqwestResponseEvent>|  {
+    /// |trigger: On<ReqwestResponseEvent>|  {
     ///   bevy::log::info!("response: {:?}", trigger.event());
     /// };
     /// ```
@@ -151,16 +771,19 @@ impl<'a> BevyReqwestBuilder<'a> {
         self
     }
 
-    /// Provide a system where the first argument is [`Trigger`] [`JsonResponse`] that will run on the
+    /// Provide a system where the first argument is [`On`] [`JsonResponse`] that will run on the
     /// response from the http request, skipping some boilerplate of having to manually doing the JSON
     /// parsing
     ///
+    /// A body that could not be deserialized triggers a [`JsonDeserializeErrorEvent`] instead, see
+    /// [`BevyReqwestBuilder::on_json_error`]
+    ///
     /// # Examples
     /// ```
-    /// use bevy::prelude::Trigger;
+    /// use bevy::prelude::On;
     /// use bevy_mod_reqwest::JsonResponse;
-    /// |trigger: Trigger<JsonResponse<T>>|  {
-    ///   bevy::log::info!("response: {:?}", trigger.event());
+    /// |trigger: On<JsonResponse<serde_json::Value>>|  {
+    ///   bevy::log::info!("response: {:?}", trigger.event().data);
     /// };
     /// ```
     #[cfg(feature = "json")]
@@ -173,217 +796,1475 @@ impl<'a> BevyReqwestBuilder<'a> {
         mut self,
         onresponse: OR,
     ) -> Self {
-        self.0
-            .observe(|evt: On<ReqwestResponseEvent>, mut commands: Commands| {
+        self.0.observe(
+            |evt: On<ReqwestResponseEvent>,
+             mut commands: Commands,
+             observed: Query<(), With<json::ObservesJsonErrors>>| {
                 let entity = evt.event().entity;
                 let evt = evt.event();
-                let data = evt.deserialize_json::<T>();
+                let data = json::deserialize::<T>(evt);
 
                 match data {
                     Ok(data) => {
                         // retrigger a new event with the serialized data
                         commands.trigger(json::JsonResponse { entity, data });
                     }
-                    Err(e) => {
-                        bevy::log::error!("deserialization error: {e}");
-                        bevy::log::debug!(
-                            "tried serializing: {}",
-                            evt.as_str().unwrap_or("failed getting event data")
-                        );
-                    }
+                    Err(e) => json::trigger_error(&mut commands, *e, observed.contains(entity)),
                 }
-            });
+            },
+        );
         self.0.observe(onresponse);
         self
     }
 
-    /// Provide a system where the first argument is [`Trigger`] [`ReqwestErrorEvent`] that will run on the
-    /// response from the http request
+    /// Provide a system where the first argument is [`On`] [`JsonDeserializeErrorEvent`] that will run if the
+    /// body of the response could not be deserialized by [`BevyReqwestBuilder::on_json_response`] or the json
+    /// status handlers such as [`BevyReqwestBuilder::on_json_success`]. The errors of requests without this
+    /// handler are logged as errors
     ///
     /// # Examples
-    ///
     /// ```
-    /// use bevy::prelude::Trigger;
-    /// use bevy_mod_reqwest::ReqwestErrorEvent;
-    /// |trigger: Trigger<ReqwestErrorEvent>|  {
-    ///   bevy::log::info!("response: {:?}", trigger.event());
+    /// use bevy::prelude::On;
+    /// use bevy_mod_reqwest::JsonDeserializeErrorEvent;
+    /// |trigger: On<JsonDeserializeErrorEvent>|  {
+    ///   let evt = trigger.event();
+    ///   bevy::log::warn!("{} at {}: {:?}", evt.error, evt.path, evt.as_str());
     /// };
     /// ```
-    pub fn on_error<EB: Bundle, EM, OE: IntoObserverSystem<ReqwestErrorEvent, EB, EM>>(
+    #[cfg(feature = "json")]
+    pub fn on_json_error<
+        JB: Bundle,
+        JM,
+        OJ: IntoObserverSystem<json::JsonDeserializeErrorEvent, JB, JM>,
+    >(
         mut self,
-        onerror: OE,
+        onjsonerror: OJ,
     ) -> Self {
-        self.0.observe(onerror);
+        self.0.insert(json::ObservesJsonErrors).observe(onjsonerror);
         self
     }
-}
 
-#[derive(SystemParam)]
-/// Systemparam to have a shorthand for creating http calls in systems
-pub struct BevyReqwest<'w, 's> {
-    commands: Commands<'w, 's>,
-    client: Res<'w, ReqwestClient>,
-}
-
-impl<'w, 's> BevyReqwest<'w, 's> {
-    /// Starts sending and processing the supplied [`reqwest::Request`]
-    /// then use the [`BevyReqwestBuilder`] to add handlers for responses and errors
-    pub fn send(&mut self, req: reqwest::Request) -> BevyReqwestBuilder {
-        let inflight = self.create_inflight_task(req);
-        BevyReqwestBuilder(self.commands.spawn((inflight, DespawnReqwestEntity)))
+    /// Provide a system where the first argument is [`On`] [`MsgpackResponse`] that will run on the
+    /// response from the http request, with the body deserialized from msgpack. A body that could not be
+    /// deserialized triggers a [`MsgpackDeserializeErrorEvent`] instead, see
+    /// [`BevyReqwestBuilder::on_msgpack_error`].
+    ///
+    /// The request is already on its way when the handlers are added, so this cannot ask for msgpack with the
+    /// `Accept` header. Build the request with [`MsgpackRequestBuilderExt`] to send msgpack and ask for it in
+    /// return, or with [`MsgpackRequestBuilderExt::accept_msgpack`] for requests without a body
+    ///
+    /// # Examples
+    /// ```
+    /// use bevy::prelude::On;
+    /// use bevy_mod_reqwest::MsgpackResponse;
+    /// |trigger: On<MsgpackResponse<Vec<u32>>>|  {
+    ///   bevy::log::info!("response: {:?}", trigger.event().data);
+    /// };
+    /// ```
+    #[cfg(feature = "msgpack")]
+    pub fn on_msgpack_response<
+        T: std::marker::Sync + std::marker::Send + serde::de::DeserializeOwned + 'static,
+        RB: Bundle,
+        RM,
+        OR: IntoObserverSystem<msgpack::MsgpackResponse<T>, RB, RM>,
+    >(
+        mut self,
+        onresponse: OR,
+    ) -> Self {
+        self.0.observe(
+            |evt: On<ReqwestResponseEvent>,
+             mut commands: Commands,
+             observed: Query<(), With<msgpack::ObservesMsgpackErrors>>| {
+                let entity = evt.event().entity;
+                match msgpack::deserialize::<T>(evt.event()) {
+                    Ok(data) => commands.trigger(msgpack::MsgpackResponse { entity, data }),
+                    Err(e) => msgpack::trigger_error(&mut commands, *e, observed.contains(entity)),
+                }
+            },
+        );
+        self.0.observe(onresponse);
+        self
     }
 
-    /// Starts sending and processing the supplied [`reqwest::Request`] on the supplied [`Entity`] if it exists
-    /// and then use the [`BevyReqwestBuilder`] to add handlers for responses and errors
-    pub fn send_using_entity(
-        &mut self,
-        entity: Entity,
-        req: reqwest::Request,
-    ) -> Result<BevyReqwestBuilder, Box<dyn std::error::Error>> {
-        let inflight = self.create_inflight_task(req);
-        let mut ec = self.commands.get_entity(entity)?;
-        info!("inserting request on entity: {:?}", entity);
-        ec.insert(inflight);
-        Ok(BevyReqwestBuilder(ec))
+    /// Provide a system where the first argument is [`On`] [`MsgpackDeserializeErrorEvent`] that will run if
+    /// the body of the response could not be deserialized by [`BevyReqwestBuilder::on_msgpack_response`] or
+    /// the msgpack status handlers such as [`BevyReqwestBuilder::on_msgpack_success`]. The errors of requests
+    /// without this handler are logged as errors
+    #[cfg(feature = "msgpack")]
+    pub fn on_msgpack_error<
+        MB: Bundle,
+        MM,
+        OM: IntoObserverSystem<msgpack::MsgpackDeserializeErrorEvent, MB, MM>,
+    >(
+        mut self,
+        onmsgpackerror: OM,
+    ) -> Self {
+        self.0
+            .insert(msgpack::ObservesMsgpackErrors)
+            .observe(onmsgpackerror);
+        self
     }
 
-    /// get access to the underlying ReqwestClient
-    pub fn client(&self) -> &reqwest::Client {
-        &self.client.0
+    /// Provide a system where the first argument is [`On`] [`ReqwestStatusEvent`] that will only run for
+    /// responses with exactly this `status`. It takes precedence over the handlers for the classes of statuses,
+    /// such as [`BevyReqwestBuilder::on_client_error`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy::prelude::*;
+    /// use bevy_mod_reqwest::*;
+    ///
+    /// fn send_requests(mut client: BevyReqwest) {
+    ///     let request = client.get("https://example.com/profile").build().unwrap();
+    ///     client
+    ///         .send(request)
+    ///         .on_success(|trigger: On<ReqwestStatusEvent>| {
+    ///             info!("profile: {:?}", trigger.event().as_str());
+    ///         })
+    ///         .on_status(StatusCode::NOT_FOUND, |_: On<ReqwestStatusEvent>| {
+    ///             info!("there is no profile yet");
+    ///         })
+    ///         .on_client_error(|trigger: On<ReqwestStatusEvent>| {
+    ///             warn!("the profile could not be requested: {}", trigger.event().status());
+    ///         })
+    ///         .on_other_status(|trigger: On<ReqwestStatusEvent>| {
+    ///             error!("unexpected response: {}", trigger.event().status());
+    ///         });
+    /// }
+    /// ```
+    pub fn on_status<SB: Bundle, SM, OS: IntoObserverSystem<ReqwestStatusEvent, SB, SM>>(
+        self,
+        status: StatusCode,
+        onstatus: OS,
+    ) -> Self {
+        self.add_status_route(StatusRoute::Status(status), |target| {
+            target.observe(onstatus);
+        })
     }
 
-    fn create_inflight_task(&self, request: reqwest::Request) -> ReqwestInflight {
-        let thread_pool = IoTaskPool::get();
-        // bevy::log::debug!("Creating: {entity:?}");
-        // if we take the data, we can use it
-        let client = self.client.0.clone();
-        let url = request.url().to_string();
-
-        // wasm implementation
-        #[cfg(target_family = "wasm")]
-        let task = {
-            let (tx, task) = bounded(1);
-            thread_pool
-                .spawn(async move {
-                    let r = client.execute(request).await;
-                    let r = match r {
-                        Ok(res) => {
-                            let parts = Parts {
-                                status: res.status(),
-                                headers: res.headers().clone(),
-                            };
-                            (res.bytes().await, Some(parts))
-                        }
-                        Err(r) => (Err(r), None),
-                    };
-                    tx.send(r).ok();
-                })
-                .detach();
-            task
-        };
-
-        // otherwise
-        #[cfg(not(target_family = "wasm"))]
-        let task = {
-            thread_pool.spawn(async move {
-                let task_res = async_compat::Compat::new(async {
-                    let p = client.execute(request).await;
-                    match p {
-                        Ok(res) => {
-                            let parts = Parts {
-                                status: res.status(),
-                                headers: res.headers().clone(),
-                            };
-                            (res.bytes().await, Some(parts))
-                        }
-                        Err(e) => (Err(e), None),
-                    }
-                })
-                .await;
-                task_res
-            })
-        };
-        // put it as a component to be polled, and remove the request, it has been handled
-        ReqwestInflight::new(task, url)
+    /// Provide a system where the first argument is [`On`] [`ReqwestStatusEvent`] that will only run for
+    /// responses with a `2xx` status
+    pub fn on_success<SB: Bundle, SM, OS: IntoObserverSystem<ReqwestStatusEvent, SB, SM>>(
+        self,
+        onsuccess: OS,
+    ) -> Self {
+        self.add_status_route(StatusRoute::Success, |target| {
+            target.observe(onsuccess);
+        })
     }
-}
-
-impl<'w, 's> Deref for BevyReqwest<'w, 's> {
-    type Target = reqwest::Client;
 
-    fn deref(&self) -> &Self::Target {
-        self.client()
+    /// Provide a system where the first argument is [`On`] [`ReqwestStatusEvent`] that will only run for
+    /// responses with a `4xx` status
+    pub fn on_client_error<SB: Bundle, SM, OS: IntoObserverSystem<ReqwestStatusEvent, SB, SM>>(
+        self,
+        onclienterror: OS,
+    ) -> Self {
+        self.add_status_route(StatusRoute::ClientError, |target| {
+            target.observe(onclienterror);
+        })
     }
-}
-
-#[derive(Component)]
-/// Marker component that is used to despawn an entity if the reqwest is finshed
-pub struct DespawnReqwestEntity;
 
-#[derive(Resource)]
-/// Wrapper around the ReqwestClient, that when inserted as a resource will start connection pools towards
-/// the hosts, and also allows all the configuration from the ReqwestLibrary such as setting default headers etc
-/// to be used inside the bevy application
-pub struct ReqwestClient(pub reqwest::Client);
-impl Default for ReqwestClient {
-    fn default() -> Self {
-        Self(reqwest::Client::new())
+    /// Provide a system where the first argument is [`On`] [`ReqwestStatusEvent`] that will only run for
+    /// responses with a `5xx` status
+    pub fn on_server_error<SB: Bundle, SM, OS: IntoObserverSystem<ReqwestStatusEvent, SB, SM>>(
+        self,
+        onservererror: OS,
+    ) -> Self {
+        self.add_status_route(StatusRoute::ServerError, |target| {
+            target.observe(onservererror);
+        })
     }
-}
 
-impl std::ops::Deref for ReqwestClient {
-    type Target = reqwest::Client;
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
-}
-impl DerefMut for ReqwestClient {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+    /// Provide a system where the first argument is [`On`] [`ReqwestStatusEvent`] that will run for the
+    /// responses that none of the other status handlers of the request matched
+    pub fn on_other_status<SB: Bundle, SM, OS: IntoObserverSystem<ReqwestStatusEvent, SB, SM>>(
+        self,
+        onother: OS,
+    ) -> Self {
+        self.add_status_route(StatusRoute::Other, |target| {
+            target.observe(onother);
+        })
     }
-}
-
-type Resp = (reqwest::Result<bytes::Bytes>, Option<Parts>);
 
-/// Dont touch these, its just to poll once every request, can be used to detect if there is an active request on the entity
-/// but should otherwise NOT be added/removed/changed by a user of this Crate
-#[derive(Component)]
-#[component(storage = "SparseSet")]
-pub struct ReqwestInflight {
-    // the url this request is handling as a string
-    pub(crate) url: String,
+    /// Like [`BevyReqwestBuilder::on_success`], but the first argument of the system is [`On`]
+    /// [`JsonStatusResponse`] with the deserialized body
+    ///
+    /// # Examples
+    /// ```
+    /// use bevy::prelude::On;
+    /// use bevy_mod_reqwest::JsonStatusResponse;
+    /// |trigger: On<JsonStatusResponse<serde_json::Value>>|  {
+    ///   bevy::log::info!("response: {:?}", trigger.event().data);
+    /// };
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn on_json_success<
+        T: std::marker::Sync + std::marker::Send + serde::de::DeserializeOwned + 'static,
+        RB: Bundle,
+        RM,
+        OR: IntoObserverSystem<JsonStatusResponse<T>, RB, RM>,
+    >(
+        self,
+        onsuccess: OR,
+    ) -> Self {
+        self.add_status_route(StatusRoute::Success, |target| {
+            target
+                .observe(status::deserialize_status_response::<T>)
+                .observe(onsuccess);
+        })
+    }
+
+    /// Like [`BevyReqwestBuilder::on_status`], but the first argument of the system is [`On`]
+    /// [`JsonStatusResponse`] with the deserialized body
+    #[cfg(feature = "json")]
+    pub fn on_json_status<
+        T: std::marker::Sync + std::marker::Send + serde::de::DeserializeOwned + 'static,
+        RB: Bundle,
+        RM,
+        OR: IntoObserverSystem<JsonStatusResponse<T>, RB, RM>,
+    >(
+        self,
+        status: StatusCode,
+        onstatus: OR,
+    ) -> Self {
+        self.add_status_route(StatusRoute::Status(status), |target| {
+            target
+                .observe(status::deserialize_status_response::<T>)
+                .observe(onstatus);
+        })
+    }
+
+    /// Like [`BevyReqwestBuilder::on_success`], but the first argument of the system is [`On`]
+    /// [`MsgpackStatusResponse`] with the body deserialized from msgpack. Like
+    /// [`BevyReqwestBuilder::on_msgpack_response`] it cannot ask for msgpack, build the request with
+    /// [`MsgpackRequestBuilderExt`] for that
+    ///
+    /// # Examples
+    /// ```
+    /// use bevy::prelude::On;
+    /// use bevy_mod_reqwest::MsgpackStatusResponse;
+    /// |trigger: On<MsgpackStatusResponse<Vec<u32>>>|  {
+    ///   bevy::log::info!("response: {:?}", trigger.event().data);
+    /// };
+    /// ```
+    #[cfg(feature = "msgpack")]
+    pub fn on_msgpack_success<
+        T: std::marker::Sync + std::marker::Send + serde::de::DeserializeOwned + 'static,
+        RB: Bundle,
+        RM,
+        OR: IntoObserverSystem<msgpack::MsgpackStatusResponse<T>, RB, RM>,
+    >(
+        self,
+        onsuccess: OR,
+    ) -> Self {
+        self.add_status_route(StatusRoute::Success, |target| {
+            target
+                .observe(msgpack::deserialize_status_response::<T>)
+                .observe(onsuccess);
+        })
+    }
+
+    /// Like [`BevyReqwestBuilder::on_status`], but the first argument of the system is [`On`]
+    /// [`MsgpackStatusResponse`] with the body deserialized from msgpack
+    #[cfg(feature = "msgpack")]
+    pub fn on_msgpack_status<
+        T: std::marker::Sync + std::marker::Send + serde::de::DeserializeOwned + 'static,
+        RB: Bundle,
+        RM,
+        OR: IntoObserverSystem<msgpack::MsgpackStatusResponse<T>, RB, RM>,
+    >(
+        self,
+        status: StatusCode,
+        onstatus: OR,
+    ) -> Self {
+        self.add_status_route(StatusRoute::Status(status), |target| {
+            target
+                .observe(msgpack::deserialize_status_response::<T>)
+                .observe(onstatus);
+        })
+    }
+
+    /// spawns the entity the handler of a status route observes, as a child so it is despawned with the request
+    fn add_status_route(
+        mut self,
+        route: StatusRoute,
+        observe: impl FnOnce(&mut EntityCommands),
+    ) -> Self {
+        let request = self.0.id();
+        let mut commands = self.0.commands();
+        let mut target = commands.spawn(ChildOf(request));
+        observe(&mut target);
+        let target = target.id();
+        self.0
+            .entry::<StatusRoutes>()
+            .or_default()
+            .and_modify(move |mut routes| routes.push(route, target));
+        self
+    }
+
+    /// Provide a system where the first argument is [`On`] [`ReqwestErrorEvent`] that will run on the
+    /// response from the http request
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy::prelude::On;
+    /// use bevy_mod_reqwest::ReqwestErrorEvent;
+    /// |trigger: On<ReqwestErrorEvent>|  {
+    ///   bevy::log::info!("response: {:?}", trigger.event());
+    /// };
+    /// ```
+    pub fn on_error<EB: Bundle, EM, OE: IntoObserverSystem<ReqwestErrorEvent, EB, EM>>(
+        mut self,
+        onerror: OE,
+    ) -> Self {
+        self.0.observe(onerror);
+        self
+    }
+
+    /// Sets if a response with a `4xx` or `5xx` status triggers a [`ReqwestStatusErrorEvent`] instead of a
+    /// [`ReqwestResponseEvent`], overriding [`ReqwestPlugin::error_for_status`]. Those responses then do not
+    /// reach [`BevyReqwestBuilder::on_response`], [`BevyReqwestBuilder::on_json_response`] or the status
+    /// handlers such as [`BevyReqwestBuilder::on_client_error`]. Streamed responses always end with a
+    /// [`ReqwestStreamEndEvent`]
+    pub fn error_for_status(mut self, enabled: bool) -> Self {
+        self.0.insert(ErrorForStatus(enabled));
+        self
+    }
+
+    /// Provide a system where the first argument is [`On`] [`ReqwestStatusErrorEvent`] that will run for the
+    /// responses with a `4xx` or `5xx` status, if [`BevyReqwestBuilder::error_for_status`] is enabled
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy::prelude::On;
+    /// use bevy_mod_reqwest::ReqwestStatusErrorEvent;
+    /// |trigger: On<ReqwestStatusErrorEvent>|  {
+    ///   bevy::log::warn!("{}: {:?}", trigger.event().status(), trigger.event().as_str());
+    /// };
+    /// ```
+    pub fn on_status_error<
+        SB: Bundle,
+        SM,
+        OS: IntoObserverSystem<ReqwestStatusErrorEvent, SB, SM>,
+    >(
+        mut self,
+        onstatuserror: OS,
+    ) -> Self {
+        self.0.observe(onstatuserror);
+        self
+    }
+
+    /// Provide a system where the first argument is [`On`] [`ReqwestCancelledEvent`] that will run if the
+    /// request is aborted before it finished, either by [`BevyReqwest::cancel`] or by despawning the entity
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy::prelude::On;
+    /// use bevy_mod_reqwest::ReqwestCancelledEvent;
+    /// |trigger: On<ReqwestCancelledEvent>|  {
+    ///   bevy::log::info!("cancelled: {:?}", trigger.event().entity);
+    /// };
+    /// ```
+    pub fn on_cancel<CB: Bundle, CM, OC: IntoObserverSystem<ReqwestCancelledEvent, CB, CM>>(
+        mut self,
+        oncancel: OC,
+    ) -> Self {
+        self.0.observe(oncancel);
+        self
+    }
+
+    /// Provide a system where the first argument is [`On`] [`ReqwestRateLimitedEvent`] that will run every time
+    /// the host answers with `429 Too Many Requests` or `503 Service Unavailable` and a `Retry-After` header,
+    /// and the request is put back in the queue
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy::prelude::On;
+    /// use bevy_mod_reqwest::ReqwestRateLimitedEvent;
+    /// |trigger: On<ReqwestRateLimitedEvent>|  {
+    ///   bevy::log::info!("{} is rate limiting for {:?}", trigger.host, trigger.delay);
+    /// };
+    /// ```
+    pub fn on_rate_limited<
+        LB: Bundle,
+        LM,
+        OL: IntoObserverSystem<ReqwestRateLimitedEvent, LB, LM>,
+    >(
+        mut self,
+        onratelimited: OL,
+    ) -> Self {
+        self.0.observe(onratelimited);
+        self
+    }
+
+    /// Sets the [`RequestPriority`] of the request, which decides which queued requests are sent first when
+    /// [`ReqwestPlugin::max_inflight`] or [`ReqwestPlugin::max_inflight_per_host`] is set
+    pub fn priority(mut self, priority: RequestPriority) -> Self {
+        self.0.insert(priority);
+        self
+    }
+
+    /// Abort the request if it has not finished within the supplied [`Duration`], overriding
+    /// [`ReqwestPlugin::default_timeout`]. The time is measured using [`Time<Real>`]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.0.insert(ReqwestTimeout::new(timeout));
+        self
+    }
+
+    /// Provide a system where the first argument is [`On`] [`ReqwestChunkEvent`] that will run for every part
+    /// of the body as it arrives, instead of waiting for the whole body. The response is then finished
+    /// with a [`ReqwestStreamEndEvent`] instead of a [`ReqwestResponseEvent`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy::prelude::On;
+    /// use bevy_mod_reqwest::ReqwestChunkEvent;
+    /// |trigger: On<ReqwestChunkEvent>|  {
+    ///   bevy::log::info!("received {} bytes", trigger.event().chunk.len());
+    /// };
+    /// ```
+    pub fn on_chunk<CB: Bundle, CM, OC: IntoObserverSystem<ReqwestChunkEvent, CB, CM>>(
+        mut self,
+        onchunk: OC,
+    ) -> Self {
+        self.0.insert(StreamReqwestBody);
+        self.0.observe(onchunk);
+        self
+    }
+
+    /// Provide a system where the first argument is [`On`] [`ReqwestStreamEndEvent`] that will run after the
+    /// last [`ReqwestChunkEvent`] of a streamed response
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy::prelude::On;
+    /// use bevy_mod_reqwest::ReqwestStreamEndEvent;
+    /// |trigger: On<ReqwestStreamEndEvent>|  {
+    ///   bevy::log::info!("stream ended with: {}", trigger.event().status);
+    /// };
+    /// ```
+    pub fn on_stream_end<SB: Bundle, SM, OS: IntoObserverSystem<ReqwestStreamEndEvent, SB, SM>>(
+        mut self,
+        onend: OS,
+    ) -> Self {
+        self.0.insert(StreamReqwestBody);
+        self.0.observe(onend);
+        self
+    }
+
+    /// Provide a system where the first argument is [`On`] [`ReqwestDownloadEvent`] that will run once a
+    /// request sent with [`BevyReqwest::download`] has written the whole body to its file
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy::prelude::On;
+    /// use bevy_mod_reqwest::ReqwestDownloadEvent;
+    /// |trigger: On<ReqwestDownloadEvent>|  {
+    ///   bevy::log::info!("downloaded {} bytes to {:?}", trigger.event().size, trigger.event().path);
+    /// };
+    /// ```
+    #[cfg(not(target_family = "wasm"))]
+    pub fn on_download<DB: Bundle, DM, OD: IntoObserverSystem<ReqwestDownloadEvent, DB, DM>>(
+        mut self,
+        ondownload: OD,
+    ) -> Self {
+        self.0.observe(ondownload);
+        self
+    }
+
+    /// Provide a system where the first argument is [`On`] [`ReqwestDownloadErrorEvent`] that will run if a
+    /// request sent with [`BevyReqwest::download`] could not write the body to its file
+    #[cfg(not(target_family = "wasm"))]
+    pub fn on_download_error<
+        EB: Bundle,
+        EM,
+        OE: IntoObserverSystem<ReqwestDownloadErrorEvent, EB, EM>,
+    >(
+        mut self,
+        onerror: OE,
+    ) -> Self {
+        self.0.observe(onerror);
+        self
+    }
+
+    /// Keep a [`ReqwestProgress`] component on the entity up to date while the response is received. Keeps the
+    /// upload progress of [`BevyReqwest::send_with_progress`] if there already is one
+    pub fn track_progress(mut self) -> Self {
+        self.0.insert_if_new(ReqwestProgress::default());
+        self
+    }
+
+    /// Provide a system where the first argument is [`On`] [`ReqwestProgressEvent`] that will run as the
+    /// response is received, at most once every [`ReqwestPlugin::progress_event_interval`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy::prelude::On;
+    /// use bevy_mod_reqwest::ReqwestProgressEvent;
+    /// |trigger: On<ReqwestProgressEvent>|  {
+    ///   let progress = &trigger.event().progress;
+    ///   bevy::log::info!("{} of {:?} bytes", progress.transferred(), progress.total());
+    /// };
+    /// ```
+    pub fn on_progress<PB: Bundle, PM, OP: IntoObserverSystem<ReqwestProgressEvent, PB, PM>>(
+        mut self,
+        onprogress: OP,
+    ) -> Self {
+        self.0.insert_if_new(ReqwestProgress::default());
+        self.0.observe(onprogress);
+        self
+    }
+
+    /// Send the request again if an attempt fails in a way the [`RetryPolicy`] considers retryable,
+    /// overriding [`ReqwestPlugin::default_retry`]. The error and response handlers only run for the last attempt
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.0.insert(policy);
+        self
+    }
+
+    /// Provide a system where the first argument is [`On`] [`ReqwestRetryEvent`] that will run every time
+    /// a failed attempt is scheduled to be sent again
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy::prelude::On;
+    /// use bevy_mod_reqwest::ReqwestRetryEvent;
+    /// |trigger: On<ReqwestRetryEvent>|  {
+    ///   bevy::log::info!("sending attempt {} in {:?}", trigger.event().attempt, trigger.event().delay);
+    /// };
+    /// ```
+    pub fn on_retry<RB: Bundle, RM, OR: IntoObserverSystem<ReqwestRetryEvent, RB, RM>>(
+        mut self,
+        onretry: OR,
+    ) -> Self {
+        self.0.observe(onretry);
+        self
+    }
+
+    /// Provide a system where the first argument is [`On`] [`ReqwestTimeoutEvent`] that will run if the
+    /// request did not finish within its timeout
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy::prelude::On;
+    /// use bevy_mod_reqwest::ReqwestTimeoutEvent;
+    /// |trigger: On<ReqwestTimeoutEvent>|  {
+    ///   bevy::log::info!("timed out after: {:?}", trigger.event().timeout);
+    /// };
+    /// ```
+    pub fn on_timeout<TB: Bundle, TM, OT: IntoObserverSystem<ReqwestTimeoutEvent, TB, TM>>(
+        mut self,
+        ontimeout: OT,
+    ) -> Self {
+        self.0.observe(ontimeout);
+        self
+    }
+}
+
+/// Wrapper around EntityCommands to add the handlers for the events of a [`WebSocketConnection`]
+pub struct WebSocketBuilder<'a>(EntityCommands<'a>);
+
+impl<'a> WebSocketBuilder<'a> {
+    /// Provide a system where the first argument is [`On`] [`WsOpenEvent`] that will run once the server has
+    /// accepted the connection
+    pub fn on_open<OB: Bundle, OM, OO: IntoObserverSystem<WsOpenEvent, OB, OM>>(
+        mut self,
+        onopen: OO,
+    ) -> Self {
+        self.0.observe(onopen);
+        self
+    }
+
+    /// Provide a system where the first argument is [`On`] [`WsMessageEvent`] that will run for every message
+    /// received on the connection
+    pub fn on_message<MB: Bundle, MM, OM: IntoObserverSystem<WsMessageEvent, MB, MM>>(
+        mut self,
+        onmessage: OM,
+    ) -> Self {
+        self.0.observe(onmessage);
+        self
+    }
+
+    /// Provide a system where the first argument is [`On`] [`WsCloseEvent`] that will run when the connection
+    /// has closed or could not be opened
+    pub fn on_close<CB: Bundle, CM, OC: IntoObserverSystem<WsCloseEvent, CB, CM>>(
+        mut self,
+        onclose: OC,
+    ) -> Self {
+        self.0.observe(onclose);
+        self
+    }
+
+    /// The [`Entity`] the connection lives on, which has the [`WebSocketConnection`] component used to send
+    /// messages
+    pub fn id(&self) -> Entity {
+        self.0.id()
+    }
+}
+
+#[derive(SystemParam)]
+/// Systemparam to have a shorthand for creating http calls in systems
+pub struct BevyReqwest<'w, 's> {
+    commands: Commands<'w, 's>,
+    client: Res<'w, ReqwestClient>,
+    defaults: Res<'w, ReqwestDefaults>,
+    queue: ResMut<'w, ReqwestQueue>,
+    rate_limits: ResMut<'w, RateLimitState>,
+    cache: Res<'w, ReqwestCache>,
+}
+
+impl<'w, 's> BevyReqwest<'w, 's> {
+    /// Starts sending and processing the supplied [`reqwest::Request`]
+    /// then use the [`BevyReqwestBuilder`] to add handlers for responses and errors
+    pub fn send(&mut self, req: reqwest::Request) -> BevyReqwestBuilder<'_> {
+        let inflight = self.create_inflight_task(req, TaskOptions::default());
+        let mut ec = self.commands.spawn(DespawnReqwestEntity);
+        inflight.insert_into(&mut ec);
+        Self::insert_defaults(&self.defaults, &mut ec);
+        BevyReqwestBuilder(ec)
+    }
+
+    /// Like [`BevyReqwest::send`], but keeps a [`ReqwestProgress`] on the entity up to date while the body of the
+    /// request is sent, and then while the response is received. Use [`BevyReqwestBuilder::on_progress`] to get
+    /// [`ReqwestProgressEvent`]s as well.
+    ///
+    /// Only bodies created from bytes, such as strings, json or forms, can report how much has been sent
+    pub fn send_with_progress(&mut self, req: reqwest::Request) -> BevyReqwestBuilder<'_> {
+        let options = TaskOptions {
+            count_upload: true,
+            ..Default::default()
+        };
+        let inflight = self.create_inflight_task(req, options);
+        let mut progress = ReqwestProgress::default();
+        progress.start(TransferDirection::Upload, inflight.upload_size());
+        let mut ec = self.commands.spawn((progress, DespawnReqwestEntity));
+        inflight.insert_into(&mut ec);
+        Self::insert_defaults(&self.defaults, &mut ec);
+        BevyReqwestBuilder(ec)
+    }
+
+    /// Like [`BevyReqwest::send`], but writes the body of a successful response to the file at `path` instead of
+    /// keeping it in memory, and triggers a [`ReqwestDownloadEvent`] once it is complete.
+    ///
+    /// The body is first written to a `.part` file next to `path`, which is renamed once it is complete. If the
+    /// download is interrupted, later attempts or downloads to the same `path` continue where it stopped, as
+    /// long as the server supports range requests and the file has not changed according to its `ETag` or
+    /// `Last-Modified` header. Responses that are not successful are delivered as a [`ReqwestResponseEvent`]
+    #[cfg(not(target_family = "wasm"))]
+    pub fn download(
+        &mut self,
+        req: reqwest::Request,
+        path: impl Into<std::path::PathBuf>,
+    ) -> BevyReqwestBuilder<'_> {
+        let options = TaskOptions {
+            download: Some(path.into()),
+            ..Default::default()
+        };
+        let inflight = self.create_inflight_task(req, options);
+        let mut ec = self.commands.spawn(DespawnReqwestEntity);
+        inflight.insert_into(&mut ec);
+        Self::insert_defaults(&self.defaults, &mut ec);
+        BevyReqwestBuilder(ec)
+    }
+
+    /// Starts sending and processing the supplied [`reqwest::Request`] on the supplied [`Entity`] if it exists
+    /// and then use the [`BevyReqwestBuilder`] to add handlers for responses and errors
+    pub fn send_using_entity(
+        &mut self,
+        entity: Entity,
+        req: reqwest::Request,
+    ) -> Result<BevyReqwestBuilder<'_>, Box<dyn std::error::Error>> {
+        let inflight = self.create_inflight_task(req, TaskOptions::default());
+        let mut ec = self.commands.get_entity(entity)?;
+        info!("inserting request on entity: {:?}", entity);
+        inflight.insert_into(&mut ec);
+        // dont let the settings of an earlier request on this entity carry over
+        ec.remove::<(
+            ReqwestTimeout,
+            RetryPolicy,
+            StreamReqwestBody,
+            ReqwestProgress,
+            RequestPriority,
+            ErrorForStatus,
+            StatusRoutes,
+        )>();
+        Self::insert_defaults(&self.defaults, &mut ec);
+        Ok(BevyReqwestBuilder(ec))
+    }
+
+    /// Opens a WebSocket connection to the url of the supplied [`reqwest::Request`], which can use the `ws`,
+    /// `wss`, `http` or `https` scheme, then use the [`WebSocketBuilder`] to add handlers for its events.
+    ///
+    /// The entity is despawned once the connection has closed, and despawning it closes the connection
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bevy::prelude::*;
+    /// use bevy_mod_reqwest::*;
+    ///
+    /// fn connect(mut client: BevyReqwest) {
+    ///     let request = client.get("wss://example.com/socket").build().unwrap();
+    ///     client
+    ///         .connect_websocket(request)
+    ///         .on_open(|trigger: On<WsOpenEvent>, connections: Query<&WebSocketConnection>| {
+    ///             if let Ok(connection) = connections.get(trigger.entity) {
+    ///                 connection.send_text("hello");
+    ///             }
+    ///         })
+    ///         .on_message(|trigger: On<WsMessageEvent>| {
+    ///             bevy::log::info!("message: {:?}", trigger.message);
+    ///         });
+    /// }
+    /// ```
+    pub fn connect_websocket(&mut self, req: reqwest::Request) -> WebSocketBuilder<'_> {
+        let connection = WebSocketConnection::new(&self.client, req);
+        let ec = self.commands.spawn((connection, DespawnReqwestEntity));
+        WebSocketBuilder(ec)
+    }
+
+    /// Sends the request of the [`PeriodicRequest`] every interval from a new entity, then use the
+    /// [`BevyReqwestBuilder`] to add handlers, which run for the response of every request
+    pub fn send_periodic(&mut self, periodic: PeriodicRequest) -> BevyReqwestBuilder<'_> {
+        let mut ec = self.commands.spawn(periodic);
+        Self::insert_defaults(&self.defaults, &mut ec);
+        BevyReqwestBuilder(ec)
+    }
+
+    /// Like [`BevyReqwest::send`], but waits for `delay` before sending the request, measured using
+    /// [`Time<Virtual>`] so the wait does not pass while it is paused
+    pub fn send_after(&mut self, req: reqwest::Request, delay: Duration) -> BevyReqwestBuilder<'_> {
+        let mut ec = self
+            .commands
+            .spawn((DelayedRequest::new(req, delay), DespawnReqwestEntity));
+        Self::insert_defaults(&self.defaults, &mut ec);
+        BevyReqwestBuilder(ec)
+    }
+
+    /// Aborts the request running on the supplied [`Entity`], if there is one, or the one waiting to be
+    /// sent by [`BevyReqwest::send_after`].
+    ///
+    /// A [`ReqwestCancelledEvent`] is triggered on the entity, and if it was spawned by [`BevyReqwest::send`]
+    /// it will be despawned, just as if the request had finished. A [`PeriodicRequest`] keeps sending, remove it
+    /// to stop
+    pub fn cancel(&mut self, entity: Entity) {
+        if let Ok(mut ec) = self.commands.get_entity(entity) {
+            ec.try_remove::<(ReqwestInflight, DelayedRequest)>();
+        }
+    }
+
+    /// get access to the underlying ReqwestClient
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client.0
+    }
+
+    /// inserts the settings from [`ReqwestDefaults`], the builder can then override them
+    fn insert_defaults(defaults: &ReqwestDefaults, ec: &mut EntityCommands) {
+        if let Some(timeout) = defaults.timeout {
+            ec.insert(ReqwestTimeout::new(timeout));
+        }
+        if let Some(policy) = &defaults.retry {
+            ec.insert(policy.clone());
+        }
+    }
+
+    fn create_inflight_task(
+        &mut self,
+        request: reqwest::Request,
+        options: TaskOptions,
+    ) -> ReqwestInflight {
+        ReqwestInflight::start(
+            &self.client,
+            request,
+            options,
+            &self.defaults,
+            &mut self.queue,
+            &mut self.rate_limits,
+            &self.cache,
+        )
+    }
+}
+
+impl<'w, 's> Deref for BevyReqwest<'w, 's> {
+    type Target = reqwest::Client;
+
+    fn deref(&self) -> &Self::Target {
+        self.client()
+    }
+}
+
+#[derive(Component)]
+/// Marker component that is used to despawn an entity if the reqwest is finshed
+pub struct DespawnReqwestEntity;
+
+#[derive(Component)]
+/// Marker component that makes the request deliver its body as [`ReqwestChunkEvent`]s while it arrives,
+/// added by [`BevyReqwestBuilder::on_chunk`]
+pub struct StreamReqwestBody;
+
+#[derive(Component, Debug, Clone, Copy)]
+/// Overrides [`ReqwestPlugin::error_for_status`] for the request, added by
+/// [`BevyReqwestBuilder::error_for_status`]
+pub struct ErrorForStatus(pub bool);
+
+#[derive(Resource, Debug, Clone, Default)]
+/// The defaults applied to every request sent through [`BevyReqwest`], inserted by the [`ReqwestPlugin`]
+/// from its settings. Can be changed at runtime, which affects requests sent afterwards
+pub struct ReqwestDefaults {
+    /// see [`ReqwestPlugin::default_timeout`]
+    pub timeout: Option<Duration>,
+    /// see [`ReqwestPlugin::default_retry`]
+    pub retry: Option<RetryPolicy>,
+    /// see [`ReqwestPlugin::progress_event_interval`]
+    pub progress_event_interval: Duration,
+    /// see [`ReqwestPlugin::max_inflight`]
+    pub max_inflight: Option<usize>,
+    /// see [`ReqwestPlugin::max_inflight_per_host`]
+    pub max_inflight_per_host: Option<usize>,
+    /// see [`ReqwestPlugin::rate_limits`]
+    pub rate_limits: HashMap<String, RateLimit>,
+    /// see [`ReqwestPlugin::rate_limit_deferrals`]
+    pub rate_limit_deferrals: u32,
+    /// see [`ReqwestPlugin::rate_limit_max_block`]
+    pub rate_limit_max_block: Duration,
+    /// see [`ReqwestPlugin::error_for_status`]
+    pub error_for_status: bool,
+    /// see [`ReqwestPlugin::base_url`]
+    pub base_url: Option<String>,
+    /// answers the requests instead of the network, set by the [`ReqwestMockPlugin`]
     #[cfg(not(target_family = "wasm"))]
-    res: Task<Resp>,
+    pub mock: Option<ReqwestMock>,
+    /// records the requests and their responses, set by the [`ReqwestFixturePlugin`]
+    #[cfg(all(feature = "json", not(target_family = "wasm")))]
+    pub recorder: Option<ReqwestRecorder>,
+}
 
-    #[cfg(target_family = "wasm")]
-    res: Receiver<Resp>,
+#[derive(Component, Debug, Clone)]
+/// The time a request on this entity is allowed to take before it is aborted, and a [`ReqwestTimeoutEvent`]
+/// is triggered. Added by [`BevyReqwestBuilder::timeout`] or from [`ReqwestDefaults::timeout`]
+pub struct ReqwestTimeout {
+    timer: Timer,
 }
 
-impl ReqwestInflight {
-    fn poll(&mut self) -> Option<Resp> {
-        #[cfg(target_family = "wasm")]
-        if let Ok(v) = self.res.try_recv() {
-            Some(v)
-        } else {
-            None
+impl ReqwestTimeout {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timer: Timer::new(timeout, TimerMode::Once),
         }
+    }
+
+    /// the total time the request is allowed to take
+    pub fn duration(&self) -> Duration {
+        self.timer.duration()
+    }
+
+    /// the time the request has been running
+    pub fn elapsed(&self) -> Duration {
+        self.timer.elapsed()
+    }
+
+    /// starts over, for the next request sent from the same entity
+    fn reset(&mut self) {
+        self.timer.reset();
+    }
+}
+
+#[derive(Resource)]
+/// Wrapper around the ReqwestClient, that when inserted as a resource will start connection pools towards
+/// the hosts, and also allows all the configuration from the ReqwestLibrary such as setting default headers etc
+/// to be used inside the bevy application
+pub struct ReqwestClient(pub reqwest::Client);
+impl Default for ReqwestClient {
+    fn default() -> Self {
+        Self(reqwest::Client::new())
+    }
+}
 
+impl std::ops::Deref for ReqwestClient {
+    type Target = reqwest::Client;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl DerefMut for ReqwestClient {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// What the task running a request sends back to the ecs
+enum TaskMessage {
+    /// the status and headers have arrived
+    Head(Parts),
+    /// a part of the body has arrived
+    Chunk(bytes::Bytes),
+    /// the whole body has arrived
+    Finished,
+    /// the request failed, the head might have arrived before
+    Failed(reqwest::Error),
+    /// a part of the request body was handed to the connection, or a part of the response body was written
+    /// to the download file
+    #[cfg(not(target_family = "wasm"))]
+    Transferred(u64),
+    /// the response body has been written to this file, with this size
+    #[cfg(not(target_family = "wasm"))]
+    Downloaded(std::path::PathBuf, u64),
+    /// the response body could not be written to the download file
+    #[cfg(not(target_family = "wasm"))]
+    FileError(std::io::Error),
+    /// the response the cache has on disk was read, the request is handed back to be looked up again
+    #[cfg(not(target_family = "wasm"))]
+    Loaded(Box<(Option<LoadedEntry>, reqwest::Request)>),
+}
+
+/// How the task handles a request and its response, kept to send retries the same way
+#[derive(Clone, Default)]
+struct TaskOptions {
+    /// report the bytes of the request body as they are sent
+    count_upload: bool,
+    /// write the response body to this file instead of keeping it in memory
+    #[cfg(not(target_family = "wasm"))]
+    download: Option<std::path::PathBuf>,
+    /// answers the request instead of the network
+    #[cfg(not(target_family = "wasm"))]
+    mock: Option<ReqwestMock>,
+    /// records the request and its response
+    #[cfg(all(feature = "json", not(target_family = "wasm")))]
+    recorder: Option<ReqwestRecorder>,
+}
+
+impl TaskOptions {
+    /// takes the settings that apply to every request from the [`ReqwestDefaults`]
+    #[cfg_attr(target_family = "wasm", allow(unused_mut, unused_variables))]
+    fn with_defaults(mut self, defaults: &ReqwestDefaults) -> Self {
         #[cfg(not(target_family = "wasm"))]
-        if let Some(v) = future::block_on(future::poll_once(&mut self.res)) {
-            Some(v)
-        } else {
-            None
+        {
+            self.mock = defaults.mock.clone();
+        }
+        #[cfg(all(feature = "json", not(target_family = "wasm")))]
+        {
+            self.recorder = defaults.recorder.clone();
+        }
+        self
+    }
+}
+
+/// Sends the requests of a task over the network, or to the [`ReqwestMock`] if there is one
+#[derive(Clone)]
+pub(crate) struct Transport {
+    client: reqwest::Client,
+    #[cfg(not(target_family = "wasm"))]
+    mock: Option<ReqwestMock>,
+    #[cfg(all(feature = "json", not(target_family = "wasm")))]
+    recorder: Option<ReqwestRecorder>,
+}
+
+impl Transport {
+    /// sends to the [`ReqwestMock`] of the `defaults` if there is one, but does not record, for responses that
+    /// are read for as long as they last, such as the stream of an [`SseSubscription`]
+    #[cfg_attr(target_family = "wasm", allow(unused_variables))]
+    pub(crate) fn unrecorded(client: &reqwest::Client, defaults: &ReqwestDefaults) -> Self {
+        Self {
+            client: client.clone(),
+            #[cfg(not(target_family = "wasm"))]
+            mock: defaults.mock.clone(),
+            #[cfg(all(feature = "json", not(target_family = "wasm")))]
+            recorder: None,
+        }
+    }
+
+    pub(crate) async fn execute(
+        &self,
+        request: reqwest::Request,
+    ) -> reqwest::Result<reqwest::Response> {
+        #[cfg(all(feature = "json", not(target_family = "wasm")))]
+        if let Some(recorder) = &self.recorder {
+            return recorder
+                .execute(request, |request| self.send(request))
+                .await;
+        }
+        self.send(request).await
+    }
+
+    /// sends the request to the mock if there is one, or else over the network
+    async fn send(&self, request: reqwest::Request) -> reqwest::Result<reqwest::Response> {
+        #[cfg(not(target_family = "wasm"))]
+        if let Some(mock) = &self.mock {
+            return mock.execute(request).await;
         }
+        self.client.execute(request).await
     }
+}
+
+/// A future running on the [`IoTaskPool`], aborted when dropped
+pub(crate) struct TaskHandle {
+    #[cfg(not(target_family = "wasm"))]
+    _task: Task<()>,
 
+    // never sent on, closes the channel the detached task listens to when dropped
     #[cfg(target_family = "wasm")]
-    pub(crate) fn new(res: Receiver<Resp>, url: String) -> Self {
-        Self { url, res }
+    _abort: async_channel::Sender<()>,
+}
+
+impl TaskHandle {
+    pub(crate) fn spawn(
+        run: impl std::future::Future<Output = ()> + bevy::tasks::ConditionalSend + 'static,
+    ) -> Self {
+        let thread_pool = IoTaskPool::get();
+
+        // wasm implementation
+        #[cfg(target_family = "wasm")]
+        let task = {
+            // the task is detached, so it is raced against this channel closing, which happens when the
+            // handle is dropped. Dropping the fetch future aborts the browser request
+            let (abort, aborted) = async_channel::bounded::<()>(1);
+            thread_pool
+                .spawn(future::or(run, async move {
+                    aborted.recv().await.ok();
+                }))
+                .detach();
+            Self { _abort: abort }
+        };
+
+        // otherwise, dropping the task cancels it
+        #[cfg(not(target_family = "wasm"))]
+        let task = Self {
+            _task: thread_pool.spawn(async_compat::Compat::new(run)),
+        };
+        task
+    }
+}
+
+/// The async part of a single attempt at a request, aborted when dropped
+struct RequestTask {
+    messages: Receiver<TaskMessage>,
+    _task: TaskHandle,
+}
+
+impl RequestTask {
+    fn spawn(
+        client: reqwest::Client,
+        mut request: reqwest::Request,
+        options: TaskOptions,
+        upload: Option<Bytes>,
+    ) -> Self {
+        let (tx, messages) = unbounded();
+        let transport = Transport {
+            client,
+            #[cfg(not(target_family = "wasm"))]
+            mock: options.mock.clone(),
+            #[cfg(all(feature = "json", not(target_family = "wasm")))]
+            recorder: options.recorder.clone(),
+        };
+
+        #[cfg(not(target_family = "wasm"))]
+        if options.count_upload {
+            match options.mock {
+                // the mock takes the whole body at once
+                Some(_) => {
+                    let len = request
+                        .body()
+                        .and_then(reqwest::Body::as_bytes)
+                        .map(<[u8]>::len);
+                    tx.try_send(TaskMessage::Transferred(len.unwrap_or_default() as u64))
+                        .ok();
+                }
+                None => {
+                    if let Some(body) = upload {
+                        Self::count_upload(&mut request, body, tx.clone());
+                    }
+                }
+            }
+        }
+        // the browser does not report how much of the body has been sent
+        #[cfg(target_family = "wasm")]
+        let _ = (options.count_upload, upload);
+
+        let run = async move {
+            #[cfg(not(target_family = "wasm"))]
+            if let Some(path) = options.download {
+                download::run(transport, request, path, &tx).await;
+                return;
+            }
+            match transport.execute(request).await {
+                Ok(res) => Self::read_response(res, &tx).await,
+                Err(e) => {
+                    tx.try_send(TaskMessage::Failed(e)).ok();
+                }
+            }
+        };
+
+        Self {
+            messages,
+            _task: TaskHandle::spawn(run),
+        }
+    }
+
+    /// reads the response the cache has on disk for `request`, before the request is sent
+    #[cfg(not(target_family = "wasm"))]
+    fn load(load: DiskLoad, request: reqwest::Request) -> Self {
+        let (tx, messages) = unbounded();
+        let run = async move {
+            tx.try_send(TaskMessage::Loaded(Box::new((load.read(), request))))
+                .ok();
+        };
+        Self {
+            messages,
+            _task: TaskHandle::spawn(run),
+        }
+    }
+
+    /// sends the head and then the body of the response to the ecs
+    async fn read_response(res: reqwest::Response, tx: &Sender<TaskMessage>) {
+        let parts = Parts {
+            status: res.status(),
+            headers: res.headers().clone(),
+        };
+        tx.try_send(TaskMessage::Head(parts)).ok();
+        let mut body = res.bytes_stream();
+        while let Some(chunk) = body.next().await {
+            let message = match chunk {
+                Ok(chunk) => TaskMessage::Chunk(chunk),
+                Err(e) => TaskMessage::Failed(e),
+            };
+            // stop reading if nobody is listening anymore
+            if tx.try_send(message).is_err() {
+                return;
+            }
+        }
+        tx.try_send(TaskMessage::Finished).ok();
     }
 
+    /// replaces the body with a stream of slices of `body` that reports the bytes handed to the connection
     #[cfg(not(target_family = "wasm"))]
-    pub(crate) fn new(res: Task<Resp>, url: String) -> Self {
-        Self { url, res }
+    fn count_upload(request: &mut reqwest::Request, body: Bytes, tx: Sender<TaskMessage>) {
+        const CHUNK: usize = 64 * 1024;
+
+        let len = body.len();
+        let chunks = (0..len)
+            .step_by(CHUNK)
+            .map(move |start| body.slice(start..(start + CHUNK).min(len)));
+        let stream = futures_lite::stream::iter(chunks).map(move |chunk| {
+            tx.try_send(TaskMessage::Transferred(chunk.len() as u64))
+                .ok();
+            Ok::<_, std::io::Error>(chunk)
+        });
+        // otherwise the stream would be sent chunked
+        request
+            .headers_mut()
+            .entry(reqwest::header::CONTENT_LENGTH)
+            .or_insert(len.into());
+        *request.body_mut() = Some(reqwest::Body::wrap_stream(stream));
+    }
+
+    fn try_recv(&self) -> Option<TaskMessage> {
+        self.messages.try_recv().ok()
+    }
+}
+
+/// Dont touch these, its just to poll once every request, can be used to detect if there is an active request on the entity
+/// but should otherwise NOT be added/removed/changed by a user of this Crate
+///
+/// Removing this component before the request has finished, or despawning its entity, aborts the request
+/// and triggers a [`ReqwestCancelledEvent`]. The component stays on the entity while waiting to retry
+/// a failed attempt, and while waiting in the queue with a [`ReqwestQueued`] marker
+#[derive(Component)]
+#[component(
+    storage = "SparseSet",
+    on_replace = ReqwestInflight::on_replace,
+    on_remove = ReqwestInflight::on_remove
+)]
+pub struct ReqwestInflight {
+    // the url this request is handling as a string
+    pub(crate) url: String,
+    method: reqwest::Method,
+    // the host of the url, to limit the requests in flight per host
+    pub(crate) host: String,
+    // set once the result has been handled, so removing the component is not seen as a cancellation
+    done: bool,
+    // a copy of the request to send on retries, None if the body could not be cloned
+    request: Option<reqwest::Request>,
+    attempt: u32,
+    // when the request was sent, the first attempt might have waited in the queue
+    sent: Instant,
+    // the running attempt, None while queued or backing off before a retry
+    task: Option<RequestTask>,
+    // the attempt waiting in the queue to be dispatched
+    queued: Option<reqwest::Request>,
+    // the times the request was put back in the queue because the host was rate limiting
+    deferrals: u32,
+    // what the cache had for the request when it was started
+    cache: CacheLookup,
+    // the body of the stored response, once the server confirmed it with a 304
+    cached_body: Option<Bytes>,
+    backoff: Option<Timer>,
+    // the response of the running attempt so far
+    parts: Option<Parts>,
+    body: BytesMut,
+    // set once a chunk has been handed out as a ReqwestChunkEvent
+    streamed: bool,
+    // how every attempt is sent
+    options: TaskOptions,
+    // the body of the request, if its upload is reported
+    upload: Option<Bytes>,
+}
+
+impl ReqwestInflight {
+    /// the attempt that is running or about to run, starting at 1
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// the time since the request was sent, including the time it waited in the queue and between attempts
+    pub fn elapsed(&self) -> Duration {
+        self.sent.elapsed()
+    }
+
+    /// the size of the request body, if it is known
+    fn upload_size(&self) -> Option<u64> {
+        let body = self.request.as_ref()?.body()?.as_bytes()?;
+        Some(body.len() as u64)
+    }
+
+    /// true while waiting to send the request again after a failed attempt
+    pub fn is_backing_off(&self) -> bool {
+        self.backoff.is_some()
+    }
+
+    /// marks the request as handled, so removing the component is not seen as a cancellation
+    fn finish(&mut self) {
+        self.done = true;
+        self.task = None;
+    }
+
+    /// drops the current attempt and waits for `delay` before sending again, returns false if the request
+    /// cannot be sent again
+    fn retry_after(&mut self, delay: Duration) -> bool {
+        if self.request.is_none() {
+            return false;
+        }
+        self.task = None;
+        self.parts = None;
+        self.body.clear();
+        self.backoff = Some(Timer::new(delay, TimerMode::Once));
+        true
+    }
+
+    /// the stored response to deliver without sending the request, along with the request that revalidates it
+    fn take_cache_hit(&mut self) -> Option<(CachedResponse, Option<Box<reqwest::Request>>)> {
+        match std::mem::replace(&mut self.cache, CacheLookup::Miss) {
+            CacheLookup::Hit(response, revalidate) => Some((response, revalidate)),
+            lookup => {
+                self.cache = lookup;
+                None
+            }
+        }
+    }
+
+    /// the stored response that a `304 Not Modified` stands for
+    fn take_revalidated(&mut self) -> Option<CachedResponse> {
+        match std::mem::replace(&mut self.cache, CacheLookup::Miss) {
+            CacheLookup::Revalidate { response, .. } => Some(response),
+            lookup => {
+                self.cache = lookup;
+                None
+            }
+        }
+    }
+
+    /// a request revalidating a stored response in the background, which waits in the queue to be sent
+    fn revalidation(
+        request: reqwest::Request,
+        response: CachedResponse,
+        defaults: &ReqwestDefaults,
+    ) -> Self {
+        let options = TaskOptions::default().with_defaults(defaults);
+        let mut inflight = Self::new(request, options);
+        inflight.cache = CacheLookup::Revalidate {
+            response,
+            stale_if_error: false,
+        };
+        inflight
+    }
+
+    /// the stale response to deliver when the server could not be reached
+    fn take_stale_if_error(&mut self) -> Option<CachedResponse> {
+        match std::mem::replace(&mut self.cache, CacheLookup::Miss) {
+            CacheLookup::Revalidate {
+                response,
+                stale_if_error: true,
+            } => Some(response),
+            lookup => {
+                self.cache = lookup;
+                None
+            }
+        }
+    }
+
+    /// drops the current attempt and puts the request back in the queue, without counting it as an attempt,
+    /// returns false if the request cannot be sent again
+    fn defer(&mut self) -> bool {
+        let Some(copy) = self.request.as_ref().and_then(|r| r.try_clone()) else {
+            return false;
+        };
+        self.task = None;
+        self.parts = None;
+        self.body.clear();
+        self.queued = Some(copy);
+        self.deferrals += 1;
+        true
+    }
+
+    /// the task itself is aborted when the component is dropped, this only notifies the observers
+    fn on_replace(mut world: DeferredWorld, ctx: HookContext) {
+        let done = world
+            .get::<ReqwestInflight>(ctx.entity)
+            .is_none_or(|inflight| inflight.done);
+        if !done {
+            // trigger right away, so the observers on the entity are still around if it is being despawned
+            world.trigger(ReqwestCancelledEvent { entity: ctx.entity });
+        }
+    }
+
+    /// not on replace, the marker may already belong to the request replacing this one
+    fn on_remove(mut world: DeferredWorld, ctx: HookContext) {
+        if let Ok(mut ec) = world.commands().get_entity(ctx.entity) {
+            ec.try_remove::<ReqwestQueued>();
+        }
+    }
+
+    /// starts sending the request, or leaves it to be dispatched if there is no room under the limits of the
+    /// [`ReqwestDefaults`] for it, or the host is rate limited. Requests the [`ReqwestCache`] can answer are not
+    /// sent at all
+    fn start(
+        client: &reqwest::Client,
+        mut request: reqwest::Request,
+        options: TaskOptions,
+        defaults: &ReqwestDefaults,
+        queue: &mut ReqwestQueue,
+        rate_limits: &mut RateLimitState,
+        cache: &ReqwestCache,
+    ) -> Self {
+        #[cfg(not(target_family = "wasm"))]
+        let lookup = match options.download {
+            Some(_) => CacheLookup::Miss,
+            None => cache.lookup(&mut request),
+        };
+        #[cfg(target_family = "wasm")]
+        let lookup = cache.lookup(&mut request);
+
+        // the file is read on the task pool, the request is sent once it is known what the cache has
+        #[cfg(not(target_family = "wasm"))]
+        if let CacheLookup::Load(load) = lookup {
+            let mut inflight = Self::new(request, options.with_defaults(defaults));
+            inflight.task = inflight
+                .queued
+                .take()
+                .map(|request| RequestTask::load(load, request));
+            return inflight;
+        }
+
+        let mut inflight = Self::new(request, options.with_defaults(defaults));
+        if matches!(lookup, CacheLookup::Hit(..)) {
+            inflight.queued = None;
+        }
+        inflight.cache = lookup;
+        if inflight.is_queued() && queue.try_reserve_new(&inflight.host, defaults, rate_limits) {
+            inflight.dispatch(client, None, None);
+        }
+        inflight
+    }
+
+    /// sends the queued attempt, with the full timeout
+    fn dispatch(
+        &mut self,
+        client: &reqwest::Client,
+        timeout: Option<Mut<ReqwestTimeout>>,
+        progress: Option<Mut<ReqwestProgress>>,
+    ) {
+        let Some(request) = self.queued.take() else {
+            return;
+        };
+        self.task = Some(RequestTask::spawn(
+            client.clone(),
+            request,
+            self.options.clone(),
+            self.upload.clone(),
+        ));
+        if let Some(mut progress) = progress.filter(|_| self.options.count_upload) {
+            progress.start(TransferDirection::Upload, self.upload_size());
+        }
+        // every attempt gets the full timeout
+        if let Some(mut timeout) = timeout {
+            timeout.reset();
+        }
+    }
+
+    /// true while an attempt is being sent
+    fn is_running(&self) -> bool {
+        self.task.is_some()
+    }
+
+    /// true while waiting in the queue for room to be sent
+    pub fn is_queued(&self) -> bool {
+        self.queued.is_some()
+    }
+
+    /// inserts the component, along with a [`ReqwestQueued`] marker if it waits to be dispatched
+    fn insert_into(self, ec: &mut EntityCommands) {
+        match self.is_queued() {
+            true => ec.insert(ReqwestQueued::new()),
+            // a request on the entity that it replaces may have been waiting in the queue
+            false => ec.remove::<ReqwestQueued>(),
+        };
+        ec.insert(self);
+    }
+
+    fn new(mut request: reqwest::Request, options: TaskOptions) -> Self {
+        // only bodies created from bytes can be counted, they are copied once and shared by every attempt
+        let upload = request
+            .body()
+            .and_then(reqwest::Body::as_bytes)
+            .filter(|_| options.count_upload)
+            .map(Bytes::copy_from_slice);
+        if let Some(upload) = &upload {
+            // so the original buffer is not kept around next to the copy
+            *request.body_mut() = Some(upload.clone().into());
+        }
+        let url = request.url().to_string();
+        let host = request.url().host_str().unwrap_or_default().to_owned();
+        Self {
+            url,
+            method: request.method().clone(),
+            host,
+            done: false,
+            // keep a copy around, in case the request has to be sent again
+            request: request.try_clone(),
+            attempt: 1,
+            sent: Instant::now(),
+            task: None,
+            queued: Some(request),
+            deferrals: 0,
+            cache: CacheLookup::Miss,
+            cached_body: None,
+            backoff: None,
+            parts: None,
+            body: BytesMut::new(),
+            streamed: false,
+            options,
+            upload,
+        }
     }
 }
 
@@ -404,12 +2285,75 @@ pub struct ReqwestResponseEvent {
     bytes: bytes::Bytes,
     status: StatusCode,
     headers: HeaderMap,
+    from_cache: bool,
 }
 
 #[derive(EntityEvent, Debug)]
+/// triggered when a request failed without a response, after its retries
 pub struct ReqwestErrorEvent {
     pub entity: Entity,
     pub error: reqwest::Error,
+    /// what went wrong, see [`ReqwestErrorKind`]
+    pub kind: ReqwestErrorKind,
+    /// the url of the request
+    pub url: String,
+    /// the method of the request
+    pub method: reqwest::Method,
+    /// the attempt that failed, starting at 1
+    pub attempt: u32,
+    /// the time since the request was sent, including the time it waited in the queue and between attempts
+    pub elapsed: Duration,
+}
+
+#[derive(Clone, EntityEvent, Debug)]
+/// triggered instead of a [`ReqwestResponseEvent`] for a response with a `4xx` or `5xx` status, when
+/// [`ReqwestPlugin::error_for_status`] or [`BevyReqwestBuilder::error_for_status`] is enabled. Derefs to the
+/// response, to get at the error message of the server
+pub struct ReqwestStatusErrorEvent {
+    pub entity: Entity,
+    pub response: ReqwestResponseEvent,
+}
+
+impl Deref for ReqwestStatusErrorEvent {
+    type Target = ReqwestResponseEvent;
+
+    fn deref(&self) -> &Self::Target {
+        &self.response
+    }
+}
+
+#[derive(Clone, EntityEvent, Debug)]
+/// a part of the body of a streamed response, in the order they arrived
+pub struct ReqwestChunkEvent {
+    pub entity: Entity,
+    pub chunk: bytes::Bytes,
+}
+
+#[derive(Clone, EntityEvent, Debug)]
+/// triggered after the last [`ReqwestChunkEvent`] of a streamed response has been delivered
+pub struct ReqwestStreamEndEvent {
+    pub entity: Entity,
+    /// the `StatusCode` of the response
+    pub status: StatusCode,
+    /// the headers of the response
+    pub headers: HeaderMap,
+    /// true if the response came from the [`ReqwestCache`], see [`ReqwestResponseEvent::is_from_cache`]
+    pub from_cache: bool,
+}
+
+#[derive(Clone, EntityEvent, Debug)]
+/// triggered when a request is aborted because it did not finish within its [`ReqwestTimeout`]
+pub struct ReqwestTimeoutEvent {
+    pub entity: Entity,
+    /// the timeout that was exceeded
+    pub timeout: Duration,
+}
+
+#[derive(Clone, EntityEvent, Debug)]
+/// triggered when a request is aborted before it finished, either through [`BevyReqwest::cancel`],
+/// by removing the [`ReqwestInflight`] component or by despawning the entity
+pub struct ReqwestCancelledEvent {
+    pub entity: Entity,
 }
 
 impl ReqwestResponseEvent {
@@ -450,16 +2394,90 @@ impl ReqwestResponseEvent {
     pub fn response_headers(&self) -> &HeaderMap {
         &self.headers
     }
+
+    #[inline]
+    /// true if the body came from the [`ReqwestCache`], either without asking the server or after the server
+    /// answered with `304 Not Modified`
+    pub fn is_from_cache(&self) -> bool {
+        self.from_cache
+    }
 }
 
 #[cfg(feature = "json")]
 pub mod json {
-    use bevy::{ecs::entity::Entity, prelude::EntityEvent};
+    use std::ops::Deref;
+
+    use bevy::{
+        ecs::entity::Entity,
+        prelude::{Commands, Component, EntityEvent},
+    };
+    use serde::de::DeserializeOwned;
+
+    use crate::ReqwestResponseEvent;
+
     #[derive(EntityEvent)]
     pub struct JsonResponse<T> {
         pub entity: Entity,
         pub data: T,
     }
+
+    #[derive(EntityEvent, Debug)]
+    /// triggered instead of the deserialized response when its body could not be deserialized, derefs to the
+    /// response for its status, headers and body
+    pub struct JsonDeserializeErrorEvent {
+        pub entity: Entity,
+        pub error: serde_json::Error,
+        /// where in the body deserializing failed, such as `players[2].name`, or `.` for the body itself
+        pub path: String,
+        pub response: ReqwestResponseEvent,
+    }
+
+    impl Deref for JsonDeserializeErrorEvent {
+        type Target = ReqwestResponseEvent;
+
+        fn deref(&self) -> &Self::Target {
+            &self.response
+        }
+    }
+
+    /// deserializes the body of the response, keeping track of where it failed
+    pub(crate) fn deserialize<T: DeserializeOwned>(
+        response: &ReqwestResponseEvent,
+    ) -> Result<T, Box<JsonDeserializeErrorEvent>> {
+        let mut deserializer = serde_json::Deserializer::from_slice(response.body());
+        let result = serde_path_to_error::deserialize(&mut deserializer)
+            .map_err(|e| (e.path().to_string(), e.into_inner()))
+            .and_then(|data| {
+                // only whitespace may follow the value
+                deserializer.end().map_err(|e| (".".to_owned(), e))?;
+                Ok(data)
+            });
+        result.map_err(|(path, error)| {
+            Box::new(JsonDeserializeErrorEvent {
+                entity: response.entity,
+                error,
+                path,
+                response: response.clone(),
+            })
+        })
+    }
+
+    /// on a request with a handler for its [`JsonDeserializeErrorEvent`]
+    #[derive(Component)]
+    pub(crate) struct ObservesJsonErrors;
+
+    /// triggers the error on its request, and logs it as an error unless the request handles it
+    pub(crate) fn trigger_error(
+        commands: &mut Commands,
+        error: JsonDeserializeErrorEvent,
+        observed: bool,
+    ) {
+        match observed {
+            true => bevy::log::debug!("deserialization error at {}: {}", error.path, error.error),
+            false => bevy::log::error!("deserialization error at {}: {}", error.path, error.error),
+        }
+        commands.trigger(error);
+    }
 }
 
 impl ReqwestResponseEvent {
@@ -474,6 +2492,7 @@ impl ReqwestResponseEvent {
             bytes,
             status,
             headers,
+            from_cache: false,
         }
     }
 }
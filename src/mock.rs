@@ -0,0 +1,596 @@
+use std::{
+    fmt, io,
+    sync::{Arc, Mutex, OnceLock},
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use bytes::Bytes;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, CONTENT_LENGTH},
+    Method, ResponseBuilderExt, StatusCode, Url,
+};
+
+use crate::ReqwestDefaults;
+
+/// Answers the requests sent through [`BevyReqwest`](crate::BevyReqwest) with the canned responses of a
+/// [`ReqwestMock`] instead of sending them over the network, so the systems sending them can be tested
+/// without one.
+///
+/// The responses go through everything a real one does, such as the cache, the retries and the rate limits,
+/// and arrive through the same [`ReqwestResponseEvent`](crate::ReqwestResponseEvent) and
+/// [`ReqwestErrorEvent`](crate::ReqwestErrorEvent) observers. The streams of an
+/// [`SseSubscription`](crate::SseSubscription) are answered by the routes as well, but WebSocket connections are
+/// not mocked, they are always opened over the network.
+///
+/// The plugin has to be added after the [`ReqwestPlugin`](crate::ReqwestPlugin).
+///
+/// # Examples
+///
+/// ```
+/// use bevy::prelude::*;
+/// use bevy_mod_reqwest::*;
+///
+/// #[derive(Resource, Default)]
+/// struct Motd(String);
+///
+/// fn fetch_motd(mut client: BevyReqwest) {
+///     let request = client.get("https://example.com/motd").build().unwrap();
+///     client
+///         .send(request)
+///         .on_response(|trigger: On<ReqwestResponseEvent>, mut motd: ResMut<Motd>| {
+///             motd.0 = trigger.event().as_string().unwrap();
+///         });
+/// }
+///
+/// let mut app = App::new();
+/// app.add_plugins((MinimalPlugins, ReqwestPlugin::default(), ReqwestMockPlugin))
+///     .init_resource::<Motd>()
+///     .add_systems(Startup, fetch_motd);
+///
+/// let mock = app.world().resource::<ReqwestMock>().clone();
+/// mock.route(
+///     MockRoute::get("https://example.com/motd"),
+///     MockResponse::ok().body("hello"),
+/// );
+///
+/// while app.world().resource::<Motd>().0.is_empty() {
+///     app.update();
+/// }
+/// assert_eq!(app.world().resource::<Motd>().0, "hello");
+/// mock.assert_called(&MockRoute::get("https://example.com/*"), 1);
+/// ```
+#[derive(Default)]
+pub struct ReqwestMockPlugin;
+
+impl Plugin for ReqwestMockPlugin {
+    fn build(&self, app: &mut App) {
+        let mock = ReqwestMock::default();
+        match app.world_mut().get_resource_mut::<ReqwestDefaults>() {
+            Some(mut defaults) => defaults.mock = Some(mock.clone()),
+            None => {
+                warn!("ReqwestMockPlugin has to be added after the ReqwestPlugin to mock requests")
+            }
+        }
+        app.insert_resource(mock);
+    }
+}
+
+/// The routes answering the requests while the [`ReqwestMockPlugin`] is in use, and the requests that were
+/// sent to them. It is a handle, so a clone of it can be kept around to add routes and check the requests
+/// from outside the app.
+///
+/// Requests are answered by the first route that matches and still has answers left. Requests that no route
+/// matches get a `404 Not Found`, unless another response is set with [`ReqwestMock::fallback`].
+///
+/// The default headers of the [`ReqwestClient`](crate::ReqwestClient) are added when a request is sent over
+/// the network, so they are not part of the mocked requests
+#[derive(Resource, Clone, Default)]
+pub struct ReqwestMock(Arc<Mutex<MockState>>);
+
+#[derive(Default)]
+struct MockState {
+    routes: Vec<MockEntry>,
+    fallback: Option<MockResponse>,
+    requests: Vec<MockRequest>,
+}
+
+struct MockEntry {
+    route: MockRoute,
+    response: MockResponse,
+    answered: usize,
+}
+
+impl fmt::Debug for ReqwestMock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.0.lock().unwrap();
+        f.debug_struct("ReqwestMock")
+            .field("routes", &state.routes.len())
+            .field("requests", &state.requests.len())
+            .finish()
+    }
+}
+
+impl ReqwestMock {
+    /// answers the requests matching `route` with `response`
+    pub fn route(&self, route: MockRoute, response: MockResponse) -> &Self {
+        self.0.lock().unwrap().routes.push(MockEntry {
+            route,
+            response,
+            answered: 0,
+        });
+        self
+    }
+
+    /// answers the requests no route matches with `response`, instead of a `404 Not Found`
+    pub fn fallback(&self, response: MockResponse) -> &Self {
+        self.0.lock().unwrap().fallback = Some(response);
+        self
+    }
+
+    /// every request that was sent, in the order they were sent, including retries
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.0.lock().unwrap().requests.clone()
+    }
+
+    /// the number of requests sent that match `route`, no matter which route answered them
+    pub fn calls(&self, route: &MockRoute) -> usize {
+        let state = self.0.lock().unwrap();
+        state
+            .requests
+            .iter()
+            .filter(|request| route.matches(request))
+            .count()
+    }
+
+    /// panics unless exactly `times` of the requests sent match `route`
+    #[track_caller]
+    pub fn assert_called(&self, route: &MockRoute, times: usize) {
+        let calls = self.calls(route);
+        if calls != times {
+            let sent: Vec<String> = self
+                .requests()
+                .iter()
+                .map(|request| format!("{} {}", request.method, request.url))
+                .collect();
+            panic!("expected {times} requests matching {route:?}, but {calls} were sent, of: {sent:#?}");
+        }
+    }
+
+    /// removes the routes, the fallback and the requests sent so far
+    pub fn reset(&self) {
+        *self.0.lock().unwrap() = MockState::default();
+    }
+
+    /// records the request and answers it like the route it matches would
+    pub(crate) async fn execute(
+        &self,
+        request: reqwest::Request,
+    ) -> reqwest::Result<reqwest::Response> {
+        let request = MockRequest::from(request);
+        let response = {
+            let mut state = self.0.lock().unwrap();
+            state.requests.push(request.clone());
+            let matched = state
+                .routes
+                .iter_mut()
+                .find(|entry| entry.has_answers() && entry.route.matches(&request));
+            match matched {
+                Some(entry) => {
+                    entry.answered += 1;
+                    entry.response.clone()
+                }
+                None => {
+                    warn!("no mock route matches {} {}", request.method, request.url);
+                    state
+                        .fallback
+                        .clone()
+                        .unwrap_or_else(|| MockResponse::new(404))
+                }
+            }
+        };
+        debug!("mocking {} {}", request.method, request.url);
+        response.respond(request.url).await
+    }
+}
+
+impl MockEntry {
+    fn has_answers(&self) -> bool {
+        self.route.times.is_none_or(|times| self.answered < times)
+    }
+}
+
+/// A request sent while the [`ReqwestMockPlugin`] is in use
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: Method,
+    pub url: Url,
+    pub headers: HeaderMap,
+    /// `None` if the body is a stream, or if the request has no body
+    pub body: Option<Bytes>,
+}
+
+impl From<reqwest::Request> for MockRequest {
+    fn from(request: reqwest::Request) -> Self {
+        Self {
+            body: request
+                .body()
+                .and_then(reqwest::Body::as_bytes)
+                .map(Bytes::copy_from_slice),
+            method: request.method().clone(),
+            url: request.url().clone(),
+            headers: request.headers().clone(),
+        }
+    }
+}
+
+/// Which requests a [`ReqwestMock`] route answers, every part that is set has to match
+///
+/// # Examples
+///
+/// ```
+/// use bevy_mod_reqwest::MockRoute;
+///
+/// // a `*` in the url matches anything, including the query
+/// let route = MockRoute::post("https://api.example.com/users/*")
+///     .header("authorization", "Bearer token")
+///     .body_contains("\"name\"")
+///     .times(1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockRoute {
+    method: Option<Method>,
+    url: Option<String>,
+    headers: Vec<(HeaderName, HeaderValue)>,
+    body: Option<MockBody>,
+    times: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+enum MockBody {
+    Exact(Bytes),
+    Contains(String),
+    #[cfg(feature = "json")]
+    Json(serde_json::Value),
+}
+
+impl MockRoute {
+    /// matches every request
+    pub fn any() -> Self {
+        Self::default()
+    }
+
+    /// matches the requests with this method to urls matching `url`, in which a `*` matches any part
+    pub fn new(method: Method, url: &str) -> Self {
+        Self::any().method(method).url(url)
+    }
+
+    /// matches `GET` requests to urls matching `url`
+    pub fn get(url: &str) -> Self {
+        Self::new(Method::GET, url)
+    }
+
+    /// matches `POST` requests to urls matching `url`
+    pub fn post(url: &str) -> Self {
+        Self::new(Method::POST, url)
+    }
+
+    /// matches `PUT` requests to urls matching `url`
+    pub fn put(url: &str) -> Self {
+        Self::new(Method::PUT, url)
+    }
+
+    /// matches `PATCH` requests to urls matching `url`
+    pub fn patch(url: &str) -> Self {
+        Self::new(Method::PATCH, url)
+    }
+
+    /// matches `DELETE` requests to urls matching `url`
+    pub fn delete(url: &str) -> Self {
+        Self::new(Method::DELETE, url)
+    }
+
+    /// only matches requests with this method
+    pub fn method(mut self, method: Method) -> Self {
+        self.method = Some(method);
+        self
+    }
+
+    /// only matches requests to urls matching `url`, in which a `*` matches any part.
+    ///
+    /// The url is compared as reqwest formats it, so `https://example.com` matches `https://example.com/`
+    pub fn url(mut self, url: &str) -> Self {
+        // a pattern such as `*/users` is not a url itself
+        let url = Url::parse(url).map_or_else(|_| url.to_owned(), String::from);
+        self.url = Some(url);
+        self
+    }
+
+    /// only matches requests with this header, panics if it is not a valid header
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push(header(name, value));
+        self
+    }
+
+    /// only matches requests with exactly this body
+    pub fn body(mut self, body: impl Into<Bytes>) -> Self {
+        self.body = Some(MockBody::Exact(body.into()));
+        self
+    }
+
+    /// only matches requests with a body containing `text`
+    pub fn body_contains(mut self, text: impl Into<String>) -> Self {
+        self.body = Some(MockBody::Contains(text.into()));
+        self
+    }
+
+    /// only matches requests with a json body equal to `json`, no matter how it is formatted
+    #[cfg(feature = "json")]
+    pub fn json<T: serde::Serialize>(mut self, json: &T) -> Self {
+        let json = serde_json::to_value(json).expect("the json of the route");
+        self.body = Some(MockBody::Json(json));
+        self
+    }
+
+    /// answers at most `times` requests, after that the requests are left to the next route that matches
+    pub fn times(mut self, times: usize) -> Self {
+        self.times = Some(times);
+        self
+    }
+
+    /// true if the request matches every part of the route that is set
+    pub fn matches(&self, request: &MockRequest) -> bool {
+        self.method
+            .as_ref()
+            .is_none_or(|method| method == request.method)
+            && self
+                .url
+                .as_ref()
+                .is_none_or(|url| wildcard(url, request.url.as_str()))
+            && self.headers.iter().all(|(name, value)| {
+                request
+                    .headers
+                    .get_all(name)
+                    .iter()
+                    .any(|sent| sent == value)
+            })
+            && self.body.as_ref().is_none_or(|body| {
+                let Some(sent) = &request.body else {
+                    return false;
+                };
+                match body {
+                    MockBody::Exact(body) => body == sent,
+                    MockBody::Contains(text) => sent
+                        .windows(text.len().max(1))
+                        .any(|window| window == text.as_bytes()),
+                    #[cfg(feature = "json")]
+                    MockBody::Json(json) => serde_json::from_slice::<serde_json::Value>(sent)
+                        .is_ok_and(|sent| &sent == json),
+                }
+            })
+    }
+}
+
+/// true if `text` matches `pattern`, in which a `*` matches any part
+fn wildcard(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // there is no `*`
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+fn header(name: &str, value: &str) -> (HeaderName, HeaderValue) {
+    let name = HeaderName::try_from(name).expect("a valid header name");
+    let value = HeaderValue::try_from(value).expect("a valid header value");
+    (name, value)
+}
+
+/// What a [`ReqwestMock`] route answers with
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+    error: Option<MockError>,
+    delay: Duration,
+}
+
+/// The ways a mocked request can fail, without any response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockError {
+    /// the request timed out, [`reqwest::Error::is_timeout`] is true for it and its kind is
+    /// [`ReqwestErrorKind::Timeout`](crate::ReqwestErrorKind::Timeout)
+    Timeout,
+    /// no connection could be made, like when the device is offline. [`reqwest::Error::is_connect`] is true for
+    /// it and its kind is [`ReqwestErrorKind::Connect`](crate::ReqwestErrorKind::Connect), so it is retried by
+    /// default and a stale response from the [`ReqwestCache`](crate::ReqwestCache) is delivered in its place
+    Unreachable,
+    /// the connection was closed by the server while the response was read. [`reqwest::Error::is_decode`] is
+    /// true for it and its kind is [`ReqwestErrorKind::Body`](crate::ReqwestErrorKind::Body), just like for a
+    /// real response that breaks off, and it is retried by default
+    ConnectionReset,
+}
+
+impl MockResponse {
+    /// a response with this status and no body, panics if it is not a valid status code
+    pub fn new(status: u16) -> Self {
+        Self {
+            status: StatusCode::from_u16(status).expect("a valid status code"),
+            headers: HeaderMap::new(),
+            body: Bytes::new(),
+            error: None,
+            delay: Duration::ZERO,
+        }
+    }
+
+    /// a `200 OK` response with no body
+    pub fn ok() -> Self {
+        Self::new(200)
+    }
+
+    /// fails the request instead of responding
+    pub fn error(error: MockError) -> Self {
+        Self {
+            error: Some(error),
+            ..Self::ok()
+        }
+    }
+
+    /// adds a header to the response, panics if it is not a valid header
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        let (name, value) = header(name, value);
+        self.headers.append(name, value);
+        self
+    }
+
+    /// sets the body of the response
+    pub fn body(mut self, body: impl Into<Bytes>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// sets the body of the response to `json`, with a `Content-Type: application/json` header
+    #[cfg(feature = "json")]
+    pub fn json<T: serde::Serialize>(mut self, json: &T) -> Self {
+        self.body = serde_json::to_vec(json)
+            .expect("the json of the response")
+            .into();
+        self.headers.insert(
+            reqwest::header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        self
+    }
+
+    /// waits this long before responding, or failing
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    async fn respond(self, url: Url) -> reqwest::Result<reqwest::Response> {
+        if !self.delay.is_zero() {
+            tokio::time::sleep(self.delay).await;
+        }
+        if let Some(error) = self.error {
+            return Err(error.into_reqwest(url).await);
+        }
+        let mut headers = self.headers;
+        headers
+            .entry(CONTENT_LENGTH)
+            .or_insert_with(|| self.body.len().into());
+        let mut response = http::Response::builder()
+            .status(self.status)
+            .url(url)
+            .body(reqwest::Body::from(self.body))
+            .expect("a valid mocked response");
+        *response.headers_mut() = headers;
+        Ok(response.into())
+    }
+}
+
+impl MockError {
+    /// a reqwest error can only be made by reqwest, so this is the error of a request that cannot connect, or
+    /// of reading a body that fails
+    async fn into_reqwest(self, url: Url) -> reqwest::Error {
+        let error = match self {
+            Self::Timeout => io::Error::new(io::ErrorKind::TimedOut, "mocked timeout"),
+            Self::Unreachable => return unreachable_error(url).await,
+            Self::ConnectionReset => {
+                io::Error::new(io::ErrorKind::ConnectionReset, "mocked connection reset")
+            }
+        };
+        let body = futures_lite::stream::once(Err::<Bytes, _>(error));
+        let response =
+            reqwest::Response::from(http::Response::new(reqwest::Body::wrap_stream(body)));
+        match response.bytes().await {
+            Err(e) => e,
+            Ok(_) => unreachable!("the body fails"),
+        }
+    }
+}
+
+/// the error of connecting to a host that has no addresses, which fails without touching the network
+async fn unreachable_error(url: Url) -> reqwest::Error {
+    struct NoAddresses;
+
+    impl reqwest::dns::Resolve for NoAddresses {
+        fn resolve(&self, _: reqwest::dns::Name) -> reqwest::dns::Resolving {
+            Box::pin(async { Ok(Box::new(std::iter::empty()) as reqwest::dns::Addrs) })
+        }
+    }
+
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    let client = CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .no_proxy()
+            .dns_resolver(Arc::new(NoAddresses))
+            .build()
+            .expect("a client for the mocked errors")
+    });
+    // a host name, the resolver is not asked for ip addresses
+    match client.get("http://unreachable.invalid/").send().await {
+        Err(e) => e.with_url(url),
+        Ok(_) => unreachable!("there is nothing to connect to"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ReqwestErrorKind, RetryPolicy};
+
+    fn error(error: MockError) -> reqwest::Error {
+        let url = Url::parse("https://example.com/data").unwrap();
+        let response = MockResponse::error(error).respond(url);
+        match futures_lite::future::block_on(async_compat::Compat::new(response)) {
+            Err(e) => e,
+            Ok(_) => panic!("{error:?} responded"),
+        }
+    }
+
+    #[test]
+    fn timeout() {
+        let error = error(MockError::Timeout);
+        assert!(error.is_timeout());
+        let kind = ReqwestErrorKind::of(&error);
+        assert_eq!(kind, ReqwestErrorKind::Timeout);
+        assert!(RetryPolicy::default().retry_errors.contains(&kind));
+    }
+
+    #[test]
+    fn unreachable() {
+        let error = error(MockError::Unreachable);
+        assert!(error.is_connect());
+        assert!(!error.is_timeout() && !error.is_decode());
+        assert_eq!(error.url().unwrap().as_str(), "https://example.com/data");
+        let kind = ReqwestErrorKind::of(&error);
+        assert_eq!(kind, ReqwestErrorKind::Connect);
+        assert!(kind.is_offline());
+        assert!(RetryPolicy::default().retry_errors.contains(&kind));
+    }
+
+    #[test]
+    fn connection_reset() {
+        let error = error(MockError::ConnectionReset);
+        assert!(error.is_decode());
+        assert!(!error.is_connect() && !error.is_request() && !error.is_body());
+        let kind = ReqwestErrorKind::of(&error);
+        assert_eq!(kind, ReqwestErrorKind::Body);
+        assert!(!kind.is_offline());
+        assert!(RetryPolicy::default().retry_errors.contains(&kind));
+    }
+}
@@ -0,0 +1,293 @@
+use std::ops::Deref;
+
+use bevy::prelude::*;
+use reqwest::header::{HeaderValue, ACCEPT, CONTENT_TYPE};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{ReqwestResponseEvent, ReqwestStatusEvent, StatusCode};
+
+/// the content type of msgpack bodies
+pub const MSGPACK_CONTENT_TYPE: &str = "application/msgpack";
+
+#[derive(EntityEvent)]
+/// triggered with the deserialized body of the response, for [`BevyReqwestBuilder::on_msgpack_response`](crate::BevyReqwestBuilder::on_msgpack_response)
+pub struct MsgpackResponse<T> {
+    pub entity: Entity,
+    pub data: T,
+}
+
+#[derive(EntityEvent, Debug)]
+/// triggered instead of the [`MsgpackResponse`] when the body of the response could not be deserialized, derefs
+/// to the response for its status, headers and body
+pub struct MsgpackDeserializeErrorEvent {
+    pub entity: Entity,
+    pub error: rmp_serde::decode::Error,
+    pub response: ReqwestResponseEvent,
+}
+
+impl Deref for MsgpackDeserializeErrorEvent {
+    type Target = ReqwestResponseEvent;
+
+    fn deref(&self) -> &Self::Target {
+        &self.response
+    }
+}
+
+/// deserializes the body of the response
+pub(crate) fn deserialize<T: DeserializeOwned>(
+    response: &ReqwestResponseEvent,
+) -> Result<T, Box<MsgpackDeserializeErrorEvent>> {
+    rmp_serde::from_slice(response.body()).map_err(|error| {
+        Box::new(MsgpackDeserializeErrorEvent {
+            entity: response.entity,
+            error,
+            response: response.clone(),
+        })
+    })
+}
+
+/// on a request with a handler for its [`MsgpackDeserializeErrorEvent`]
+#[derive(Component)]
+pub(crate) struct ObservesMsgpackErrors;
+
+/// triggers the error on its request, and logs it as an error unless the request handles it
+pub(crate) fn trigger_error(
+    commands: &mut Commands,
+    error: MsgpackDeserializeErrorEvent,
+    observed: bool,
+) {
+    match observed {
+        true => bevy::log::debug!("deserialization error: {}", error.error),
+        false => bevy::log::error!("deserialization error: {}", error.error),
+    }
+    commands.trigger(error);
+}
+
+#[derive(EntityEvent, Debug)]
+/// The deserialized body of a response that matched a msgpack status handler such as
+/// [`BevyReqwestBuilder::on_msgpack_success`](crate::BevyReqwestBuilder::on_msgpack_success)
+pub struct MsgpackStatusResponse<T> {
+    #[event_target]
+    target: Entity,
+    /// the entity of the request
+    pub entity: Entity,
+    /// the `StatusCode` of the response
+    pub status: StatusCode,
+    pub data: T,
+}
+
+/// deserializes the responses that are triggered on a msgpack status handler
+pub(crate) fn deserialize_status_response<T: DeserializeOwned + Send + Sync + 'static>(
+    trigger: On<ReqwestStatusEvent>,
+    mut commands: Commands,
+    observed: Query<(), With<ObservesMsgpackErrors>>,
+) {
+    let evt = trigger.event();
+    match deserialize::<T>(evt) {
+        Ok(data) => commands.trigger(MsgpackStatusResponse {
+            target: trigger.event_target(),
+            entity: evt.entity,
+            status: evt.status(),
+            data,
+        }),
+        // triggered on the request, for its BevyReqwestBuilder::on_msgpack_error
+        Err(e) => trigger_error(&mut commands, *e, observed.contains(evt.entity)),
+    }
+}
+
+/// Adds msgpack bodies to [`reqwest::RequestBuilder`], like its `json` method
+///
+/// # Examples
+///
+/// ```
+/// use bevy::prelude::*;
+/// use bevy_mod_reqwest::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize)]
+/// struct Move {
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Board {
+///     turn: u32,
+/// }
+///
+/// fn send_move(mut client: BevyReqwest) -> Result {
+///     let request = client
+///         .post("https://example.com/moves")
+///         .msgpack(&Move { x: 1, y: 2 })?
+///         .build()?;
+///     client
+///         .send(request)
+///         .on_msgpack_response(|trigger: On<MsgpackResponse<Board>>| {
+///             info!("it is turn {}", trigger.event().data.turn);
+///         });
+///     Ok(())
+/// }
+/// ```
+pub trait MsgpackRequestBuilderExt: Sized {
+    /// Sends `body` encoded as msgpack, with the msgpack `Content-Type`, and asks for a msgpack response
+    /// with the `Accept` header. Structs are encoded as maps, so the fields can be found by name
+    fn msgpack<T: Serialize + ?Sized>(self, body: &T) -> Result<Self, rmp_serde::encode::Error>;
+
+    /// Asks for a msgpack response with the `Accept` header, for requests without a body. Not needed after
+    /// [`MsgpackRequestBuilderExt::msgpack`]
+    fn accept_msgpack(self) -> Self;
+}
+
+impl MsgpackRequestBuilderExt for reqwest::RequestBuilder {
+    fn msgpack<T: Serialize + ?Sized>(self, body: &T) -> Result<Self, rmp_serde::encode::Error> {
+        let body = rmp_serde::to_vec_named(body)?;
+        Ok(self
+            .header(CONTENT_TYPE, HeaderValue::from_static(MSGPACK_CONTENT_TYPE))
+            .accept_msgpack()
+            .body(body))
+    }
+
+    fn accept_msgpack(self) -> Self {
+        self.header(ACCEPT, HeaderValue::from_static(MSGPACK_CONTENT_TYPE))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
+
+    use bevy::{
+        ecs::system::RunSystemOnce,
+        log::{
+            tracing::{self, Event, Level, Subscriber},
+            tracing_subscriber::{
+                layer::{Context, SubscriberExt},
+                Layer, Registry,
+            },
+        },
+    };
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::{
+        BevyReqwest, MockResponse, MockRoute, ReqwestMock, ReqwestMockPlugin, ReqwestPlugin,
+    };
+
+    const URL: &str = "https://example.com/moves";
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct Move {
+        x: i32,
+        y: i32,
+    }
+
+    /// what the observers of the request saw
+    #[derive(Resource, Default)]
+    struct Seen(Vec<String>);
+
+    fn app() -> (App, ReqwestMock) {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, ReqwestPlugin::default(), ReqwestMockPlugin))
+            .init_resource::<Seen>();
+        let mock = app.world().resource::<ReqwestMock>().clone();
+        (app, mock)
+    }
+
+    /// sends `Move { x: 1, y: 2 }` as msgpack, with a handler for the errors if `handle_errors` is true
+    fn send(app: &mut App, handle_errors: bool) {
+        app.world_mut()
+            .run_system_once(move |mut client: BevyReqwest| {
+                let request = client
+                    .post(URL)
+                    .msgpack(&Move { x: 1, y: 2 })
+                    .unwrap()
+                    .build()
+                    .unwrap();
+                let builder = client.send(request).on_msgpack_response(
+                    |trigger: On<MsgpackResponse<Move>>, mut seen: ResMut<Seen>| {
+                        seen.0.push(format!("{:?}", trigger.event().data));
+                    },
+                );
+                if handle_errors {
+                    builder.on_msgpack_error(
+                        |trigger: On<MsgpackDeserializeErrorEvent>, mut seen: ResMut<Seen>| {
+                            seen.0.push(format!("error {}", trigger.event().status()));
+                        },
+                    );
+                }
+            })
+            .unwrap();
+    }
+
+    /// updates the app until `done` is true
+    fn update_until(app: &mut App, done: impl Fn(&App) -> bool) {
+        let start = Instant::now();
+        while !done(app) {
+            assert!(start.elapsed() < Duration::from_secs(5), "not done in time");
+            std::thread::sleep(Duration::from_millis(5));
+            app.update();
+        }
+    }
+
+    /// collects the levels of the events logged by this module
+    struct Levels(Arc<Mutex<Vec<Level>>>);
+
+    impl<S: Subscriber> Layer<S> for Levels {
+        fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+            if event.metadata().target() == "bevy_mod_reqwest::msgpack" {
+                self.0.lock().unwrap().push(*event.metadata().level());
+            }
+        }
+    }
+
+    /// the levels this module logs at when the response is not msgpack, and what the observers saw
+    fn logged(handle_errors: bool) -> (Vec<Level>, Vec<String>) {
+        let (mut app, mock) = app();
+        mock.route(MockRoute::post(URL), MockResponse::ok().body("not msgpack"));
+        let levels = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = Registry::default().with(Levels(levels.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            send(&mut app, handle_errors);
+            update_until(&mut app, |_| !levels.lock().unwrap().is_empty());
+        });
+        let levels = levels.lock().unwrap().clone();
+        (levels, app.world().resource::<Seen>().0.clone())
+    }
+
+    #[test]
+    fn sends_a_named_map_and_asks_for_msgpack() {
+        let (mut app, mock) = app();
+        let response = rmp_serde::to_vec(&Move { x: 3, y: 4 }).unwrap();
+        mock.route(MockRoute::post(URL), MockResponse::ok().body(response));
+
+        send(&mut app, true);
+        update_until(&mut app, |app| !app.world().resource::<Seen>().0.is_empty());
+
+        assert_eq!(app.world().resource::<Seen>().0, ["Move { x: 3, y: 4 }"]);
+
+        let request = &mock.requests()[0];
+        assert_eq!(request.headers[CONTENT_TYPE], MSGPACK_CONTENT_TYPE);
+        assert_eq!(request.headers[ACCEPT], MSGPACK_CONTENT_TYPE);
+        // the fields are encoded by name
+        let body = request.body.as_ref().unwrap();
+        let fields: HashMap<String, i32> = rmp_serde::from_slice(body).unwrap();
+        assert_eq!(fields, HashMap::from([("x".into(), 1), ("y".into(), 2)]));
+    }
+
+    #[test]
+    fn a_handled_deserialize_error_is_only_logged_for_debugging() {
+        assert_eq!(
+            logged(true),
+            (vec![Level::DEBUG], vec!["error 200 OK".into()])
+        );
+    }
+
+    #[test]
+    fn an_unhandled_deserialize_error_is_logged_as_an_error() {
+        assert_eq!(logged(false), (vec![Level::ERROR], vec![]));
+    }
+}
@@ -0,0 +1,134 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use reqwest::header::{HeaderMap, CONTENT_LENGTH, CONTENT_RANGE};
+
+/// how long the transfer rate is measured over before it is updated
+const RATE_WINDOW: Duration = Duration::from_millis(500);
+
+/// Which way the body in a [`ReqwestProgress`] is going
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransferDirection {
+    /// the request body is being sent
+    Upload,
+    /// the response body is being received
+    #[default]
+    Download,
+}
+
+/// The progress of the transfer of a request, kept up to date on the request entity while it is in flight.
+/// Insert it using [`BevyReqwestBuilder::track_progress`](crate::BevyReqwestBuilder::track_progress) or
+/// [`BevyReqwestBuilder::on_progress`](crate::BevyReqwestBuilder::on_progress).
+///
+/// Requests sent with [`BevyReqwest::send_with_progress`](crate::BevyReqwest::send_with_progress) first report
+/// the upload of the request body, and then the download of the response body. On wasm the browser does not
+/// report upload progress, so the upload goes from nothing to done when the response arrives
+#[derive(Component, Debug, Clone, Default)]
+pub struct ReqwestProgress {
+    direction: TransferDirection,
+    transferred: u64,
+    total: Option<u64>,
+    bytes_per_second: f32,
+    // bytes and time since the rate was last updated
+    window_bytes: u64,
+    window_time: Duration,
+    // time since the last ReqwestProgressEvent, and if there is anything new to report
+    since_event: Duration,
+    dirty: bool,
+}
+
+impl ReqwestProgress {
+    /// if the request body is being sent, or the response body received
+    pub fn direction(&self) -> TransferDirection {
+        self.direction
+    }
+
+    /// the number of bytes of the body transferred so far
+    pub fn transferred(&self) -> u64 {
+        self.transferred
+    }
+
+    /// the size of the body, if it is known up front
+    pub fn total(&self) -> Option<u64> {
+        self.total
+    }
+
+    /// the transfer rate measured over the last half second
+    pub fn bytes_per_second(&self) -> f32 {
+        self.bytes_per_second
+    }
+
+    /// the transferred part of the body between 0 and 1, if the size is known
+    pub fn fraction(&self) -> Option<f32> {
+        match self.total? {
+            0 => Some(1.0),
+            total => Some((self.transferred as f32 / total as f32).min(1.0)),
+        }
+    }
+
+    /// starts over, for a new attempt at the request or once the response starts arriving
+    pub(crate) fn start(&mut self, direction: TransferDirection, total: Option<u64>) {
+        *self = Self {
+            direction,
+            total,
+            dirty: true,
+            ..Default::default()
+        };
+    }
+
+    /// starts over using the size of the response, from the `Content-Range` of a partial response or the
+    /// `Content-Length`
+    pub(crate) fn start_response(&mut self, headers: &HeaderMap) {
+        let complete = headers
+            .get(CONTENT_RANGE)
+            .and_then(|range| range.to_str().ok()?.rsplit_once('/')?.1.parse().ok());
+        let total = complete.or_else(|| {
+            headers
+                .get(CONTENT_LENGTH)
+                .and_then(|len| len.to_str().ok()?.parse().ok())
+        });
+        self.start(TransferDirection::Download, total);
+    }
+
+    /// marks the whole body as transferred, for when the bytes could not be counted
+    #[cfg(target_family = "wasm")]
+    pub(crate) fn complete(&mut self) {
+        self.transferred = self.total.unwrap_or(self.transferred);
+        self.dirty = true;
+    }
+
+    pub(crate) fn add(&mut self, bytes: u64) {
+        self.transferred += bytes;
+        self.window_bytes += bytes;
+        self.dirty = true;
+    }
+
+    /// advances the rate measurement, returns true if a [`ReqwestProgressEvent`] is due
+    pub(crate) fn tick(&mut self, delta: Duration, event_interval: Duration) -> bool {
+        self.window_time += delta;
+        if self.window_time >= RATE_WINDOW {
+            self.bytes_per_second = self.window_bytes as f32 / self.window_time.as_secs_f32();
+            self.window_bytes = 0;
+            self.window_time = Duration::ZERO;
+        }
+        self.since_event += delta;
+        self.dirty && self.since_event >= event_interval
+    }
+
+    /// returns true if there is progress that has not been reported in a [`ReqwestProgressEvent`] yet
+    pub(crate) fn take_event(&mut self) -> bool {
+        let dirty = self.dirty;
+        self.dirty = false;
+        self.since_event = Duration::ZERO;
+        dirty
+    }
+}
+
+#[derive(Clone, EntityEvent, Debug)]
+/// triggered when a request with a [`ReqwestProgress`] has made progress, at most once every
+/// [`ReqwestPlugin::progress_event_interval`](crate::ReqwestPlugin::progress_event_interval), and once
+/// more when the transfer is complete
+pub struct ReqwestProgressEvent {
+    pub entity: Entity,
+    pub progress: ReqwestProgress,
+}
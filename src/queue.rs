@@ -0,0 +1,176 @@
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    RateLimitState, ReqwestClient, ReqwestDefaults, ReqwestInflight, ReqwestProgress,
+    ReqwestTimeout,
+};
+
+/// the order requests were queued in, shared by all worlds
+static QUEUE_ORDER: AtomicU64 = AtomicU64::new(0);
+
+/// Marks a request that is waiting for room under [`ReqwestPlugin::max_inflight`](crate::ReqwestPlugin::max_inflight)
+/// or [`ReqwestPlugin::max_inflight_per_host`](crate::ReqwestPlugin::max_inflight_per_host), or for the
+/// [`RateLimit`](crate::RateLimit) of its host, before it is sent. Removed once the request is dispatched
+#[derive(Component, Debug, Clone)]
+#[component(storage = "SparseSet")]
+pub struct ReqwestQueued {
+    order: u64,
+}
+
+impl ReqwestQueued {
+    pub(crate) fn new() -> Self {
+        Self {
+            order: QUEUE_ORDER.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+}
+
+/// The priority of a request waiting in the queue, queued requests with a higher priority are always sent
+/// first, and requests with the same priority in the order they were queued. Set it using
+/// [`BevyReqwestBuilder::priority`](crate::BevyReqwestBuilder::priority), requests without it have
+/// [`RequestPriority::NORMAL`].
+///
+/// The priority can be changed while the request is queued by mutating the component, for example to send the
+/// thumbnails that scrolled into view first
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RequestPriority(pub i32);
+
+impl RequestPriority {
+    pub const LOW: Self = Self(-100);
+    pub const NORMAL: Self = Self(0);
+    pub const HIGH: Self = Self(100);
+    pub const CRITICAL: Self = Self(200);
+}
+
+/// The number of requests waiting in the queue and in flight, updated every frame by the [`ReqwestPlugin`]
+/// after it has dispatched the queued requests, and whenever a request is sent right away
+///
+/// [`ReqwestPlugin`]: crate::ReqwestPlugin
+#[derive(Resource, Debug, Clone, Default)]
+pub struct ReqwestQueue {
+    queued: usize,
+    in_flight: usize,
+    queued_per_host: HashMap<String, usize>,
+    in_flight_per_host: HashMap<String, usize>,
+}
+
+impl ReqwestQueue {
+    /// the number of requests waiting to be sent
+    pub fn queued(&self) -> usize {
+        self.queued
+    }
+
+    /// the number of requests being sent, not counting the ones waiting to retry
+    pub fn in_flight(&self) -> usize {
+        self.in_flight
+    }
+
+    /// the number of requests to `host` waiting to be sent
+    pub fn queued_for_host(&self, host: &str) -> usize {
+        self.queued_per_host.get(host).copied().unwrap_or_default()
+    }
+
+    /// the number of requests to `host` being sent
+    pub fn in_flight_for_host(&self, host: &str) -> usize {
+        self.in_flight_per_host
+            .get(host)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// takes a place in flight for a request to `host`, and a token from its [`RateLimit`](crate::RateLimit),
+    /// returns false if there is no room and the request has to wait in the queue
+    pub(crate) fn try_reserve(
+        &mut self,
+        host: &str,
+        defaults: &ReqwestDefaults,
+        rate_limits: &mut RateLimitState,
+    ) -> bool {
+        let room = defaults.max_inflight.is_none_or(|max| self.in_flight < max)
+            && defaults
+                .max_inflight_per_host
+                .is_none_or(|max| self.in_flight_for_host(host) < max);
+        // the rate limit is checked last, so no token is taken for a request that cannot be sent
+        if !room || !rate_limits.try_acquire(host, defaults.rate_limits.get(host)) {
+            return false;
+        }
+        self.in_flight += 1;
+        *self.in_flight_per_host.entry(host.to_owned()).or_default() += 1;
+        true
+    }
+
+    /// like [`ReqwestQueue::try_reserve`] for a request that is not in the queue yet, which waits behind the
+    /// queued requests to the same host
+    pub(crate) fn try_reserve_new(
+        &mut self,
+        host: &str,
+        defaults: &ReqwestDefaults,
+        rate_limits: &mut RateLimitState,
+    ) -> bool {
+        self.queued_for_host(host) == 0 && self.try_reserve(host, defaults, rate_limits)
+    }
+}
+
+/// sends the queued requests, by priority and then in the order they were queued, for as long as there is room
+/// under the limits
+#[allow(clippy::type_complexity)]
+pub(crate) fn dispatch_queued_requests(
+    mut commands: Commands,
+    client: Res<ReqwestClient>,
+    defaults: Res<ReqwestDefaults>,
+    time: Res<Time<Real>>,
+    mut queue: ResMut<ReqwestQueue>,
+    mut rate_limits: ResMut<RateLimitState>,
+    mut requests: Query<(
+        Entity,
+        &mut ReqwestInflight,
+        Option<&ReqwestQueued>,
+        Option<&RequestPriority>,
+        Option<&mut ReqwestTimeout>,
+        Option<&mut ReqwestProgress>,
+    )>,
+) {
+    rate_limits.set_now(time.elapsed());
+    let mut counts = ReqwestQueue::default();
+    let mut waiting = Vec::new();
+    for (entity, request, queued, priority, _, _) in requests.iter() {
+        if request.is_running() {
+            counts.in_flight += 1;
+            *counts
+                .in_flight_per_host
+                .entry(request.host.clone())
+                .or_default() += 1;
+        } else if let Some(queued) = queued.filter(|_| request.is_queued()) {
+            let priority = priority.copied().unwrap_or_default();
+            waiting.push((Reverse(priority), queued.order, entity));
+        }
+    }
+    waiting.sort_unstable();
+
+    for (_, _, entity) in waiting {
+        let Ok((entity, mut request, _, _, timeout, progress)) = requests.get_mut(entity) else {
+            continue;
+        };
+        if !counts.try_reserve(&request.host, &defaults, &mut rate_limits) {
+            counts.queued += 1;
+            *counts
+                .queued_per_host
+                .entry(request.host.clone())
+                .or_default() += 1;
+            continue;
+        }
+        debug!("dispatching queued request {entity:?}");
+        request.dispatch(&client, timeout, progress);
+        if let Ok(mut ec) = commands.get_entity(entity) {
+            ec.remove::<ReqwestQueued>();
+        }
+    }
+
+    *queue = counts;
+}
@@ -0,0 +1,327 @@
+use std::{collections::HashMap, time::Duration};
+
+use bevy::prelude::*;
+use reqwest::{header::HeaderMap, StatusCode};
+
+use crate::retry_after;
+
+/// A token bucket limiting how often requests are sent to a host, set per host in
+/// [`ReqwestPlugin::rate_limits`](crate::ReqwestPlugin::rate_limits).
+///
+/// Up to `burst` requests can be sent right away, after that one request every `per / burst`. Requests over
+/// the limit wait in the queue with a [`ReqwestQueued`](crate::ReqwestQueued) marker
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    /// the number of requests that can be sent at once
+    pub burst: u32,
+    /// the time it takes to be able to send `burst` requests again
+    pub per: Duration,
+}
+
+impl RateLimit {
+    /// allows `requests` requests every `per`
+    pub fn new(requests: u32, per: Duration) -> Self {
+        Self {
+            burst: requests,
+            per,
+        }
+    }
+
+    /// allows `requests` requests every second
+    pub fn per_second(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(1))
+    }
+}
+
+/// What is known about the rate limits of a host, from the responses it sent and the [`RateLimit`] set for it
+#[derive(Debug, Clone, Default)]
+pub struct HostRateLimit {
+    /// from the `X-RateLimit-Limit` header of the last response that had it
+    pub limit: Option<u64>,
+    /// from the `X-RateLimit-Remaining` header of the last response that had it
+    pub remaining: Option<u64>,
+    // when the remaining requests are reset, as the real time of the app
+    reset_at: Option<Duration>,
+    // no requests are sent until then, after a Retry-After
+    blocked_until: Option<Duration>,
+    // the token bucket of the configured RateLimit, filled up the first time it is used
+    tokens: Option<f32>,
+    refilled_at: Duration,
+}
+
+/// The rate limits of every host requests have been sent to, updated from the `X-RateLimit-Limit`,
+/// `X-RateLimit-Remaining` and `X-RateLimit-Reset` headers of the responses, so systems can back off on their
+/// own. Times are measured using [`Time<Real>`]
+#[derive(Resource, Debug, Clone, Default)]
+pub struct RateLimitState {
+    hosts: HashMap<String, HostRateLimit>,
+    // the real time of the app when the state was last updated
+    now: Duration,
+}
+
+impl RateLimitState {
+    /// what is known about the rate limits of `host`
+    pub fn host(&self, host: &str) -> Option<&HostRateLimit> {
+        self.hosts.get(host)
+    }
+
+    /// the number of requests `host` said are left before it starts limiting
+    pub fn remaining(&self, host: &str) -> Option<u64> {
+        self.hosts.get(host)?.remaining
+    }
+
+    /// the time until the remaining requests of `host` are reset
+    pub fn reset_in(&self, host: &str) -> Option<Duration> {
+        let reset_at = self.hosts.get(host)?.reset_at?;
+        Some(reset_at.saturating_sub(self.now))
+    }
+
+    /// the time until requests are sent to `host` again, after it answered with a `Retry-After` header
+    pub fn blocked_for(&self, host: &str) -> Option<Duration> {
+        let blocked_until = self.hosts.get(host)?.blocked_until?;
+        Some(blocked_until.saturating_sub(self.now)).filter(|blocked| !blocked.is_zero())
+    }
+
+    /// true while no requests are sent to `host` because it answered with a `Retry-After` header
+    pub fn is_blocked(&self, host: &str) -> bool {
+        self.blocked_for(host).is_some()
+    }
+
+    pub(crate) fn set_now(&mut self, now: Duration) {
+        self.now = now;
+    }
+
+    /// takes a token from the bucket of `host`, returns false if the request has to wait
+    pub(crate) fn try_acquire(&mut self, host: &str, limit: Option<&RateLimit>) -> bool {
+        if self.is_blocked(host) {
+            return false;
+        }
+        let Some(limit) = limit else {
+            return true;
+        };
+        let now = self.now;
+        let state = self.hosts.entry(host.to_owned()).or_default();
+        let burst = limit.burst.max(1) as f32;
+        let elapsed = now.saturating_sub(state.refilled_at).as_secs_f32();
+        let refill = elapsed * burst / limit.per.as_secs_f32().max(f32::EPSILON);
+        let tokens = state
+            .tokens
+            .map_or(burst, |tokens| (tokens + refill).min(burst));
+        state.refilled_at = now;
+        if tokens < 1.0 {
+            state.tokens = Some(tokens);
+            return false;
+        }
+        state.tokens = Some(tokens - 1.0);
+        true
+    }
+
+    /// reads the rate limit headers of a response from `host`, and returns how long to wait before sending to
+    /// it again if it asked to, at most `max_block`
+    pub(crate) fn update(
+        &mut self,
+        host: &str,
+        status: StatusCode,
+        headers: &HeaderMap,
+        max_block: Duration,
+    ) -> Option<Duration> {
+        let now = self.now;
+        let state = self.hosts.entry(host.to_owned()).or_default();
+        if let Some(limit) = header_number(headers, "x-ratelimit-limit") {
+            state.limit = Some(limit);
+        }
+        if let Some(remaining) = header_number(headers, "x-ratelimit-remaining") {
+            state.remaining = Some(remaining);
+        }
+        if let Some(reset) = reset_delay(headers) {
+            state.reset_at = Some(now + reset);
+        }
+        let limited =
+            status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE;
+        let delay = retry_after(headers).filter(|_| limited)?.min(max_block);
+        let until = now + delay;
+        state.blocked_until = Some(
+            state
+                .blocked_until
+                .map_or(until, |blocked| blocked.max(until)),
+        );
+        Some(delay)
+    }
+}
+
+fn header_number(headers: &HeaderMap, name: &str) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+/// the `X-RateLimit-Reset` header is either the seconds until the reset, or the unix time of it
+fn reset_delay(headers: &HeaderMap) -> Option<Duration> {
+    // anything later than a year is taken as a unix time
+    const UNIX_TIME: u64 = 365 * 24 * 60 * 60;
+
+    let reset = header_number(headers, "x-ratelimit-reset")?;
+    if reset < UNIX_TIME {
+        return Some(Duration::from_secs(reset));
+    }
+    // there is no system clock to compare the time to on wasm
+    #[cfg(not(target_family = "wasm"))]
+    {
+        let reset = std::time::UNIX_EPOCH + Duration::from_secs(reset);
+        Some(
+            reset
+                .duration_since(std::time::SystemTime::now())
+                .unwrap_or_default(),
+        )
+    }
+    #[cfg(target_family = "wasm")]
+    None
+}
+
+#[derive(Clone, EntityEvent, Debug)]
+/// triggered when a request was answered with `429 Too Many Requests` or `503 Service Unavailable` and a
+/// `Retry-After` header, and is put back in the queue until the host can be sent to again
+pub struct ReqwestRateLimitedEvent {
+    pub entity: Entity,
+    /// the host that limited the request
+    pub host: String,
+    /// the time until requests are sent to the host again
+    pub delay: Duration,
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::{HeaderName, HeaderValue};
+
+    use super::*;
+
+    const MAX_BLOCK: Duration = Duration::from_secs(60);
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| {
+                (
+                    HeaderName::from_static(name),
+                    HeaderValue::from_str(value).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reads_the_rate_limit_headers() {
+        let mut state = RateLimitState::default();
+        let headers = headers(&[
+            ("x-ratelimit-limit", "100"),
+            ("x-ratelimit-remaining", " 42 "),
+            ("x-ratelimit-reset", "30"),
+        ]);
+        assert_eq!(
+            state.update("a.com", StatusCode::OK, &headers, MAX_BLOCK),
+            None
+        );
+        let host = state.host("a.com").unwrap();
+        assert_eq!(host.limit, Some(100));
+        assert_eq!(host.remaining, Some(42));
+        assert_eq!(state.reset_in("a.com"), Some(Duration::from_secs(30)));
+        assert_eq!(state.remaining("b.com"), None);
+    }
+
+    #[test]
+    fn keeps_the_last_known_values() {
+        let mut state = RateLimitState::default();
+        let first = headers(&[("x-ratelimit-limit", "10"), ("x-ratelimit-remaining", "9")]);
+        state.update("a.com", StatusCode::OK, &first, MAX_BLOCK);
+        let second = headers(&[("x-ratelimit-remaining", "not a number")]);
+        state.update("a.com", StatusCode::OK, &second, MAX_BLOCK);
+        let host = state.host("a.com").unwrap();
+        assert_eq!(host.limit, Some(10));
+        assert_eq!(host.remaining, Some(9));
+    }
+
+    #[test]
+    fn reset_counts_down_with_the_time_of_the_app() {
+        let mut state = RateLimitState::default();
+        state.set_now(Duration::from_secs(100));
+        state.update(
+            "a.com",
+            StatusCode::OK,
+            &headers(&[("x-ratelimit-reset", "60")]),
+            MAX_BLOCK,
+        );
+        state.set_now(Duration::from_secs(120));
+        assert_eq!(state.reset_in("a.com"), Some(Duration::from_secs(40)));
+        state.set_now(Duration::from_secs(200));
+        assert_eq!(state.reset_in("a.com"), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn reset_as_unix_time() {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap();
+        let reset = (now + Duration::from_secs(90)).as_secs().to_string();
+        let delay = reset_delay(&headers(&[("x-ratelimit-reset", &reset)])).unwrap();
+        assert!(delay > Duration::from_secs(85) && delay <= Duration::from_secs(90));
+        // a reset in the past is right away
+        let reset = (now - Duration::from_secs(90)).as_secs().to_string();
+        let delay = reset_delay(&headers(&[("x-ratelimit-reset", &reset)]));
+        assert_eq!(delay, Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn retry_after_blocks_the_host() {
+        let mut state = RateLimitState::default();
+        let retry = headers(&[("retry-after", "5")]);
+        // only limiting statuses block
+        assert_eq!(
+            state.update("a.com", StatusCode::OK, &retry, MAX_BLOCK),
+            None
+        );
+        assert!(!state.is_blocked("a.com"));
+
+        let delay = state.update("a.com", StatusCode::TOO_MANY_REQUESTS, &retry, MAX_BLOCK);
+        assert_eq!(delay, Some(Duration::from_secs(5)));
+        assert!(!state.try_acquire("a.com", None));
+        // a shorter Retry-After does not unblock it early
+        let shorter = headers(&[("retry-after", "1")]);
+        state.update(
+            "a.com",
+            StatusCode::SERVICE_UNAVAILABLE,
+            &shorter,
+            MAX_BLOCK,
+        );
+        state.set_now(Duration::from_secs(2));
+        assert_eq!(state.blocked_for("a.com"), Some(Duration::from_secs(3)));
+        assert!(!state.is_blocked("b.com"));
+
+        state.set_now(Duration::from_secs(5));
+        assert!(!state.is_blocked("a.com"));
+        assert!(state.try_acquire("a.com", None));
+    }
+
+    #[test]
+    fn retry_after_is_clamped_to_the_max_block() {
+        let mut state = RateLimitState::default();
+        let day = headers(&[("retry-after", "86400")]);
+        let delay = state.update("a.com", StatusCode::TOO_MANY_REQUESTS, &day, MAX_BLOCK);
+        assert_eq!(delay, Some(MAX_BLOCK));
+        assert_eq!(state.blocked_for("a.com"), Some(MAX_BLOCK));
+        state.set_now(MAX_BLOCK);
+        assert!(!state.is_blocked("a.com"));
+    }
+
+    #[test]
+    fn token_bucket_refills_over_time() {
+        let mut state = RateLimitState::default();
+        let limit = RateLimit::per_second(2);
+        assert!(state.try_acquire("a.com", Some(&limit)));
+        assert!(state.try_acquire("a.com", Some(&limit)));
+        assert!(!state.try_acquire("a.com", Some(&limit)));
+        // one request every half second
+        state.set_now(Duration::from_millis(500));
+        assert!(state.try_acquire("a.com", Some(&limit)));
+        assert!(!state.try_acquire("a.com", Some(&limit)));
+        // other hosts have their own bucket
+        assert!(state.try_acquire("b.com", Some(&limit)));
+    }
+}
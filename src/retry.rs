@@ -0,0 +1,294 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+
+use crate::ReqwestErrorKind;
+
+/// Describes if and when a failed request should be sent again, insert it on the request entity using
+/// [`BevyReqwestBuilder::retry`](crate::BevyReqwestBuilder::retry) or for every request using
+/// [`ReqwestPlugin::default_retry`](crate::ReqwestPlugin::default_retry).
+///
+/// The waits between attempts grow exponentially, and are measured using [`Time<Real>`], so nothing is blocking
+/// while waiting. Requests with a body that cannot be cloned, such as streams, are never retried.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use bevy_mod_reqwest::RetryPolicy;
+/// let policy = RetryPolicy {
+///     max_attempts: 5,
+///     initial_backoff: Duration::from_secs(1),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Component, Debug, Clone)]
+pub struct RetryPolicy {
+    /// the total number of attempts, including the first one
+    pub max_attempts: u32,
+    /// the wait before the second attempt
+    pub initial_backoff: Duration,
+    /// the longest wait between two attempts, before jitter is applied
+    pub max_backoff: Duration,
+    /// how much the wait grows for every attempt
+    pub multiplier: f32,
+    /// the fraction, between 0 and 1, of every wait that is randomized, to avoid many clients retrying in lockstep
+    pub jitter: f32,
+    /// responses with these status codes are treated as failed attempts
+    pub retry_statuses: Vec<StatusCode>,
+    /// requests that failed without a response with an error of these kinds are sent again, by default the
+    /// errors of connecting, sending or receiving, such as a response that breaks off, but not errors in building
+    /// the request or decoding the response
+    pub retry_errors: Vec<ReqwestErrorKind>,
+    /// also retry attempts that ran out of their [`ReqwestTimeout`](crate::ReqwestTimeout)
+    pub retry_timeouts: bool,
+    /// wait as long as the `Retry-After` header of a retried response asks for, instead of the backoff, up to
+    /// [`RetryPolicy::max_backoff`]
+    pub respect_retry_after: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.5,
+            retry_statuses: vec![
+                StatusCode::REQUEST_TIMEOUT,
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            retry_errors: vec![
+                ReqwestErrorKind::Timeout,
+                ReqwestErrorKind::Connect,
+                ReqwestErrorKind::Dns,
+                ReqwestErrorKind::Body,
+                // the browser does not say why a fetch failed, so failing to connect is a request error there
+                #[cfg(target_family = "wasm")]
+                ReqwestErrorKind::Request,
+            ],
+            retry_timeouts: true,
+            respect_retry_after: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// the wait before sending the attempt following `attempt`, with jitter applied
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let secs = self.initial_backoff.as_secs_f32() * self.multiplier.max(1.0).powi(exponent);
+        let backoff = Duration::try_from_secs_f32(secs)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff);
+        let jitter = self.jitter.clamp(0.0, 1.0) * fastrand::f32();
+        backoff.mul_f32(1.0 - jitter)
+    }
+
+    /// the wait before the next attempt after `attempt` got a response with `status`, or `None` if it should
+    /// not be retried
+    pub(crate) fn status_retry_delay(
+        &self,
+        attempt: u32,
+        status: StatusCode,
+        headers: &HeaderMap,
+    ) -> Option<Duration> {
+        if attempt >= self.max_attempts || !self.retry_statuses.contains(&status) {
+            return None;
+        }
+        let retry_after = retry_after(headers).filter(|_| self.respect_retry_after);
+        // a server asking for more than the longest wait does not get to park the request for that long
+        Some(retry_after.map_or_else(
+            || self.backoff(attempt),
+            |delay| delay.min(self.max_backoff),
+        ))
+    }
+
+    /// the wait before the next attempt after `attempt` failed with an error of `kind`, or `None` if it should not
+    /// be retried
+    pub(crate) fn error_retry_delay(
+        &self,
+        attempt: u32,
+        kind: ReqwestErrorKind,
+    ) -> Option<Duration> {
+        (attempt < self.max_attempts && self.retry_errors.contains(&kind))
+            .then(|| self.backoff(attempt))
+    }
+
+    /// the wait before the next attempt after `attempt` timed out, or `None` if it should not be retried
+    pub(crate) fn timeout_retry_delay(&self, attempt: u32) -> Option<Duration> {
+        (self.retry_timeouts && attempt < self.max_attempts).then(|| self.backoff(attempt))
+    }
+}
+
+/// parses the `Retry-After` header, given either as seconds or as a date
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    // there is no system clock to compare the date to on wasm
+    #[cfg(not(target_family = "wasm"))]
+    if let Ok(date) = httpdate::parse_http_date(value) {
+        return Some(
+            date.duration_since(std::time::SystemTime::now())
+                .unwrap_or_default(),
+        );
+    }
+    None
+}
+
+#[derive(Clone, EntityEvent, Debug)]
+/// triggered when an attempt failed and the request will be sent again after `delay`
+pub struct ReqwestRetryEvent {
+    pub entity: Entity,
+    /// the attempt that will be sent next, starting at 2 for the first retry
+    pub attempt: u32,
+    /// the time until the next attempt is sent
+    pub delay: Duration,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            jitter: 0.0,
+            ..Default::default()
+        }
+    }
+
+    fn retry_after_headers(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn backoff_grows_up_to_max() {
+        let policy = policy();
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_secs(1));
+        assert_eq!(policy.backoff(3), Duration::from_secs(2));
+        assert_eq!(policy.backoff(100), policy.max_backoff);
+    }
+
+    #[test]
+    fn retry_after_is_respected() {
+        let delay = policy().status_retry_delay(
+            1,
+            StatusCode::SERVICE_UNAVAILABLE,
+            &retry_after_headers("7"),
+        );
+        assert_eq!(delay, Some(Duration::from_secs(7)));
+    }
+
+    #[test]
+    fn retry_after_is_clamped_to_max_backoff() {
+        let policy = policy();
+        let delay = policy.status_retry_delay(
+            1,
+            StatusCode::SERVICE_UNAVAILABLE,
+            &retry_after_headers("86400"),
+        );
+        assert_eq!(delay, Some(policy.max_backoff));
+    }
+
+    #[test]
+    fn no_retry_for_other_statuses_or_last_attempt() {
+        let policy = policy();
+        let headers = HeaderMap::new();
+        assert_eq!(
+            policy.status_retry_delay(1, StatusCode::NOT_FOUND, &headers),
+            None
+        );
+        assert_eq!(
+            policy.status_retry_delay(3, StatusCode::SERVICE_UNAVAILABLE, &headers),
+            None
+        );
+    }
+
+    #[test]
+    fn parses_retry_after() {
+        assert_eq!(
+            retry_after(&retry_after_headers(" 120 ")),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            retry_after(&retry_after_headers("Wed, 21 Oct 2015 07:28:00 GMT")),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(retry_after(&retry_after_headers("soon")), None);
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+    }
+
+    /// the error of reading a body that sends `sent` and then fails with `error`
+    #[cfg(not(target_family = "wasm"))]
+    fn body_error(sent: &'static [u8], error: std::io::Error) -> reqwest::Error {
+        use futures_lite::StreamExt;
+
+        let body = futures_lite::stream::iter([Ok(bytes::Bytes::from_static(sent)), Err(error)]);
+        let response =
+            reqwest::Response::from(http::Response::new(reqwest::Body::wrap_stream(body)));
+        let read = async {
+            let mut stream = response.bytes_stream();
+            // the part before the break arrives
+            assert_eq!(stream.next().await.unwrap().unwrap(), sent);
+            stream.next().await.unwrap().unwrap_err()
+        };
+        futures_lite::future::block_on(async_compat::Compat::new(read))
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    #[test]
+    fn retries_bodies_that_break_off() {
+        use std::io::{Error, ErrorKind};
+
+        let reset = body_error(b"{\"players\": [", ErrorKind::ConnectionReset.into());
+        assert!(reset.is_decode());
+        assert_eq!(ReqwestErrorKind::of(&reset), ReqwestErrorKind::Body);
+        let eof = body_error(
+            b"partial",
+            Error::new(ErrorKind::UnexpectedEof, "early eof"),
+        );
+        assert_eq!(
+            policy().error_retry_delay(1, ReqwestErrorKind::of(&eof)),
+            Some(policy().backoff(1))
+        );
+    }
+
+    #[cfg(all(feature = "json", not(target_family = "wasm")))]
+    #[test]
+    fn does_not_retry_bodies_that_do_not_decode() {
+        let response = reqwest::Response::from(http::Response::new(reqwest::Body::from("{")));
+        let read = response.json::<serde_json::Value>();
+        let error = futures_lite::future::block_on(read).unwrap_err();
+        assert!(error.is_decode());
+        let kind = ReqwestErrorKind::of(&error);
+        assert_eq!(policy().error_retry_delay(1, kind), None);
+    }
+
+    #[test]
+    fn retries_the_kinds_of_errors_it_is_set_to() {
+        let policy = RetryPolicy {
+            retry_errors: vec![ReqwestErrorKind::Tls],
+            ..policy()
+        };
+        assert_eq!(
+            policy.error_retry_delay(1, ReqwestErrorKind::Tls),
+            Some(policy.backoff(1))
+        );
+        assert_eq!(policy.error_retry_delay(1, ReqwestErrorKind::Timeout), None);
+        // not after the last attempt
+        assert_eq!(policy.error_retry_delay(3, ReqwestErrorKind::Tls), None);
+    }
+}
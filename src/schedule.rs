@@ -0,0 +1,188 @@
+use std::time::Duration;
+
+use bevy::{
+    ecs::{lifecycle::HookContext, world::DeferredWorld},
+    prelude::*,
+};
+
+use crate::{
+    RateLimitState, ReqwestCache, ReqwestCancelledEvent, ReqwestClient, ReqwestDefaults,
+    ReqwestInflight, ReqwestQueue, ReqwestTimeout, TaskOptions,
+};
+
+/// Sends a request from the same entity every `interval`, send it using
+/// [`BevyReqwest::send_periodic`](crate::BevyReqwest::send_periodic) to register the observers once for every
+/// response.
+///
+/// A new request is only sent once the previous one has finished, an interval that passes while it is still in
+/// flight is skipped. The interval is measured using [`Time<Virtual>`], so no requests are sent while it is
+/// paused. Remove the component or despawn the entity to stop sending. Requests with a body that cannot be
+/// cloned, such as streams, can not be sent periodically
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use bevy::prelude::*;
+/// use bevy_mod_reqwest::*;
+///
+/// fn poll_scores(mut client: BevyReqwest) {
+///     let request = client.get("https://example.com/scores").build().unwrap();
+///     client
+///         .send_periodic(PeriodicRequest::new(request, Duration::from_secs(5)))
+///         .on_response(|trigger: On<ReqwestResponseEvent>| {
+///             bevy::log::info!("scores: {:?}", trigger.event().as_str());
+///         });
+/// }
+/// ```
+#[derive(Component)]
+pub struct PeriodicRequest {
+    request: reqwest::Request,
+    interval: Duration,
+    // runs once for the initial delay, then repeats every interval
+    timer: Timer,
+}
+
+impl PeriodicRequest {
+    /// sends `request` right away, and then every `interval`
+    pub fn new(request: reqwest::Request, interval: Duration) -> Self {
+        Self {
+            request,
+            interval,
+            timer: Timer::new(Duration::ZERO, TimerMode::Once),
+        }
+    }
+
+    /// waits for `delay` before sending the first request
+    pub fn with_initial_delay(mut self, delay: Duration) -> Self {
+        self.timer = Timer::new(delay, TimerMode::Once);
+        self
+    }
+
+    /// the time between two requests
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// the time until the next request is sent
+    pub fn remaining(&self) -> Duration {
+        self.timer.remaining()
+    }
+
+    /// the request that is sent every interval
+    pub fn request(&self) -> &reqwest::Request {
+        &self.request
+    }
+}
+
+/// Sends a request once after a delay, added by [`BevyReqwest::send_after`](crate::BevyReqwest::send_after).
+///
+/// The delay is measured using [`Time<Virtual>`], so it does not pass while it is paused. Removing the component
+/// before the request was sent, or despawning the entity, triggers a [`ReqwestCancelledEvent`]
+#[derive(Component)]
+#[component(on_replace = DelayedRequest::on_replace)]
+pub struct DelayedRequest {
+    // taken once it is sent
+    request: Option<reqwest::Request>,
+    timer: Timer,
+}
+
+impl DelayedRequest {
+    pub fn new(request: reqwest::Request, delay: Duration) -> Self {
+        Self {
+            request: Some(request),
+            timer: Timer::new(delay, TimerMode::Once),
+        }
+    }
+
+    /// the time until the request is sent
+    pub fn remaining(&self) -> Duration {
+        self.timer.remaining()
+    }
+
+    fn on_replace(mut world: DeferredWorld, ctx: HookContext) {
+        let sent = world
+            .get::<DelayedRequest>(ctx.entity)
+            .is_none_or(|delayed| delayed.request.is_none());
+        if !sent {
+            // trigger right away, so the observers on the entity are still around if it is being despawned
+            world.trigger(ReqwestCancelledEvent { entity: ctx.entity });
+        }
+    }
+}
+
+/// sends the periodic and delayed requests that are due
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+pub(crate) fn send_scheduled_requests(
+    mut commands: Commands,
+    time: Res<Time<Virtual>>,
+    client: Res<ReqwestClient>,
+    defaults: Res<ReqwestDefaults>,
+    mut queue: ResMut<ReqwestQueue>,
+    mut rate_limits: ResMut<RateLimitState>,
+    cache: Res<ReqwestCache>,
+    mut periodic: Query<(
+        Entity,
+        &mut PeriodicRequest,
+        Has<ReqwestInflight>,
+        Option<&mut ReqwestTimeout>,
+    )>,
+    mut delayed: Query<(Entity, &mut DelayedRequest)>,
+) {
+    for (entity, mut periodic, inflight, timeout) in periodic.iter_mut() {
+        if !periodic.timer.tick(time.delta()).is_finished() {
+            continue;
+        }
+        if periodic.timer.mode() == TimerMode::Once {
+            let interval = periodic.interval;
+            periodic.timer = Timer::new(interval, TimerMode::Repeating);
+        }
+        // never overlap with the previous request, or with the retries of it
+        if inflight {
+            debug!("skipping periodic request of {entity:?}, the previous one is still in flight");
+            continue;
+        }
+        let Some(request) = periodic.request.try_clone() else {
+            warn!("the body of the periodic request of {entity:?} can not be cloned");
+            continue;
+        };
+        let inflight = ReqwestInflight::start(
+            &client,
+            request,
+            TaskOptions::default(),
+            &defaults,
+            &mut queue,
+            &mut rate_limits,
+            &cache,
+        );
+        // the timeout is for every request, not for all of them together
+        if let Some(mut timeout) = timeout {
+            timeout.reset();
+        }
+        if let Ok(mut ec) = commands.get_entity(entity) {
+            inflight.insert_into(&mut ec);
+        }
+    }
+
+    for (entity, mut delayed) in delayed.iter_mut() {
+        if !delayed.timer.tick(time.delta()).is_finished() {
+            continue;
+        }
+        let Some(request) = delayed.request.take() else {
+            continue;
+        };
+        let inflight = ReqwestInflight::start(
+            &client,
+            request,
+            TaskOptions::default(),
+            &defaults,
+            &mut queue,
+            &mut rate_limits,
+            &cache,
+        );
+        if let Ok(mut ec) = commands.get_entity(entity) {
+            inflight.insert_into(&mut ec);
+            ec.remove::<DelayedRequest>();
+        }
+    }
+}
@@ -0,0 +1,616 @@
+use std::{fmt, time::Duration};
+
+use async_channel::{unbounded, Receiver, Sender};
+use bevy::prelude::*;
+use futures_lite::StreamExt;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, CACHE_CONTROL, CONTENT_TYPE},
+    StatusCode,
+};
+
+use crate::{ReqwestClient, ReqwestDefaults, TaskHandle, Transport};
+
+/// the name of the header that tells the server the last message that was received before reconnecting
+const LAST_EVENT_ID: HeaderName = HeaderName::from_static("last-event-id");
+/// a line, or the data of a message, larger than this drops the connection, instead of being buffered
+const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// A subscription to a `text/event-stream` endpoint, the stream is opened as soon as the component is spawned
+/// and every message is triggered as an [`SseMessageEvent`] on its entity.
+///
+/// When the stream ends or fails, it is opened again after [`SseSubscription::reconnect_delay`], sending the
+/// id of the last message in the `Last-Event-ID` header. A response that is not successful or not a
+/// `text/event-stream` closes the subscription for good, just like `204 No Content`, and triggers an
+/// [`SseClosedEvent`]. A line, or the data of a message, larger than 64 MiB drops the connection with an
+/// [`SseErrorEvent`]. Despawning the entity, or removing the component, closes the stream.
+///
+/// # Examples
+///
+/// ```
+/// use bevy::prelude::*;
+/// use bevy_mod_reqwest::*;
+///
+/// fn subscribe(mut commands: Commands) {
+///     commands
+///         .spawn(SseSubscription::new("https://example.com/announcements"))
+///         .observe(|trigger: On<SseMessageEvent>| {
+///             bevy::log::info!("{}: {}", trigger.event, trigger.data);
+///         });
+/// }
+/// ```
+#[derive(Component)]
+pub struct SseSubscription {
+    url: String,
+    headers: HeaderMap,
+    reconnect_delay: Duration,
+    last_event_id: Option<String>,
+    // the open stream, None while waiting to reconnect
+    connection: Option<SseConnection>,
+    reconnect: Option<Timer>,
+    // set when the server asked not to reconnect, or refused the stream
+    closed: bool,
+}
+
+impl SseSubscription {
+    /// subscribes to the stream at `url`
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            headers: HeaderMap::new(),
+            reconnect_delay: Duration::from_secs(3),
+            last_event_id: None,
+            connection: None,
+            reconnect: None,
+            closed: false,
+        }
+    }
+
+    /// adds a header to every request opening the stream, such as `Authorization`
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// sets the wait before reconnecting, until the server sets another one
+    pub fn with_reconnect_delay(mut self, delay: Duration) -> Self {
+        self.reconnect_delay = delay;
+        self
+    }
+
+    /// continues a stream after the message with this id
+    pub fn with_last_event_id(mut self, id: impl Into<String>) -> Self {
+        self.last_event_id = Some(id.into());
+        self
+    }
+
+    /// the url of the stream
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// the wait before reconnecting, as last set by the server using the `retry` field
+    pub fn reconnect_delay(&self) -> Duration {
+        self.reconnect_delay
+    }
+
+    /// the id of the last message, sent as `Last-Event-ID` when reconnecting
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.last_event_id.as_deref()
+    }
+
+    /// true while the stream is open, and not waiting to reconnect
+    pub fn is_connected(&self) -> bool {
+        self.connection.as_ref().is_some_and(|c| c.open)
+    }
+
+    /// true if the server answered with `204 No Content`, or refused the stream, which means the stream will not
+    /// be opened again, see [`SseCloseReason`]
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    fn connect(&mut self, transport: Transport) {
+        let mut request = transport
+            .client
+            .get(&self.url)
+            .headers(self.headers.clone())
+            .header(ACCEPT, "text/event-stream")
+            .header(CACHE_CONTROL, "no-cache");
+        if let Some(id) = &self.last_event_id {
+            request = request.header(LAST_EVENT_ID, id);
+        }
+        let (tx, messages) = unbounded();
+        let task = TaskHandle::spawn(async move {
+            match request.build() {
+                Ok(request) => run(transport, request, &tx).await,
+                Err(e) => {
+                    tx.try_send(SseTaskMessage::Failed(SseError::Request(e)))
+                        .ok();
+                }
+            }
+        });
+        self.connection = Some(SseConnection {
+            messages,
+            open: false,
+            _task: task,
+        });
+    }
+
+    /// drops the stream and waits before opening it again
+    fn disconnect(&mut self) {
+        self.connection = None;
+        self.reconnect = Some(Timer::new(self.reconnect_delay, TimerMode::Once));
+    }
+}
+
+/// The task reading an open stream, aborted when dropped
+struct SseConnection {
+    messages: Receiver<SseTaskMessage>,
+    // set once the server accepted the request
+    open: bool,
+    _task: TaskHandle,
+}
+
+/// What the task reading a stream sends back to the ecs
+enum SseTaskMessage {
+    /// the server accepted the request, the messages follow
+    Open,
+    /// a complete message
+    Message(SseMessage),
+    /// a block without data that set the id of the last message
+    Id(String),
+    /// the server set the wait before reconnecting
+    Retry(Duration),
+    /// the server closed the stream
+    Ended,
+    /// the server asked not to reconnect, or refused the stream
+    Closed(SseCloseReason),
+    /// the request or the stream failed
+    Failed(SseError),
+}
+
+struct SseMessage {
+    event: String,
+    data: String,
+    id: Option<String>,
+}
+
+async fn run(transport: Transport, request: reqwest::Request, tx: &Sender<SseTaskMessage>) {
+    let res = match transport.execute(request).await {
+        Ok(res) => res,
+        Err(e) => {
+            tx.try_send(SseTaskMessage::Failed(SseError::Request(e)))
+                .ok();
+            return;
+        }
+    };
+    if let Some(reason) = SseCloseReason::of(&res) {
+        tx.try_send(SseTaskMessage::Closed(reason)).ok();
+        return;
+    }
+    tx.try_send(SseTaskMessage::Open).ok();
+
+    let mut parser = SseParser::new(MAX_MESSAGE_SIZE);
+    let mut body = res.bytes_stream();
+    while let Some(chunk) = body.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                tx.try_send(SseTaskMessage::Failed(SseError::Request(e)))
+                    .ok();
+                return;
+            }
+        };
+        for message in parser.feed(&chunk) {
+            let failed = matches!(message, SseTaskMessage::Failed(_));
+            // stop reading if nobody is listening anymore, or the message got too large
+            if tx.try_send(message).is_err() || failed {
+                return;
+            }
+        }
+    }
+    tx.try_send(SseTaskMessage::Ended).ok();
+}
+
+/// Splits the bytes of a stream into lines, and the lines into messages
+#[derive(Default)]
+struct SseParser {
+    // the most bytes of the current line and the data of the pending message together
+    max_size: usize,
+    line: Vec<u8>,
+    // a line ended with \r, so a \n right after it belongs to the same line break
+    after_cr: bool,
+    // the byte order mark is only allowed at the start of the stream
+    started: bool,
+    event: String,
+    data: String,
+    has_data: bool,
+    id: Option<String>,
+    id_changed: bool,
+}
+
+impl SseParser {
+    fn new(max_size: usize) -> Self {
+        Self {
+            max_size,
+            ..default()
+        }
+    }
+
+    /// the messages completed by `bytes`, ending with [`SseTaskMessage::Failed`] if a line or message got larger
+    /// than the limit, after which nothing more should be fed
+    fn feed(&mut self, bytes: &[u8]) -> Vec<SseTaskMessage> {
+        let mut messages = Vec::new();
+        for &byte in bytes {
+            match byte {
+                b'\n' if self.after_cr => self.after_cr = false,
+                b'\r' | b'\n' => {
+                    self.after_cr = byte == b'\r';
+                    let line = std::mem::take(&mut self.line);
+                    messages.extend(self.parse_line(&line));
+                }
+                _ => {
+                    self.after_cr = false;
+                    if self.line.len() + self.data.len() >= self.max_size {
+                        messages.push(SseTaskMessage::Failed(SseError::TooLarge(self.max_size)));
+                        return messages;
+                    }
+                    self.line.push(byte);
+                }
+            }
+        }
+        messages
+    }
+
+    fn parse_line(&mut self, line: &[u8]) -> Option<SseTaskMessage> {
+        let mut line = String::from_utf8_lossy(line);
+        if !self.started {
+            self.started = true;
+            if let Some(rest) = line.strip_prefix('\u{feff}') {
+                line = rest.to_owned().into();
+            }
+        }
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        let (field, value) = line.split_once(':').unwrap_or((&line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            // a comment, used to keep the connection alive
+            "" => {}
+            "event" => self.event = value.to_owned(),
+            "data" => {
+                // the line break is not counted against the limit
+                if self.has_data {
+                    self.data.push('\n');
+                }
+                self.data.push_str(value);
+                self.has_data = true;
+            }
+            "id" if !value.contains('\0') => {
+                self.id = Some(value.to_owned()).filter(|id| !id.is_empty());
+                self.id_changed = true;
+            }
+            "retry" => {
+                if let Ok(millis) = value.parse() {
+                    return Some(SseTaskMessage::Retry(Duration::from_millis(millis)));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// ends the block of lines at an empty line
+    fn dispatch(&mut self) -> Option<SseTaskMessage> {
+        let event = std::mem::take(&mut self.event);
+        let data = std::mem::take(&mut self.data);
+        let id_changed = std::mem::take(&mut self.id_changed);
+        if !std::mem::take(&mut self.has_data) {
+            return id_changed.then(|| SseTaskMessage::Id(self.id.clone().unwrap_or_default()));
+        }
+        Some(SseTaskMessage::Message(SseMessage {
+            event: if event.is_empty() {
+                "message".to_owned()
+            } else {
+                event
+            },
+            data,
+            id: self.id.clone(),
+        }))
+    }
+}
+
+/// opens new subscriptions, reconnects the ones that ended and triggers the messages that arrived
+pub(crate) fn poll_sse_subscriptions(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    client: Res<ReqwestClient>,
+    defaults: Res<ReqwestDefaults>,
+    mut q: Query<(Entity, &mut SseSubscription)>,
+) {
+    for (entity, mut subscription) in q.iter_mut() {
+        if subscription.closed {
+            continue;
+        }
+        if let Some(timer) = subscription.reconnect.as_mut() {
+            if !timer.tick(time.delta()).is_finished() {
+                continue;
+            }
+            subscription.reconnect = None;
+        }
+        if subscription.connection.is_none() {
+            subscription.connect(Transport::unrecorded(&client, &defaults));
+        }
+
+        while let Some(message) = subscription
+            .connection
+            .as_ref()
+            .and_then(|c| c.messages.try_recv().ok())
+        {
+            match message {
+                SseTaskMessage::Open => {
+                    if let Some(connection) = subscription.connection.as_mut() {
+                        connection.open = true;
+                    }
+                    commands.trigger(SseOpenEvent { entity });
+                }
+                SseTaskMessage::Message(message) => {
+                    // an empty id resets the last event id, just like it does without data
+                    subscription.last_event_id = message.id.clone();
+                    commands.trigger(SseMessageEvent {
+                        entity,
+                        event: message.event,
+                        data: message.data,
+                        id: message.id,
+                    });
+                }
+                SseTaskMessage::Id(id) => {
+                    subscription.last_event_id = Some(id).filter(|id| !id.is_empty());
+                }
+                SseTaskMessage::Retry(delay) => subscription.reconnect_delay = delay,
+                SseTaskMessage::Ended => subscription.disconnect(),
+                SseTaskMessage::Closed(reason) => {
+                    subscription.connection = None;
+                    subscription.closed = true;
+                    commands.trigger(SseClosedEvent { entity, reason });
+                }
+                SseTaskMessage::Failed(error) => {
+                    subscription.disconnect();
+                    commands.trigger(SseErrorEvent { entity, error });
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, EntityEvent, Debug)]
+/// triggered on the entity of an [`SseSubscription`] for every message on the stream
+pub struct SseMessageEvent {
+    pub entity: Entity,
+    /// the type of the message, from the `event` field, `"message"` if it was not set
+    pub event: String,
+    /// the `data` fields of the message, joined with newlines
+    pub data: String,
+    /// the id of the message, or of the last message before it that had one
+    pub id: Option<String>,
+}
+
+impl SseMessageEvent {
+    #[cfg(feature = "json")]
+    /// try to deserialize the data of the message using json
+    pub fn deserialize_json<'de, T: serde::Deserialize<'de>>(&'de self) -> anyhow::Result<T> {
+        Ok(serde_json::from_str(&self.data)?)
+    }
+}
+
+#[derive(Clone, EntityEvent, Debug)]
+/// triggered when the stream of an [`SseSubscription`] has been opened, including after reconnecting
+pub struct SseOpenEvent {
+    pub entity: Entity,
+}
+
+#[derive(EntityEvent, Debug)]
+/// triggered when the server of an [`SseSubscription`] could not be reached, or the stream failed while it was
+/// open, it is opened again after [`SseSubscription::reconnect_delay`]
+pub struct SseErrorEvent {
+    pub entity: Entity,
+    pub error: SseError,
+}
+
+/// Why the stream of an [`SseSubscription`] failed
+#[derive(Debug)]
+pub enum SseError {
+    /// the server could not be reached, or the stream broke off
+    Request(reqwest::Error),
+    /// a line, or the data of a message, was larger than this many bytes, so the connection was dropped
+    TooLarge(usize),
+}
+
+impl fmt::Display for SseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Request(e) => write!(f, "the stream failed: {e}"),
+            Self::TooLarge(limit) => {
+                write!(f, "a message on the stream was larger than {limit} bytes")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Request(e) => Some(e),
+            Self::TooLarge(_) => None,
+        }
+    }
+}
+
+#[derive(Clone, EntityEvent, Debug)]
+/// triggered when the server answered an [`SseSubscription`] with `204 No Content` or refused the stream, it
+/// will not be opened again
+pub struct SseClosedEvent {
+    pub entity: Entity,
+    /// why the stream will not be opened again
+    pub reason: SseCloseReason,
+}
+
+/// Why the server of an [`SseSubscription`] closed it for good
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SseCloseReason {
+    /// the server answered with `204 No Content` to stop the client from reconnecting
+    NoContent,
+    /// the server answered with a status that is not successful, such as `401 Unauthorized`
+    Status(StatusCode),
+    /// the server answered with another `Content-Type` than `text/event-stream`, or without one
+    ContentType(Option<HeaderValue>),
+}
+
+impl SseCloseReason {
+    /// the reason the response to the request opening the stream closes it, `None` if the messages follow
+    fn of(res: &reqwest::Response) -> Option<Self> {
+        let status = res.status();
+        if status == StatusCode::NO_CONTENT {
+            return Some(Self::NoContent);
+        }
+        if !status.is_success() {
+            return Some(Self::Status(status));
+        }
+        let content_type = res.headers().get(CONTENT_TYPE);
+        // the type may have parameters, such as a charset
+        let event_stream = content_type
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .is_some_and(|essence| essence.trim().eq_ignore_ascii_case("text/event-stream"));
+        (!event_stream).then(|| Self::ContentType(content_type.cloned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the messages of `chunks` fed one after the other, in a form that is easy to compare
+    fn parse(chunks: &[&[u8]]) -> Vec<String> {
+        parse_with_limit(chunks, MAX_MESSAGE_SIZE)
+    }
+
+    fn parse_with_limit(chunks: &[&[u8]], max_size: usize) -> Vec<String> {
+        let mut parser = SseParser::new(max_size);
+        chunks
+            .iter()
+            .flat_map(|chunk| parser.feed(chunk))
+            .map(|message| match message {
+                SseTaskMessage::Message(m) => format!("{}|{}|{:?}", m.event, m.data, m.id),
+                SseTaskMessage::Id(id) => format!("id {id}"),
+                SseTaskMessage::Retry(delay) => format!("retry {}", delay.as_millis()),
+                SseTaskMessage::Failed(e) => e.to_string(),
+                _ => unreachable!("the parser only produces messages, ids, retries and failures"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn single_message() {
+        assert_eq!(parse(&[b"data: hello\n\n"]), ["message|hello|None"]);
+    }
+
+    #[test]
+    fn line_endings() {
+        let expected = ["message|a|None", "message|b|None"];
+        assert_eq!(parse(&[b"data: a\n\ndata: b\n\n"]), expected);
+        assert_eq!(parse(&[b"data: a\r\n\r\ndata: b\r\n\r\n"]), expected);
+        assert_eq!(parse(&[b"data: a\r\rdata: b\r\r"]), expected);
+        // a \r\n split over two chunks is still one line break
+        assert_eq!(
+            parse(&[b"data: a\r", b"\n\r", b"\ndata: b\r\n\r\n"]),
+            expected
+        );
+    }
+
+    #[test]
+    fn multi_line_data() {
+        assert_eq!(
+            parse(&[b"data: first\ndata:second\ndata\n\n"]),
+            ["message|first\nsecond\n|None"]
+        );
+    }
+
+    #[test]
+    fn message_split_over_chunks() {
+        assert_eq!(
+            parse(&[b"eve", b"nt: sco", b"re\nda", b"ta: 1", b"\n", b"\n"]),
+            ["score|1|None"]
+        );
+    }
+
+    #[test]
+    fn event_and_id() {
+        assert_eq!(
+            parse(&[b"event: score\nid: 7\ndata: 1\n\ndata: 2\n\n"]),
+            ["score|1|Some(\"7\")", "message|2|Some(\"7\")"]
+        );
+    }
+
+    #[test]
+    fn id_without_data() {
+        assert_eq!(parse(&[b"id: 3\n\n"]), ["id 3"]);
+        // an empty id resets the last event id
+        assert_eq!(parse(&[b"id: 3\n\nid\n\n"]), ["id 3", "id "]);
+        // an empty id before the data resets the id of that message
+        assert_eq!(
+            parse(&[b"id: 3\ndata: a\n\nid:\ndata: b\n\n"]),
+            ["message|a|Some(\"3\")", "message|b|None"]
+        );
+        // ids with a null are ignored
+        assert_eq!(parse(&[b"id: a\0b\ndata: x\n\n"]), ["message|x|None"]);
+    }
+
+    #[test]
+    fn retry() {
+        assert_eq!(parse(&[b"retry: 2500\n"]), ["retry 2500"]);
+        assert!(parse(&[b"retry: soon\n"]).is_empty());
+    }
+
+    #[test]
+    fn comments_and_unknown_fields() {
+        assert_eq!(
+            parse(&[b": keep alive\nfoo: bar\ndata: x\n\n"]),
+            ["message|x|None"]
+        );
+        // a block of only comments is not a message
+        assert!(parse(&[b": ping\n\n"]).is_empty());
+    }
+
+    #[test]
+    fn byte_order_mark() {
+        assert_eq!(parse(&[b"\xEF\xBB\xBFdata: x\n\n"]), ["message|x|None"]);
+        // only at the start of the stream
+        assert!(parse(&[b"data: x\n\n\xEF\xBB\xBFdata: y\n\n"])
+            .iter()
+            .all(|message| !message.contains('y')));
+    }
+
+    #[test]
+    fn incomplete_message_is_not_dispatched() {
+        assert!(parse(&[b"data: x\n"]).is_empty());
+    }
+
+    #[test]
+    fn too_large() {
+        let too_large = "a message on the stream was larger than 8 bytes";
+        // a single line
+        assert_eq!(parse_with_limit(&[b": 0123456789\n"], 8), [too_large]);
+        // the data of a message over several lines and chunks, after the messages that fit
+        assert_eq!(
+            parse_with_limit(&[b"data: 1\n\ndata: 12\n", b"data: 345\ndata: 6789\n\n"], 8),
+            ["message|1|None", too_large]
+        );
+        // up to the limit is fine
+        assert_eq!(
+            parse_with_limit(&[b"data:123\n\n"], 8),
+            ["message|123|None"]
+        );
+    }
+}
@@ -0,0 +1,220 @@
+use std::ops::Deref;
+
+use bevy::{
+    ecs::{lifecycle::HookContext, world::DeferredWorld},
+    prelude::*,
+};
+
+use crate::{ReqwestResponseEvent, StatusCode};
+
+/// Which responses a status handler of a [`BevyReqwestBuilder`](crate::BevyReqwestBuilder) runs for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StatusRoute {
+    /// exactly this status, takes precedence over the classes
+    Status(StatusCode),
+    /// `2xx`
+    Success,
+    /// `4xx`
+    ClientError,
+    /// `5xx`
+    ServerError,
+    /// every status that no other handler matched
+    Other,
+}
+
+impl StatusRoute {
+    fn matches(&self, status: StatusCode) -> bool {
+        match self {
+            Self::Status(expected) => *expected == status,
+            Self::Success => status.is_success(),
+            Self::ClientError => status.is_client_error(),
+            Self::ServerError => status.is_server_error(),
+            Self::Other => false,
+        }
+    }
+}
+
+/// The status handlers of a request, each one observes a child entity of the request that the matching
+/// responses are triggered on. The children are despawned with the component, so the handlers of an earlier
+/// request on the same entity do not run for the next one
+#[derive(Component, Default)]
+#[component(on_remove = StatusRoutes::on_remove)]
+pub(crate) struct StatusRoutes(Vec<(StatusRoute, Entity)>);
+
+impl StatusRoutes {
+    pub(crate) fn push(&mut self, route: StatusRoute, target: Entity) {
+        self.0.push((route, target));
+    }
+
+    /// the handlers for `status`, only the most specific ones that match
+    fn matching(&self, status: StatusCode) -> Vec<Entity> {
+        let targets = |filter: &dyn Fn(&StatusRoute) -> bool| -> Vec<Entity> {
+            self.0
+                .iter()
+                .filter(|(route, _)| filter(route))
+                .map(|(_, target)| *target)
+                .collect()
+        };
+        let exact =
+            targets(&|route| matches!(route, StatusRoute::Status(_)) && route.matches(status));
+        if !exact.is_empty() {
+            return exact;
+        }
+        let classes = targets(&|route| route.matches(status));
+        if !classes.is_empty() {
+            return classes;
+        }
+        targets(&|route| *route == StatusRoute::Other)
+    }
+
+    fn on_remove(mut world: DeferredWorld, ctx: HookContext) {
+        let targets: Vec<Entity> = world
+            .get::<StatusRoutes>(ctx.entity)
+            .map(|routes| routes.0.iter().map(|(_, target)| *target).collect())
+            .unwrap_or_default();
+        let mut commands = world.commands();
+        for target in targets {
+            // they might already be despawned along with the request
+            if let Ok(mut ec) = commands.get_entity(target) {
+                ec.try_despawn();
+            }
+        }
+    }
+}
+
+/// forwards the response to the status handlers that match it, for the requests that have any
+pub(crate) fn route_response_by_status(
+    trigger: On<ReqwestResponseEvent>,
+    routes: Query<&StatusRoutes>,
+    mut commands: Commands,
+) {
+    let response = trigger.event();
+    let Ok(routes) = routes.get(response.entity) else {
+        return;
+    };
+    for target in routes.matching(response.status()) {
+        commands.trigger(ReqwestStatusEvent {
+            target,
+            entity: response.entity,
+            response: response.clone(),
+        });
+    }
+}
+
+#[derive(EntityEvent, Clone, Debug)]
+/// A response that matched a status handler such as [`BevyReqwestBuilder::on_success`](crate::BevyReqwestBuilder::on_success),
+/// derefs to the [`ReqwestResponseEvent`]
+pub struct ReqwestStatusEvent {
+    #[event_target]
+    target: Entity,
+    /// the entity of the request
+    pub entity: Entity,
+    pub response: ReqwestResponseEvent,
+}
+
+impl Deref for ReqwestStatusEvent {
+    type Target = ReqwestResponseEvent;
+
+    fn deref(&self) -> &Self::Target {
+        &self.response
+    }
+}
+
+#[cfg(feature = "json")]
+#[derive(EntityEvent, Debug)]
+/// The deserialized body of a response that matched a json status handler such as
+/// [`BevyReqwestBuilder::on_json_success`](crate::BevyReqwestBuilder::on_json_success)
+pub struct JsonStatusResponse<T> {
+    #[event_target]
+    target: Entity,
+    /// the entity of the request
+    pub entity: Entity,
+    /// the `StatusCode` of the response
+    pub status: StatusCode,
+    pub data: T,
+}
+
+/// deserializes the responses that are triggered on a json status handler
+#[cfg(feature = "json")]
+pub(crate) fn deserialize_status_response<
+    T: serde::de::DeserializeOwned + Send + Sync + 'static,
+>(
+    trigger: On<ReqwestStatusEvent>,
+    mut commands: Commands,
+    observed: Query<(), With<crate::json::ObservesJsonErrors>>,
+) {
+    let evt = trigger.event();
+    match crate::json::deserialize::<T>(evt) {
+        Ok(data) => commands.trigger(JsonStatusResponse {
+            target: evt.target,
+            entity: evt.entity,
+            status: evt.status(),
+            data,
+        }),
+        // triggered on the request, for its BevyReqwestBuilder::on_json_error
+        Err(e) => crate::json::trigger_error(&mut commands, *e, observed.contains(evt.entity)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the routes, each with a target entity of its own
+    fn routed(routes: &[StatusRoute]) -> (StatusRoutes, Vec<Entity>) {
+        let mut world = World::new();
+        let mut status_routes = StatusRoutes::default();
+        let targets = routes
+            .iter()
+            .map(|route| {
+                let target = world.spawn_empty().id();
+                status_routes.push(*route, target);
+                target
+            })
+            .collect();
+        (status_routes, targets)
+    }
+
+    #[test]
+    fn an_exact_status_beats_its_class() {
+        let (routes, targets) = routed(&[
+            StatusRoute::ClientError,
+            StatusRoute::Status(StatusCode::NOT_FOUND),
+            StatusRoute::Other,
+        ]);
+        assert_eq!(routes.matching(StatusCode::NOT_FOUND), [targets[1]]);
+        assert_eq!(routes.matching(StatusCode::FORBIDDEN), [targets[0]]);
+    }
+
+    #[test]
+    fn a_class_beats_the_other_statuses() {
+        let (routes, targets) = routed(&[StatusRoute::Other, StatusRoute::Success]);
+        assert_eq!(routes.matching(StatusCode::CREATED), [targets[1]]);
+        assert_eq!(routes.matching(StatusCode::BAD_GATEWAY), [targets[0]]);
+    }
+
+    #[test]
+    fn every_handler_of_the_winning_level_runs() {
+        let (routes, targets) = routed(&[
+            StatusRoute::Success,
+            StatusRoute::Status(StatusCode::OK),
+            StatusRoute::Other,
+            StatusRoute::Status(StatusCode::OK),
+            StatusRoute::Success,
+        ]);
+        assert_eq!(routes.matching(StatusCode::OK), [targets[1], targets[3]]);
+        assert_eq!(
+            routes.matching(StatusCode::ACCEPTED),
+            [targets[0], targets[4]]
+        );
+    }
+
+    #[test]
+    fn nothing_matches_without_a_handler_for_the_other_statuses() {
+        let (routes, _) = routed(&[StatusRoute::Success, StatusRoute::ServerError]);
+        assert!(routes.matching(StatusCode::NOT_FOUND).is_empty());
+        // a status handler runs for exactly that status
+        let (routes, _) = routed(&[StatusRoute::Status(StatusCode::NOT_FOUND)]);
+        assert!(routes.matching(StatusCode::GONE).is_empty());
+    }
+}
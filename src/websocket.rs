@@ -0,0 +1,982 @@
+use async_channel::{unbounded, Receiver, Sender};
+use bevy::{
+    ecs::{lifecycle::HookContext, world::DeferredWorld},
+    prelude::*,
+};
+
+use crate::TaskHandle;
+
+/// A message sent or received over a [`WebSocketConnection`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WsMessage {
+    Text(String),
+    Binary(bytes::Bytes),
+}
+
+impl WsMessage {
+    /// the message as text, if it was sent as text
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            WsMessage::Text(text) => Some(text),
+            WsMessage::Binary(_) => None,
+        }
+    }
+
+    /// the bytes of the message, the utf-8 bytes for text
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            WsMessage::Text(text) => text.as_bytes(),
+            WsMessage::Binary(bytes) => bytes,
+        }
+    }
+}
+
+impl From<String> for WsMessage {
+    fn from(text: String) -> Self {
+        WsMessage::Text(text)
+    }
+}
+
+impl From<&str> for WsMessage {
+    fn from(text: &str) -> Self {
+        WsMessage::Text(text.to_owned())
+    }
+}
+
+impl From<bytes::Bytes> for WsMessage {
+    fn from(bytes: bytes::Bytes) -> Self {
+        WsMessage::Binary(bytes)
+    }
+}
+
+impl From<Vec<u8>> for WsMessage {
+    fn from(bytes: Vec<u8>) -> Self {
+        WsMessage::Binary(bytes.into())
+    }
+}
+
+/// A WebSocket connection, opened using [`BevyReqwest::connect_websocket`](crate::BevyReqwest::connect_websocket).
+///
+/// On native the connection is an upgraded HTTP/1.1 request sent with the [`ReqwestClient`](crate::ReqwestClient),
+/// so it uses the same TLS config, proxy settings and default headers. On wasm the browser `WebSocket` is used,
+/// which only takes the url and the `Sec-WebSocket-Protocol` header from the request. The connection is never
+/// answered by the `ReqwestMock`.
+///
+/// Messages can be sent from any system with access to the component, they are queued until the connection is
+/// open. The component is removed once the connection has closed. Removing it before that, or despawning the
+/// entity, drops the connection and triggers a [`WsCloseEvent`]
+///
+/// # Examples
+///
+/// ```
+/// use bevy::prelude::*;
+/// use bevy_mod_reqwest::*;
+///
+/// fn chat(connections: Query<&WebSocketConnection>) {
+///     for connection in connections.iter() {
+///         connection.send_text("hello");
+///     }
+/// }
+/// ```
+#[derive(Component)]
+#[component(on_replace = WebSocketConnection::on_replace)]
+pub struct WebSocketConnection {
+    pub(crate) url: String,
+    outgoing: Sender<Outgoing>,
+    messages: Receiver<WsTaskMessage>,
+    open: bool,
+    // set once the close has been handled, so removing the component does not trigger another WsCloseEvent
+    closed: bool,
+    _task: TaskHandle,
+}
+
+impl WebSocketConnection {
+    /// starts connecting to the `ws`, `wss`, `http` or `https` url of the request
+    pub fn new(client: &reqwest::Client, request: reqwest::Request) -> Self {
+        let url = request.url().to_string();
+        let (tx, messages) = unbounded();
+        let (outgoing, outgoing_rx) = unbounded();
+
+        #[cfg(not(target_family = "wasm"))]
+        let task = TaskHandle::spawn(native::run(
+            client.clone(),
+            request,
+            tx,
+            outgoing.clone(),
+            outgoing_rx,
+        ));
+        // the browser opens the connection itself
+        #[cfg(target_family = "wasm")]
+        let task = {
+            let _ = client;
+            TaskHandle::spawn(wasm::run(request, tx, outgoing_rx))
+        };
+
+        Self {
+            url,
+            outgoing,
+            messages,
+            open: false,
+            closed: false,
+            _task: task,
+        }
+    }
+
+    /// the url the connection was opened to
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// true once the server has accepted the connection, until it is closed
+    pub fn is_open(&self) -> bool {
+        self.open && !self.outgoing.is_closed()
+    }
+
+    /// queues a message to be sent, returns false if the connection is closing or closed
+    pub fn send(&self, message: impl Into<WsMessage>) -> bool {
+        self.outgoing
+            .try_send(Outgoing::Message(message.into()))
+            .is_ok()
+    }
+
+    /// queues a text frame to be sent, returns false if the connection is closing or closed
+    pub fn send_text(&self, text: impl Into<String>) -> bool {
+        self.send(WsMessage::Text(text.into()))
+    }
+
+    /// queues a binary frame to be sent, returns false if the connection is closing or closed
+    pub fn send_binary(&self, bytes: impl Into<bytes::Bytes>) -> bool {
+        self.send(WsMessage::Binary(bytes.into()))
+    }
+
+    /// starts closing the connection, a [`WsCloseEvent`] is triggered once the server has answered. The reason is
+    /// cut off after at most 123 bytes, so it fits in the close frame
+    pub fn close(&self, code: u16, reason: impl Into<String>) {
+        self.outgoing
+            .try_send(Outgoing::Close(code, reason.into()))
+            .ok();
+    }
+
+    /// the task itself is aborted when the component is dropped, this only notifies the observers
+    fn on_replace(mut world: DeferredWorld, ctx: HookContext) {
+        let closed = world
+            .get::<WebSocketConnection>(ctx.entity)
+            .is_none_or(|connection| connection.closed);
+        if !closed {
+            // trigger right away, so the observers on the entity are still around if it is being despawned
+            world.trigger(WsCloseEvent {
+                entity: ctx.entity,
+                code: None,
+                reason: String::new(),
+                clean: false,
+            });
+        }
+    }
+}
+
+/// What the systems send to the task running a connection
+enum Outgoing {
+    Message(WsMessage),
+    /// answers a ping from the server
+    #[cfg_attr(target_family = "wasm", allow(dead_code))]
+    Pong(bytes::Bytes),
+    Close(u16, String),
+}
+
+/// What the task running a connection sends back to the ecs
+enum WsTaskMessage {
+    Open {
+        protocol: Option<String>,
+    },
+    Message(WsMessage),
+    Closed {
+        code: Option<u16>,
+        reason: String,
+        clean: bool,
+    },
+}
+
+/// triggers the events of the connections, and removes the ones that have closed
+pub(crate) fn poll_websockets(
+    mut commands: Commands,
+    mut q: Query<(Entity, &mut WebSocketConnection)>,
+) {
+    for (entity, mut connection) in q.iter_mut() {
+        while let Ok(message) = connection.messages.try_recv() {
+            match message {
+                WsTaskMessage::Open { protocol } => {
+                    connection.open = true;
+                    commands.trigger(WsOpenEvent { entity, protocol });
+                }
+                WsTaskMessage::Message(message) => {
+                    commands.trigger(WsMessageEvent { entity, message });
+                }
+                WsTaskMessage::Closed {
+                    code,
+                    reason,
+                    clean,
+                } => {
+                    connection.open = false;
+                    connection.closed = true;
+                    commands.trigger(WsCloseEvent {
+                        entity,
+                        code,
+                        reason,
+                        clean,
+                    });
+                    if let Ok(mut ec) = commands.get_entity(entity) {
+                        ec.remove::<WebSocketConnection>();
+                    }
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+mod native {
+    use async_channel::{Receiver, Sender};
+    use base64::{prelude::BASE64_STANDARD, Engine};
+    use futures_lite::{
+        future,
+        io::{AsyncReadExt, AsyncWriteExt},
+        AsyncRead, AsyncWrite,
+    };
+    use reqwest::{
+        header::{
+            HeaderValue, CONNECTION, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY,
+            SEC_WEBSOCKET_PROTOCOL, SEC_WEBSOCKET_VERSION, UPGRADE,
+        },
+        StatusCode, Version,
+    };
+
+    use super::{Outgoing, WsMessage, WsTaskMessage};
+
+    /// appended to the key before hashing it, see RFC 6455
+    const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+    /// messages larger than this close the connection, instead of being buffered
+    const MAX_MESSAGE_SIZE: u64 = 64 * 1024 * 1024;
+    /// the payload of a frame is read in pieces of at most this size
+    const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+    const CONTINUATION: u8 = 0x0;
+    const TEXT: u8 = 0x1;
+    const BINARY: u8 = 0x2;
+    const CLOSE: u8 = 0x8;
+    const PING: u8 = 0x9;
+    const PONG: u8 = 0xA;
+
+    const PROTOCOL_ERROR: u16 = 1002;
+    const INVALID_DATA: u16 = 1007;
+    const TOO_BIG: u16 = 1009;
+
+    /// how the reading half of a connection ended
+    #[derive(Debug)]
+    struct Close {
+        code: Option<u16>,
+        reason: String,
+        clean: bool,
+    }
+
+    impl Close {
+        fn failed(reason: impl ToString) -> Self {
+            Self {
+                code: None,
+                reason: reason.to_string(),
+                clean: false,
+            }
+        }
+    }
+
+    pub(super) async fn run(
+        client: reqwest::Client,
+        request: reqwest::Request,
+        tx: Sender<WsTaskMessage>,
+        outgoing_tx: Sender<Outgoing>,
+        outgoing: Receiver<Outgoing>,
+    ) {
+        let close = match connect(client, request, &tx).await {
+            Ok(upgraded) => {
+                let (reader, writer) = futures_lite::io::split(async_compat::Compat::new(upgraded));
+                future::zip(
+                    read_frames(reader, &tx, &outgoing_tx),
+                    write_frames(writer, &outgoing),
+                )
+                .await
+                .0
+            }
+            Err(close) => close,
+        };
+        outgoing.close();
+        tx.try_send(WsTaskMessage::Closed {
+            code: close.code,
+            reason: close.reason,
+            clean: close.clean,
+        })
+        .ok();
+    }
+
+    /// sends the upgrade request and checks that the server accepted it
+    async fn connect(
+        client: reqwest::Client,
+        mut request: reqwest::Request,
+        tx: &Sender<WsTaskMessage>,
+    ) -> Result<reqwest::Upgraded, Close> {
+        // reqwest only knows the http schemes
+        let scheme = match request.url().scheme() {
+            "ws" => Some("http"),
+            "wss" => Some("https"),
+            _ => None,
+        };
+        if let Some(scheme) = scheme {
+            request.url_mut().set_scheme(scheme).ok();
+        }
+        // the upgrade mechanism only exists in HTTP/1.1
+        *request.version_mut() = Version::HTTP_11;
+        let key = BASE64_STANDARD.encode(std::array::from_fn::<u8, 16, _>(|_| fastrand::u8(..)));
+        let headers = request.headers_mut();
+        headers.insert(CONNECTION, HeaderValue::from_static("upgrade"));
+        headers.insert(UPGRADE, HeaderValue::from_static("websocket"));
+        headers.insert(SEC_WEBSOCKET_VERSION, HeaderValue::from_static("13"));
+        if let Ok(key) = HeaderValue::from_str(&key) {
+            headers.insert(SEC_WEBSOCKET_KEY, key);
+        }
+
+        let res = client.execute(request).await.map_err(Close::failed)?;
+        if res.status() != StatusCode::SWITCHING_PROTOCOLS {
+            return Err(Close::failed(format!(
+                "the server answered the websocket upgrade with {}",
+                res.status()
+            )));
+        }
+        let accept = ring::digest::digest(
+            &ring::digest::SHA1_FOR_LEGACY_USE_ONLY,
+            format!("{key}{ACCEPT_GUID}").as_bytes(),
+        );
+        let accepted = res
+            .headers()
+            .get(SEC_WEBSOCKET_ACCEPT)
+            .is_some_and(|value| value.as_bytes() == BASE64_STANDARD.encode(accept).as_bytes());
+        if !accepted {
+            return Err(Close::failed(
+                "the server sent the wrong Sec-WebSocket-Accept",
+            ));
+        }
+        let protocol = res
+            .headers()
+            .get(SEC_WEBSOCKET_PROTOCOL)
+            .and_then(|protocol| protocol.to_str().ok())
+            .map(str::to_owned);
+        let upgraded = res.upgrade().await.map_err(Close::failed)?;
+        tx.try_send(WsTaskMessage::Open { protocol }).ok();
+        Ok(upgraded)
+    }
+
+    #[derive(Debug)]
+    struct Frame {
+        fin: bool,
+        opcode: u8,
+        payload: Vec<u8>,
+    }
+
+    async fn read_frame(reader: &mut (impl AsyncRead + Unpin)) -> Result<Frame, Close> {
+        let mut head = [0; 2];
+        reader.read_exact(&mut head).await.map_err(Close::failed)?;
+        let fin = head[0] & 0x80 != 0;
+        let opcode = head[0] & 0x0F;
+        if head[0] & 0x70 != 0 {
+            return Err(protocol_error("reserved bits are set"));
+        }
+        // only the frames of a client are masked, see RFC 6455 section 5.1
+        if head[1] & 0x80 != 0 {
+            return Err(protocol_error("the server sent a masked frame"));
+        }
+        let len = match head[1] & 0x7F {
+            126 => {
+                let mut len = [0; 2];
+                reader.read_exact(&mut len).await.map_err(Close::failed)?;
+                u16::from_be_bytes(len) as u64
+            }
+            127 => {
+                let mut len = [0; 8];
+                reader.read_exact(&mut len).await.map_err(Close::failed)?;
+                u64::from_be_bytes(len)
+            }
+            len => len as u64,
+        };
+        if opcode >= CLOSE && (len > 125 || !fin) {
+            return Err(protocol_error(
+                "control frames can not be split or be larger than 125 bytes",
+            ));
+        }
+        if len > MAX_MESSAGE_SIZE {
+            return Err(Close {
+                code: Some(TOO_BIG),
+                reason: "the message is too big".to_owned(),
+                clean: false,
+            });
+        }
+        // grows with what has arrived, instead of trusting the length before the payload is there
+        let mut payload = Vec::new();
+        while (payload.len() as u64) < len {
+            let start = payload.len();
+            let chunk = READ_CHUNK_SIZE.min((len - start as u64) as usize);
+            payload.resize(start + chunk, 0);
+            reader
+                .read_exact(&mut payload[start..])
+                .await
+                .map_err(Close::failed)?;
+        }
+        Ok(Frame {
+            fin,
+            opcode,
+            payload,
+        })
+    }
+
+    fn protocol_error(reason: &str) -> Close {
+        Close {
+            code: Some(PROTOCOL_ERROR),
+            reason: reason.to_owned(),
+            clean: false,
+        }
+    }
+
+    /// reads messages until the connection is closed, and answers pings and close frames
+    async fn read_frames(
+        mut reader: impl AsyncRead + Unpin,
+        tx: &Sender<WsTaskMessage>,
+        outgoing: &Sender<Outgoing>,
+    ) -> Close {
+        let close = read_messages(&mut reader, tx, outgoing).await;
+        // tell the server why, unless it closed the connection itself
+        if let (Some(code), false) = (close.code, close.clean) {
+            outgoing
+                .try_send(Outgoing::Close(code, close.reason.clone()))
+                .ok();
+        }
+        // lets the writing half finish once it has sent what is queued
+        outgoing.close();
+        close
+    }
+
+    async fn read_messages(
+        reader: &mut (impl AsyncRead + Unpin),
+        tx: &Sender<WsTaskMessage>,
+        outgoing: &Sender<Outgoing>,
+    ) -> Close {
+        // the opcode and data of a message split into several frames
+        let mut message: Option<(u8, Vec<u8>)> = None;
+        loop {
+            let frame = match read_frame(reader).await {
+                Ok(frame) => frame,
+                Err(close) => return close,
+            };
+            let (opcode, data) = match (frame.opcode, message.take()) {
+                (CONTINUATION, Some((opcode, mut data))) => {
+                    if data.len() as u64 + frame.payload.len() as u64 > MAX_MESSAGE_SIZE {
+                        return Close {
+                            code: Some(TOO_BIG),
+                            reason: "the message is too big".to_owned(),
+                            clean: false,
+                        };
+                    }
+                    data.extend_from_slice(&frame.payload);
+                    (opcode, data)
+                }
+                (TEXT | BINARY, None) => (frame.opcode, frame.payload),
+                (PING, pending) => {
+                    message = pending;
+                    outgoing.try_send(Outgoing::Pong(frame.payload.into())).ok();
+                    continue;
+                }
+                (PONG, pending) => {
+                    message = pending;
+                    continue;
+                }
+                (CLOSE, _) => {
+                    let code = frame
+                        .payload
+                        .get(..2)
+                        .map(|code| u16::from_be_bytes([code[0], code[1]]));
+                    let reason = frame
+                        .payload
+                        .get(2..)
+                        .map(|reason| String::from_utf8_lossy(reason).into_owned())
+                        .unwrap_or_default();
+                    // echo the code to complete the closing handshake
+                    outgoing
+                        .try_send(Outgoing::Close(code.unwrap_or(1000), String::new()))
+                        .ok();
+                    return Close {
+                        code,
+                        reason,
+                        clean: true,
+                    };
+                }
+                _ => return protocol_error("unexpected frame"),
+            };
+            if !frame.fin {
+                message = Some((opcode, data));
+                continue;
+            }
+            let message = if opcode == TEXT {
+                match String::from_utf8(data) {
+                    Ok(text) => WsMessage::Text(text),
+                    Err(_) => {
+                        return Close {
+                            code: Some(INVALID_DATA),
+                            reason: "a text message was not utf-8".to_owned(),
+                            clean: false,
+                        }
+                    }
+                }
+            } else {
+                WsMessage::Binary(data.into())
+            };
+            // stop reading if nobody is listening anymore
+            if tx.try_send(WsTaskMessage::Message(message)).is_err() {
+                return Close::failed("the connection was dropped");
+            }
+        }
+    }
+
+    /// the code followed by the reason, which is cut off at a character so the payload fits in a control frame
+    fn close_payload(code: u16, reason: &str) -> Vec<u8> {
+        let mut len = reason.len().min(125 - 2);
+        while !reason.is_char_boundary(len) {
+            len -= 1;
+        }
+        let mut payload = code.to_be_bytes().to_vec();
+        payload.extend_from_slice(&reason.as_bytes()[..len]);
+        payload
+    }
+
+    /// sends the queued messages until a close frame has been sent
+    async fn write_frames(mut writer: impl AsyncWrite + Unpin, outgoing: &Receiver<Outgoing>) {
+        while let Ok(message) = outgoing.recv().await {
+            let (opcode, payload) = match message {
+                Outgoing::Message(WsMessage::Text(text)) => (TEXT, text.into_bytes()),
+                Outgoing::Message(WsMessage::Binary(bytes)) => (BINARY, bytes.to_vec()),
+                Outgoing::Pong(bytes) => (PONG, bytes.to_vec()),
+                Outgoing::Close(code, reason) => (CLOSE, close_payload(code, &reason)),
+            };
+            let written = writer.write_all(&encode_frame(opcode, payload)).await;
+            if written.is_err() || writer.flush().await.is_err() || opcode == CLOSE {
+                // nothing can be sent after a close frame
+                outgoing.close();
+                return;
+            }
+        }
+    }
+
+    /// a single final frame, masked as every frame from a client has to be
+    fn encode_frame(opcode: u8, mut payload: Vec<u8>) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(payload.len() + 14);
+        frame.push(0x80 | opcode);
+        match payload.len() {
+            len @ 0..=125 => frame.push(0x80 | len as u8),
+            len @ 126..=0xFFFF => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(0x80 | 127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        let mask = fastrand::u32(..).to_be_bytes();
+        frame.extend_from_slice(&mask);
+        apply_mask(&mut payload, mask);
+        frame.extend_from_slice(&payload);
+        frame
+    }
+
+    fn apply_mask(payload: &mut [u8], mask: [u8; 4]) {
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use async_channel::unbounded;
+
+        use super::*;
+
+        /// an unmasked frame, as a server sends them
+        fn server_frame(fin: bool, opcode: u8, payload: &[u8]) -> Vec<u8> {
+            let mut frame = vec![if fin { 0x80 } else { 0 } | opcode];
+            match payload.len() {
+                len @ 0..=125 => frame.push(len as u8),
+                len @ 126..=0xFFFF => {
+                    frame.push(126);
+                    frame.extend_from_slice(&(len as u16).to_be_bytes());
+                }
+                len => {
+                    frame.push(127);
+                    frame.extend_from_slice(&(len as u64).to_be_bytes());
+                }
+            }
+            frame.extend_from_slice(payload);
+            frame
+        }
+
+        /// the header byte and unmasked payload of a frame written by [`encode_frame`]
+        fn decode_client_frame(frame: &[u8]) -> (u8, Vec<u8>) {
+            assert!(frame[1] & 0x80 != 0, "client frames have to be masked");
+            let (len, start) = match frame[1] & 0x7F {
+                126 => (u16::from_be_bytes([frame[2], frame[3]]) as usize, 4),
+                127 => (
+                    u64::from_be_bytes(frame[2..10].try_into().unwrap()) as usize,
+                    10,
+                ),
+                len => (len as usize, 2),
+            };
+            let mask = frame[start..start + 4].try_into().unwrap();
+            let mut payload = frame[start + 4..].to_vec();
+            assert_eq!(payload.len(), len);
+            apply_mask(&mut payload, mask);
+            (frame[0], payload)
+        }
+
+        fn read(bytes: &[u8]) -> Result<Frame, Close> {
+            future::block_on(read_frame(&mut &bytes[..]))
+        }
+
+        /// the messages and the close of a connection that receives `bytes`, and what it queued to send
+        fn receive(bytes: &[u8]) -> (Vec<WsMessage>, Close, Vec<Outgoing>) {
+            let (tx, rx) = unbounded();
+            let (outgoing_tx, outgoing) = unbounded();
+            let close = future::block_on(read_messages(&mut &bytes[..], &tx, &outgoing_tx));
+            let messages = std::iter::from_fn(|| rx.try_recv().ok())
+                .map(|message| match message {
+                    WsTaskMessage::Message(message) => message,
+                    _ => unreachable!("only messages are sent while reading"),
+                })
+                .collect();
+            let outgoing = std::iter::from_fn(|| outgoing.try_recv().ok()).collect();
+            (messages, close, outgoing)
+        }
+
+        #[test]
+        fn encodes_masked_frames() {
+            for len in [0, 5, 125, 126, 0xFFFF, 0x10000] {
+                let payload: Vec<u8> = (0..len).map(|i| i as u8).collect();
+                let (head, decoded) = decode_client_frame(&encode_frame(BINARY, payload.clone()));
+                assert_eq!(head, 0x80 | BINARY);
+                assert_eq!(decoded, payload);
+            }
+        }
+
+        #[test]
+        fn decodes_frames() {
+            for len in [0, 125, 126, 0xFFFF, 0x10000] {
+                let payload: Vec<u8> = (0..len).map(|i| i as u8).collect();
+                let frame = read(&server_frame(true, BINARY, &payload)).unwrap();
+                assert!(frame.fin);
+                assert_eq!(frame.opcode, BINARY);
+                assert_eq!(frame.payload, payload);
+            }
+        }
+
+        #[test]
+        fn rejects_masked_frames() {
+            let mut frame = server_frame(true, TEXT, b"");
+            frame[1] |= 0x80;
+            frame.extend_from_slice(&[0; 4]);
+            assert_eq!(read(&frame).unwrap_err().code, Some(PROTOCOL_ERROR));
+        }
+
+        #[test]
+        fn rejects_reserved_bits() {
+            let mut frame = server_frame(true, TEXT, b"hi");
+            frame[0] |= 0x40;
+            assert_eq!(read(&frame).unwrap_err().code, Some(PROTOCOL_ERROR));
+        }
+
+        #[test]
+        fn rejects_large_or_split_control_frames() {
+            let frame = server_frame(true, PING, &[0; 126]);
+            assert_eq!(read(&frame).unwrap_err().code, Some(PROTOCOL_ERROR));
+            let frame = server_frame(false, PING, b"");
+            assert_eq!(read(&frame).unwrap_err().code, Some(PROTOCOL_ERROR));
+        }
+
+        #[test]
+        fn rejects_frames_that_are_too_big() {
+            let mut frame = vec![0x80 | BINARY, 127];
+            frame.extend_from_slice(&u64::MAX.to_be_bytes());
+            assert_eq!(read(&frame).unwrap_err().code, Some(TOO_BIG));
+        }
+
+        #[test]
+        fn close_reason_is_cut_off_at_a_character() {
+            let payload = close_payload(1000, "bye");
+            assert_eq!(payload, [0x03, 0xE8, b'b', b'y', b'e']);
+            // 61 two byte characters are 122 bytes, the next one does not fit in the 123 that are left
+            let reason = "é".repeat(70);
+            let payload = close_payload(1000, &reason);
+            assert_eq!(payload.len(), 2 + 122);
+            assert_eq!(std::str::from_utf8(&payload[2..]).unwrap(), "é".repeat(61));
+        }
+
+        #[test]
+        fn truncated_payload_fails() {
+            // claims a megabyte, but the connection ends after a few bytes
+            let mut frame = server_frame(true, BINARY, &[0; 0x100000]);
+            frame.truncate(20);
+            let close = read(&frame).unwrap_err();
+            assert_eq!(close.code, None);
+            assert!(!close.clean);
+        }
+
+        #[test]
+        fn joins_fragmented_messages() {
+            let mut bytes = server_frame(false, TEXT, b"hel");
+            // control frames may come between the fragments
+            bytes.extend(server_frame(true, PING, b"p"));
+            bytes.extend(server_frame(false, CONTINUATION, b"lo "));
+            bytes.extend(server_frame(true, CONTINUATION, b"world"));
+            bytes.extend(server_frame(true, BINARY, &[1, 2]));
+            let (messages, close, outgoing) = receive(&bytes);
+            assert_eq!(
+                messages,
+                [
+                    WsMessage::Text("hello world".to_owned()),
+                    WsMessage::Binary(vec![1, 2].into())
+                ]
+            );
+            assert!(matches!(&outgoing[..], [Outgoing::Pong(payload)] if payload[..] == b"p"[..]));
+            // the bytes ran out without a close frame
+            assert!(!close.clean);
+        }
+
+        #[test]
+        fn rejects_unexpected_continuations() {
+            let (_, close, _) = receive(&server_frame(true, CONTINUATION, b"x"));
+            assert_eq!(close.code, Some(PROTOCOL_ERROR));
+            let mut bytes = server_frame(false, TEXT, b"a");
+            bytes.extend(server_frame(true, TEXT, b"b"));
+            let (_, close, _) = receive(&bytes);
+            assert_eq!(close.code, Some(PROTOCOL_ERROR));
+        }
+
+        #[test]
+        fn rejects_invalid_text() {
+            let (messages, close, _) = receive(&server_frame(true, TEXT, &[0xFF, 0xFE]));
+            assert!(messages.is_empty());
+            assert_eq!(close.code, Some(INVALID_DATA));
+        }
+
+        #[test]
+        fn answers_the_close_of_the_server() {
+            let mut payload = 1001u16.to_be_bytes().to_vec();
+            payload.extend_from_slice(b"going away");
+            let (_, close, outgoing) = receive(&server_frame(true, CLOSE, &payload));
+            assert_eq!(close.code, Some(1001));
+            assert_eq!(close.reason, "going away");
+            assert!(close.clean);
+            assert!(matches!(&outgoing[..], [Outgoing::Close(1001, _)]));
+        }
+
+        #[test]
+        fn stops_writing_after_a_close_frame() {
+            let (outgoing_tx, outgoing) = unbounded();
+            outgoing_tx
+                .try_send(Outgoing::Message(WsMessage::Text("bye".to_owned())))
+                .ok();
+            outgoing_tx
+                .try_send(Outgoing::Close(1000, "done".to_owned()))
+                .ok();
+            outgoing_tx
+                .try_send(Outgoing::Message(WsMessage::Text("late".to_owned())))
+                .ok();
+            let mut written = Vec::new();
+            future::block_on(write_frames(&mut written, &outgoing));
+            assert!(outgoing.is_closed());
+
+            // the header, the mask and the text
+            let text_len = 2 + 4 + b"bye".len();
+            let (head, payload) = decode_client_frame(&written[..text_len]);
+            assert_eq!((head, &payload[..]), (0x80 | TEXT, &b"bye"[..]));
+            let (head, payload) = decode_client_frame(&written[text_len..]);
+            assert_eq!(head, 0x80 | CLOSE);
+            assert_eq!(payload[..2], 1000u16.to_be_bytes());
+            assert_eq!(&payload[2..], b"done");
+        }
+    }
+}
+
+#[cfg(target_family = "wasm")]
+mod wasm {
+    use async_channel::{Receiver, Sender};
+    use futures_lite::future;
+    use reqwest::header::SEC_WEBSOCKET_PROTOCOL;
+    use wasm_bindgen::{closure::Closure, JsCast};
+    use web_sys::{BinaryType, CloseEvent, MessageEvent, WebSocket};
+
+    use super::{Outgoing, WsMessage, WsTaskMessage};
+
+    /// closes the socket and drops the callbacks when the task is dropped
+    struct Socket {
+        socket: WebSocket,
+        _onopen: Closure<dyn FnMut()>,
+        _onmessage: Closure<dyn FnMut(MessageEvent)>,
+        _onclose: Closure<dyn FnMut(CloseEvent)>,
+    }
+
+    impl Drop for Socket {
+        fn drop(&mut self) {
+            self.socket.set_onopen(None);
+            self.socket.set_onmessage(None);
+            self.socket.set_onclose(None);
+            self.socket.close().ok();
+        }
+    }
+
+    pub(super) async fn run(
+        request: reqwest::Request,
+        tx: Sender<WsTaskMessage>,
+        outgoing: Receiver<Outgoing>,
+    ) {
+        let mut url = request.url().clone();
+        // the browser only takes the websocket schemes
+        let scheme = match url.scheme() {
+            "http" => Some("ws"),
+            "https" => Some("wss"),
+            _ => None,
+        };
+        if let Some(scheme) = scheme {
+            url.set_scheme(scheme).ok();
+        }
+        let protocol = request
+            .headers()
+            .get(SEC_WEBSOCKET_PROTOCOL)
+            .and_then(|protocol| protocol.to_str().ok());
+        let socket = match protocol {
+            Some(protocol) => WebSocket::new_with_str(url.as_str(), protocol),
+            None => WebSocket::new(url.as_str()),
+        };
+        let socket = match socket {
+            Ok(socket) => socket,
+            Err(e) => {
+                tx.try_send(WsTaskMessage::Closed {
+                    code: None,
+                    reason: format!("{e:?}"),
+                    clean: false,
+                })
+                .ok();
+                return;
+            }
+        };
+        socket.set_binary_type(BinaryType::Arraybuffer);
+
+        let (opened_tx, opened) = async_channel::bounded::<()>(1);
+        let (closed_tx, closed) = async_channel::bounded::<()>(1);
+
+        let onopen = {
+            let tx = tx.clone();
+            let socket = socket.clone();
+            Closure::<dyn FnMut()>::new(move || {
+                let protocol = Some(socket.protocol()).filter(|protocol| !protocol.is_empty());
+                tx.try_send(WsTaskMessage::Open { protocol }).ok();
+                opened_tx.try_send(()).ok();
+            })
+        };
+        let onmessage = {
+            let tx = tx.clone();
+            Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+                let data = event.data();
+                let message = if let Some(text) = data.as_string() {
+                    WsMessage::Text(text)
+                } else if let Ok(buffer) = data.dyn_into::<js_sys::ArrayBuffer>() {
+                    WsMessage::Binary(js_sys::Uint8Array::new(&buffer).to_vec().into())
+                } else {
+                    return;
+                };
+                tx.try_send(WsTaskMessage::Message(message)).ok();
+            })
+        };
+        let onclose = {
+            let tx = tx.clone();
+            Closure::<dyn FnMut(CloseEvent)>::new(move |event: CloseEvent| {
+                tx.try_send(WsTaskMessage::Closed {
+                    code: Some(event.code()),
+                    reason: event.reason(),
+                    clean: event.was_clean(),
+                })
+                .ok();
+                closed_tx.try_send(()).ok();
+            })
+        };
+        socket.set_onopen(Some(onopen.as_ref().unchecked_ref()));
+        socket.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+        socket.set_onclose(Some(onclose.as_ref().unchecked_ref()));
+        let socket = Socket {
+            socket,
+            _onopen: onopen,
+            _onmessage: onmessage,
+            _onclose: onclose,
+        };
+
+        let send = async {
+            // the browser does not queue messages sent before the connection is open
+            if opened.recv().await.is_err() {
+                return;
+            }
+            while let Ok(message) = outgoing.recv().await {
+                match message {
+                    Outgoing::Message(WsMessage::Text(text)) => {
+                        socket.socket.send_with_str(&text).ok();
+                    }
+                    Outgoing::Message(WsMessage::Binary(bytes)) => {
+                        socket.socket.send_with_u8_array(&bytes).ok();
+                    }
+                    // the browser answers pings itself
+                    Outgoing::Pong(_) => {}
+                    Outgoing::Close(code, reason) => {
+                        socket.socket.close_with_code_and_reason(code, &reason).ok();
+                        outgoing.close();
+                    }
+                }
+            }
+            // wait for the close event
+            future::pending::<()>().await;
+        };
+        future::or(send, async {
+            closed.recv().await.ok();
+        })
+        .await;
+        outgoing.close();
+    }
+}
+
+#[derive(Clone, EntityEvent, Debug)]
+/// triggered when the server has accepted a [`WebSocketConnection`]
+pub struct WsOpenEvent {
+    pub entity: Entity,
+    /// the subprotocol the server picked from the `Sec-WebSocket-Protocol` header of the request
+    pub protocol: Option<String>,
+}
+
+#[derive(Clone, EntityEvent, Debug)]
+/// triggered for every message received on a [`WebSocketConnection`], in the order they arrived
+pub struct WsMessageEvent {
+    pub entity: Entity,
+    pub message: WsMessage,
+}
+
+#[derive(Clone, EntityEvent, Debug)]
+/// triggered when a [`WebSocketConnection`] has closed, could not be opened, or was dropped
+pub struct WsCloseEvent {
+    pub entity: Entity,
+    /// the close code sent by the server, or the one sent to it when the connection was closed because of an
+    /// error in the protocol
+    pub code: Option<u16>,
+    /// the reason sent along with the code, or a description of the error that closed the connection
+    pub reason: String,
+    /// true if the connection was closed with a closing handshake
+    pub clean: bool,
+}
@@ -0,0 +1,130 @@
+//! Assets loaded from a url by the `AssetServer`, through the `ReqwestAssetPlugin`
+
+#![cfg(all(feature = "asset", not(target_family = "wasm")))]
+
+mod common;
+
+use std::time::Duration;
+
+use bevy::{
+    asset::{
+        io::{AssetReaderError, Reader},
+        AssetLoadError, AssetLoader, AssetMetaCheck, LoadContext, LoadState,
+    },
+    prelude::*,
+};
+use bevy_mod_reqwest::*;
+use common::*;
+
+const URL: &str = "https://example.com/hello.txt";
+
+/// the content of a `.txt` file
+#[derive(Asset, TypePath, Debug)]
+struct Text(String);
+
+#[derive(Default)]
+struct TextLoader;
+
+impl AssetLoader for TextLoader {
+    type Asset = Text;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Text, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(Text(String::from_utf8_lossy(&bytes).into_owned()))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["txt"]
+    }
+}
+
+/// an app with the `AssetServer` and the asset sources of `plugin`, whose requests are answered by the mock
+fn app(plugin: ReqwestAssetPlugin) -> (App, ReqwestMock) {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        plugin,
+        AssetPlugin {
+            // the mock would only answer them with a 404
+            meta_check: AssetMetaCheck::Never,
+            ..default()
+        },
+        ReqwestPlugin::default(),
+        ReqwestMockPlugin,
+    ))
+    .init_resource::<Seen>()
+    .init_asset::<Text>()
+    .register_asset_loader(TextLoader);
+    let mock = app.world().resource::<ReqwestMock>().clone();
+    (app, mock)
+}
+
+/// starts loading `path`, and updates the app until it is loaded or failed
+fn load(app: &mut App, path: &'static str) -> Handle<Text> {
+    let handle = app.world().resource::<AssetServer>().load(path);
+    update_until(app, Duration::from_secs(5), |app| {
+        let state = app.world().resource::<AssetServer>().load_state(&handle);
+        state.is_loaded() || state.is_failed()
+    });
+    handle
+}
+
+fn text(app: &App, handle: &Handle<Text>) -> String {
+    let texts = app.world().resource::<Assets<Text>>();
+    texts.get(handle).unwrap().0.clone()
+}
+
+/// the error of the asset reader the asset failed to load with
+fn reader_error(app: &App, handle: &Handle<Text>) -> AssetReaderError {
+    match app.world().resource::<AssetServer>().load_state(handle) {
+        LoadState::Failed(error) => match &*error {
+            AssetLoadError::AssetReaderError(error) => error.clone(),
+            error => panic!("not an error of the asset reader: {error}"),
+        },
+        state => panic!("the asset did not fail to load: {state:?}"),
+    }
+}
+
+#[test]
+fn loads_an_asset_from_a_url() {
+    let (mut app, mock) = app(ReqwestAssetPlugin::default());
+    mock.route(MockRoute::get(URL), MockResponse::ok().body("hello"));
+
+    let handle = load(&mut app, URL);
+
+    assert_eq!(text(&app, &handle), "hello");
+    mock.assert_called(&MockRoute::get(URL), 1);
+}
+
+#[test]
+fn not_found_is_reported_as_not_found() {
+    let (mut app, _) = app(ReqwestAssetPlugin::default());
+
+    let handle = load(&mut app, "https://example.com/missing.txt");
+
+    assert_eq!(
+        reader_error(&app, &handle),
+        AssetReaderError::NotFound("example.com/missing.txt".into())
+    );
+}
+
+#[test]
+fn other_statuses_are_reported_as_http_errors() {
+    let (mut app, mock) = app(ReqwestAssetPlugin::default());
+    mock.route(MockRoute::get(URL), MockResponse::new(403));
+
+    let handle = load(&mut app, URL);
+
+    assert_eq!(
+        reader_error(&app, &handle),
+        AssetReaderError::HttpError(403)
+    );
+}
@@ -0,0 +1,71 @@
+//! An app that answers its requests with a [`ReqwestMock`], and the helpers to drive it
+
+#![allow(dead_code)]
+
+use std::time::{Duration, Instant};
+
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use bevy_mod_reqwest::*;
+
+/// what the observers of the requests saw, in order
+#[derive(Resource, Default, Debug)]
+pub struct Seen(pub Vec<String>);
+
+impl Seen {
+    pub fn contains(&self, event: &str) -> bool {
+        self.0.iter().any(|seen| seen == event)
+    }
+}
+
+/// an app with the [`ReqwestPlugin`] and its mock, and [`Seen`] for the observers to write to
+pub fn app(plugin: ReqwestPlugin) -> (App, ReqwestMock) {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, plugin, ReqwestMockPlugin))
+        .init_resource::<Seen>();
+    let mock = app.world().resource::<ReqwestMock>().clone();
+    (app, mock)
+}
+
+/// runs `system` once, such as to send requests with a [`BevyReqwest`]
+pub fn run<O: 'static, M>(app: &mut App, system: impl IntoSystem<(), O, M>) -> O {
+    let output = app.world_mut().run_system_once(system).unwrap();
+    // the commands of the system are applied on the next update
+    app.update();
+    output
+}
+
+/// updates the app until `done` is true, and fails the test if that takes longer than `within`
+pub fn update_until(app: &mut App, within: Duration, mut done: impl FnMut(&mut App) -> bool) {
+    let start = Instant::now();
+    while !done(app) {
+        assert!(
+            start.elapsed() < within,
+            "not done within {within:?}, seen: {:?}",
+            app.world().resource::<Seen>().0
+        );
+        std::thread::sleep(Duration::from_millis(5));
+        app.update();
+    }
+}
+
+/// updates the app for `duration`
+pub fn update_for(app: &mut App, duration: Duration) {
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        std::thread::sleep(Duration::from_millis(5));
+        app.update();
+    }
+}
+
+/// updates the app until an observer saw `event`
+pub fn update_until_seen(app: &mut App, event: &str) {
+    let event = event.to_owned();
+    update_until(app, Duration::from_secs(5), |app| {
+        app.world().resource::<Seen>().contains(&event)
+    });
+}
+
+/// a system for an observer that writes `event` to [`Seen`]
+pub fn see<E: EntityEvent>(event: &'static str) -> impl FnMut(On<E>, ResMut<Seen>) {
+    move |_: On<E>, mut seen: ResMut<Seen>| seen.0.push(event.to_owned())
+}
@@ -0,0 +1,170 @@
+//! Downloads to a file, and resuming them from the part file an earlier download left behind
+
+#![cfg(not(target_family = "wasm"))]
+
+mod common;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use bevy_mod_reqwest::*;
+use common::*;
+
+const URL: &str = "https://example.com/file.txt";
+
+/// an empty directory that is deleted again when dropped
+struct TestDir(PathBuf);
+
+impl TestDir {
+    fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!("bevy_mod_reqwest_{}", fastrand::u64(..)));
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    fn file(&self) -> PathBuf {
+        self.0.join("file.txt")
+    }
+
+    /// the part file and its validator, as an interrupted download leaves them
+    fn interrupted(&self, part: &str, validator: &str) {
+        fs::write(self.0.join("file.txt.part"), part).unwrap();
+        fs::write(self.0.join("file.txt.part.validator"), validator).unwrap();
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.0).ok();
+    }
+}
+
+/// downloads [`URL`] to `path`, and updates the app until it is done
+fn download(app: &mut App, path: &Path) {
+    let path = path.to_owned();
+    run(app, move |mut client: BevyReqwest| {
+        let request = client.get(URL).build().unwrap();
+        client.download(request, path.clone()).on_download(
+            |trigger: On<ReqwestDownloadEvent>, mut seen: ResMut<Seen>| {
+                let event = trigger.event();
+                seen.0
+                    .push(format!("downloaded {} {}", event.status, event.size));
+            },
+        );
+    });
+    update_until(app, std::time::Duration::from_secs(5), |app| {
+        !app.world().resource::<Seen>().0.is_empty()
+    });
+}
+
+fn seen(app: &App) -> &[String] {
+    &app.world().resource::<Seen>().0
+}
+
+#[test]
+fn downloads_the_whole_file() {
+    let dir = TestDir::new();
+    let (mut app, mock) = app(ReqwestPlugin::default());
+    mock.route(
+        MockRoute::get(URL),
+        MockResponse::ok()
+            .header("etag", "\"v1\"")
+            .body("hello world"),
+    );
+
+    download(&mut app, &dir.file());
+
+    assert_eq!(seen(&app), ["downloaded 200 OK 11"]);
+    assert_eq!(fs::read_to_string(dir.file()).unwrap(), "hello world");
+    assert!(!dir.0.join("file.txt.part").exists());
+    assert!(!dir.0.join("file.txt.part.validator").exists());
+    assert!(mock.requests()[0].headers.get("range").is_none());
+}
+
+#[test]
+fn resumes_from_the_part_file() {
+    let dir = TestDir::new();
+    dir.interrupted("hello ", "\"v1\"");
+    let (mut app, mock) = app(ReqwestPlugin::default());
+    mock.route(
+        MockRoute::get(URL)
+            .header("range", "bytes=6-")
+            .header("if-range", "\"v1\""),
+        MockResponse::new(206)
+            .header("content-range", "bytes 6-10/11")
+            .body("world"),
+    );
+
+    download(&mut app, &dir.file());
+
+    assert_eq!(seen(&app), ["downloaded 206 Partial Content 11"]);
+    assert_eq!(fs::read_to_string(dir.file()).unwrap(), "hello world");
+    mock.assert_called(&MockRoute::get(URL), 1);
+}
+
+#[test]
+fn starts_over_when_the_whole_file_is_sent() {
+    let dir = TestDir::new();
+    dir.interrupted("stale ", "\"v1\"");
+    let (mut app, mock) = app(ReqwestPlugin::default());
+    // the file changed, so the If-Range does not match
+    mock.route(
+        MockRoute::get(URL),
+        MockResponse::ok()
+            .header("etag", "\"v2\"")
+            .body("hello world"),
+    );
+
+    download(&mut app, &dir.file());
+
+    assert_eq!(seen(&app), ["downloaded 200 OK 11"]);
+    assert_eq!(fs::read_to_string(dir.file()).unwrap(), "hello world");
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].headers["range"], "bytes=6-");
+}
+
+#[test]
+fn starts_over_when_the_range_is_not_satisfiable() {
+    let dir = TestDir::new();
+    dir.interrupted("longer than the file", "\"v1\"");
+    let (mut app, mock) = app(ReqwestPlugin::default());
+    mock.route(
+        MockRoute::get(URL).header("range", "bytes=20-"),
+        MockResponse::new(416).header("content-range", "bytes */11"),
+    );
+    mock.route(MockRoute::get(URL), MockResponse::ok().body("hello world"));
+
+    download(&mut app, &dir.file());
+
+    assert_eq!(seen(&app), ["downloaded 200 OK 11"]);
+    assert_eq!(fs::read_to_string(dir.file()).unwrap(), "hello world");
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 2);
+    // the part file was thrown away before asking again
+    assert!(requests[1].headers.get("range").is_none());
+}
+
+#[test]
+fn starts_over_when_another_range_is_sent() {
+    let dir = TestDir::new();
+    dir.interrupted("hello ", "\"v1\"");
+    let (mut app, mock) = app(ReqwestPlugin::default());
+    // a proxy that answers every range request from the start of the file
+    mock.route(
+        MockRoute::get(URL).header("range", "bytes=6-"),
+        MockResponse::new(206)
+            .header("content-range", "bytes 0-10/11")
+            .body("hello world"),
+    );
+    mock.route(MockRoute::get(URL), MockResponse::ok().body("hello world"));
+
+    download(&mut app, &dir.file());
+
+    assert_eq!(seen(&app), ["downloaded 200 OK 11"]);
+    assert_eq!(fs::read_to_string(dir.file()).unwrap(), "hello world");
+    assert_eq!(mock.requests().len(), 2);
+}
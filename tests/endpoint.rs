@@ -0,0 +1,188 @@
+//! The requests built by the `HttpEndpoint` derive
+
+#![cfg(feature = "derive")]
+
+mod common;
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_mod_reqwest::*;
+use common::*;
+use serde::{Deserialize, Serialize};
+
+const BASE_URL: Option<&str> = Some("https://api.example.com/");
+
+fn build(endpoint: &impl HttpEndpoint, base_url: Option<&str>) -> reqwest::Request {
+    endpoint
+        .request(&reqwest::Client::new(), base_url)
+        .build()
+        .unwrap()
+}
+
+fn body(request: &reqwest::Request) -> &str {
+    let bytes = request.body().and_then(|body| body.as_bytes()).unwrap();
+    std::str::from_utf8(bytes).unwrap()
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Post {
+    title: String,
+}
+
+#[derive(HttpEndpoint)]
+#[endpoint(path = "/users/{id}/posts/{slug}")]
+struct ListPosts {
+    id: u64,
+    slug: String,
+    page: u32,
+    #[endpoint(query = "pageSize")]
+    page_size: Option<u32>,
+    tag: Option<String>,
+}
+
+#[test]
+fn fills_in_the_path_and_the_query() {
+    let request = build(
+        &ListPosts {
+            id: 7,
+            slug: "hello world/?".to_owned(),
+            page: 2,
+            page_size: Some(50),
+            tag: None,
+        },
+        BASE_URL,
+    );
+    assert_eq!(request.method(), reqwest::Method::GET);
+    assert_eq!(
+        request.url().as_str(),
+        "https://api.example.com/users/7/posts/hello%20world%2F%3F?page=2&pageSize=50"
+    );
+}
+
+#[derive(HttpEndpoint)]
+#[endpoint(method = "post", path = "users/{id}/posts", response = Post)]
+struct CreatePost {
+    id: u64,
+    #[endpoint(body)]
+    post: Post,
+    #[endpoint(header = "X-Request-Id")]
+    request_id: u32,
+    #[endpoint(skip)]
+    #[allow(dead_code)]
+    retries: u8,
+}
+
+#[test]
+fn sends_the_json_body_and_headers() {
+    let request = build(
+        &CreatePost {
+            id: 1,
+            post: Post {
+                title: "first".to_owned(),
+            },
+            request_id: 42,
+            retries: 3,
+        },
+        BASE_URL,
+    );
+    assert_eq!(request.method(), reqwest::Method::POST);
+    // the skipped field is not a query parameter
+    assert_eq!(
+        request.url().as_str(),
+        "https://api.example.com/users/1/posts"
+    );
+    assert_eq!(request.headers()["x-request-id"], "42");
+    assert_eq!(request.headers()["content-type"], "application/json");
+    assert_eq!(body(&request), r#"{"title":"first"}"#);
+    assert_eq!(
+        CreatePost::parse_response(br#"{"title":"first"}"#).unwrap(),
+        Post {
+            title: "first".to_owned()
+        }
+    );
+}
+
+#[derive(HttpEndpoint)]
+#[endpoint(method = "PUT", path = "/login")]
+struct Login {
+    #[endpoint(body = "form")]
+    form: Vec<(&'static str, &'static str)>,
+}
+
+#[test]
+fn sends_the_form_body() {
+    let request = build(
+        &Login {
+            form: vec![("user", "me"), ("password", "a b&c")],
+        },
+        BASE_URL,
+    );
+    assert_eq!(request.method(), reqwest::Method::PUT);
+    assert_eq!(
+        request.headers()["content-type"],
+        "application/x-www-form-urlencoded"
+    );
+    assert_eq!(body(&request), "user=me&password=a+b%26c");
+    // without a response type the body is not looked at
+    assert!(Login::parse_response(b"not json").is_ok());
+}
+
+#[derive(HttpEndpoint)]
+#[endpoint(method = "DELETE", path = "https://other.example.com/items/{id}")]
+struct DeleteItem {
+    id: i32,
+}
+
+#[test]
+fn keeps_full_urls() {
+    let request = build(&DeleteItem { id: -1 }, BASE_URL);
+    assert_eq!(request.method(), reqwest::Method::DELETE);
+    assert_eq!(request.url().as_str(), "https://other.example.com/items/-1");
+    assert_eq!(
+        build(&DeleteItem { id: 2 }, None).url().as_str(),
+        "https://other.example.com/items/2"
+    );
+}
+
+#[derive(HttpEndpoint)]
+#[endpoint(path = "/health")]
+struct Health;
+
+#[test]
+fn unit_structs_have_no_parameters() {
+    let request = build(&Health, Some("http://localhost:8080"));
+    assert_eq!(request.url().as_str(), "http://localhost:8080/health");
+    assert!(request.body().is_none());
+}
+
+#[cfg(not(target_family = "wasm"))]
+#[test]
+fn cancelling_a_call_is_an_error() {
+    let (mut app, mock) = app(ReqwestPlugin::default());
+    mock.route(
+        MockRoute::delete("https://other.example.com/items/*"),
+        MockResponse::ok().delay(Duration::from_secs(10)),
+    );
+
+    run(&mut app, |mut client: BevyReqwest| {
+        client.call(DeleteItem { id: 3 }).unwrap().on_err(
+            |trigger: On<EndpointErr<serde_json::Value>>, mut seen: ResMut<Seen>| {
+                seen.0.push(trigger.event().error.to_string());
+            },
+        );
+    });
+    let entity = app
+        .world_mut()
+        .query_filtered::<Entity, With<ReqwestInflight>>()
+        .single(app.world())
+        .unwrap();
+    run(&mut app, move |mut client: BevyReqwest| {
+        client.cancel(entity)
+    });
+
+    assert_eq!(
+        app.world().resource::<Seen>().0,
+        ["the request was cancelled"]
+    );
+}
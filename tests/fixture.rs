@@ -0,0 +1,183 @@
+//! Recording a session with the `ReqwestFixturePlugin`, and replaying it
+
+#![cfg(all(feature = "json", not(target_family = "wasm")))]
+
+mod common;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use bevy_mod_reqwest::*;
+use common::*;
+
+/// a fixture file in the temporary directory, which is deleted again when dropped
+struct TestFixture(PathBuf);
+
+impl TestFixture {
+    fn new() -> Self {
+        let name = format!("bevy_mod_reqwest_{}.json", fastrand::u64(..));
+        Self(std::env::temp_dir().join(name))
+    }
+}
+
+impl Drop for TestFixture {
+    fn drop(&mut self) {
+        fs::remove_file(&self.0).ok();
+    }
+}
+
+/// an app with the `plugin` added after the [`ReqwestPlugin`], and the mock the plugin records or replays
+fn app(plugin: ReqwestFixturePlugin) -> (App, ReqwestMock) {
+    let (mut app, mock) = common::app(ReqwestPlugin::default());
+    app.add_plugins(plugin);
+    (app, mock)
+}
+
+/// sends a `GET` request to `url` with `headers`, and updates the app until its status and body arrived
+fn get(
+    app: &mut App,
+    url: &'static str,
+    headers: &'static [(&'static str, &'static str)],
+) -> (u16, Vec<u8>) {
+    let response = Arc::new(Mutex::new(None));
+    let received = response.clone();
+    run(app, move |mut client: BevyReqwest| {
+        let mut request = client.get(url);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let request = request.build().unwrap();
+        let received = received.clone();
+        client
+            .send(request)
+            .on_response(move |trigger: On<ReqwestResponseEvent>| {
+                let response = trigger.event();
+                let status = response.status().as_u16();
+                *received.lock().unwrap() = Some((status, response.body().to_vec()));
+            });
+    });
+    update_until(app, Duration::from_secs(5), |_| {
+        response.lock().unwrap().is_some()
+    });
+    let response = response.lock().unwrap().take();
+    response.unwrap()
+}
+
+/// records a session against a mock to `path`
+fn record(path: &Path) {
+    let (mut app, mock) = app(ReqwestFixturePlugin::record(path));
+    mock.route(
+        MockRoute::get("https://example.com/login"),
+        MockResponse::ok()
+            .header("set-cookie", "session=secret")
+            .body("welcome"),
+    );
+    mock.route(
+        MockRoute::get("https://example.com/count").times(1),
+        MockResponse::ok().body("1"),
+    );
+    mock.route(
+        MockRoute::get("https://example.com/count"),
+        MockResponse::ok().body("2"),
+    );
+    mock.route(
+        MockRoute::get("https://example.com/scores?*"),
+        MockResponse::ok().body("scores"),
+    );
+    mock.route(
+        MockRoute::get("https://example.com/image"),
+        MockResponse::ok().body(vec![0xff, 0xfe, 0, 1]),
+    );
+
+    get(
+        &mut app,
+        "https://example.com/login",
+        &[("authorization", "Bearer secret"), ("cookie", "secret")],
+    );
+    get(&mut app, "https://example.com/count", &[]);
+    get(&mut app, "https://example.com/count", &[]);
+    get(&mut app, "https://example.com/scores?page=1&nonce=abc", &[]);
+    get(&mut app, "https://example.com/image", &[]);
+
+    let recorder = app.world().resource::<ReqwestRecorder>();
+    assert_eq!(recorder.len(), 5);
+    recorder.save().unwrap();
+}
+
+#[test]
+fn the_secret_headers_are_redacted() {
+    let fixture = TestFixture::new();
+    record(&fixture.0);
+
+    let json = fs::read_to_string(&fixture.0).unwrap();
+    assert!(!json.contains("secret"), "{json}");
+    assert!(json.contains("authorization: REDACTED"));
+    assert!(json.contains("cookie: REDACTED"));
+    assert!(json.contains("set-cookie: REDACTED"));
+}
+
+#[test]
+fn a_recording_is_replayed() {
+    let fixture = TestFixture::new();
+    record(&fixture.0);
+    let (mut app, mock) = app(ReqwestFixturePlugin::replay(&fixture.0));
+
+    assert_eq!(
+        get(&mut app, "https://example.com/login", &[]),
+        (200, b"welcome".to_vec())
+    );
+    mock.assert_called(&MockRoute::get("https://example.com/login"), 1);
+}
+
+#[test]
+fn repeated_requests_are_replayed_in_order_and_then_the_last_keeps_answering() {
+    let fixture = TestFixture::new();
+    record(&fixture.0);
+    let (mut app, _) = app(ReqwestFixturePlugin::replay(&fixture.0));
+
+    let mut count = || get(&mut app, "https://example.com/count", &[]).1;
+    assert_eq!(count(), b"1");
+    assert_eq!(count(), b"2");
+    assert_eq!(count(), b"2");
+}
+
+#[test]
+fn ignored_query_parameters_may_differ() {
+    let fixture = TestFixture::new();
+    record(&fixture.0);
+    let matching = FixtureMatching {
+        ignore_query: vec!["nonce".to_owned()],
+        ..default()
+    };
+    let (mut app, _) = app(ReqwestFixturePlugin::replay(&fixture.0).matching(matching));
+
+    assert_eq!(
+        get(&mut app, "https://example.com/scores?page=1&nonce=xyz", &[]),
+        (200, b"scores".to_vec())
+    );
+    // the other parameters still have to be the same
+    assert_eq!(
+        get(&mut app, "https://example.com/scores?page=2&nonce=xyz", &[]).0,
+        404
+    );
+}
+
+#[test]
+fn a_body_that_is_not_text_is_replayed_whole() {
+    let fixture = TestFixture::new();
+    record(&fixture.0);
+
+    let json = fs::read_to_string(&fixture.0).unwrap();
+    assert!(json.contains("body_base64"), "{json}");
+
+    let (mut app, _) = app(ReqwestFixturePlugin::replay(&fixture.0));
+    assert_eq!(
+        get(&mut app, "https://example.com/image", &[]),
+        (200, vec![0xff, 0xfe, 0, 1])
+    );
+}
@@ -0,0 +1,126 @@
+//! What happens to a request when its entity is reused or despawned
+
+#![cfg(not(target_family = "wasm"))]
+
+mod common;
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_mod_reqwest::*;
+use common::*;
+
+#[test]
+fn reusing_an_entity_with_a_request_in_flight_sends_the_new_one() {
+    // every request goes through the queue
+    let (mut app, mock) = app(ReqwestPlugin {
+        max_inflight: Some(4),
+        ..default()
+    });
+    mock.route(
+        MockRoute::get("https://example.com/slow"),
+        MockResponse::ok().delay(Duration::from_secs(10)),
+    );
+    mock.route(
+        MockRoute::get("https://example.com/fast"),
+        MockResponse::ok().body("fast"),
+    );
+    let entity = app.world_mut().spawn_empty().id();
+
+    run(&mut app, move |mut client: BevyReqwest| {
+        let request = client.get("https://example.com/slow").build().unwrap();
+        client
+            .send_using_entity(entity, request)
+            .unwrap()
+            .on_cancel(see::<ReqwestCancelledEvent>("cancelled"));
+    });
+    update_until(&mut app, Duration::from_secs(5), |_| {
+        mock.requests().len() == 1
+    });
+    run(&mut app, move |mut client: BevyReqwest| {
+        let request = client.get("https://example.com/fast").build().unwrap();
+        client
+            .send_using_entity(entity, request)
+            .unwrap()
+            .on_response(see::<ReqwestResponseEvent>("fast"));
+    });
+    update_until_seen(&mut app, "fast");
+
+    // the request that was replaced is cancelled
+    assert!(app.world().resource::<Seen>().contains("cancelled"));
+    mock.assert_called(&MockRoute::get("https://example.com/fast"), 1);
+}
+
+#[test]
+fn reusing_an_entity_does_not_keep_error_for_status() {
+    let (mut app, mock) = app(ReqwestPlugin::default());
+    mock.route(
+        MockRoute::get("https://example.com/missing"),
+        MockResponse::new(404).body("gone"),
+    );
+    let entity = app.world_mut().spawn_empty().id();
+
+    run(&mut app, move |mut client: BevyReqwest| {
+        let request = client.get("https://example.com/missing").build().unwrap();
+        client
+            .send_using_entity(entity, request)
+            .unwrap()
+            .error_for_status(true)
+            .on_status_error(see::<ReqwestStatusErrorEvent>("status error"));
+    });
+    update_until_seen(&mut app, "status error");
+    run(&mut app, move |mut client: BevyReqwest| {
+        let request = client.get("https://example.com/missing").build().unwrap();
+        client
+            .send_using_entity(entity, request)
+            .unwrap()
+            .on_response(
+                |trigger: On<ReqwestResponseEvent>, mut seen: ResMut<Seen>| {
+                    seen.0
+                        .push(format!("response {}", trigger.event().status()));
+                },
+            );
+    });
+    update_until_seen(&mut app, "response 404 Not Found");
+
+    // only the first request turned the 404 into an error
+    let seen = &app.world().resource::<Seen>().0;
+    assert_eq!(seen.iter().filter(|e| *e == "status error").count(), 1);
+}
+
+#[test]
+fn reusing_an_entity_does_not_keep_the_status_handlers() {
+    let (mut app, mock) = app(ReqwestPlugin::default());
+    mock.route(
+        MockRoute::get("https://example.com/profile"),
+        MockResponse::ok(),
+    );
+    let entity = app.world_mut().spawn_empty().id();
+
+    run(&mut app, move |mut client: BevyReqwest| {
+        let request = client.get("https://example.com/profile").build().unwrap();
+        client
+            .send_using_entity(entity, request)
+            .unwrap()
+            .on_success(see::<ReqwestStatusEvent>("first success"));
+    });
+    update_until_seen(&mut app, "first success");
+    run(&mut app, move |mut client: BevyReqwest| {
+        let request = client.get("https://example.com/profile").build().unwrap();
+        client
+            .send_using_entity(entity, request)
+            .unwrap()
+            .on_success(see::<ReqwestStatusEvent>("second success"));
+    });
+    update_until_seen(&mut app, "second success");
+    app.update();
+
+    // the handler of the first request ran once, and the second one was not routed twice
+    assert_eq!(
+        app.world().resource::<Seen>().0,
+        ["first success", "second success"]
+    );
+    // only the handler of the second request is left
+    let children = app.world().get::<Children>(entity).unwrap();
+    assert_eq!(children.len(), 1);
+}
@@ -0,0 +1,223 @@
+//! The requests held back in the queue by the limits of the `ReqwestPlugin`
+
+#![cfg(not(target_family = "wasm"))]
+
+mod common;
+
+use std::time::Duration;
+
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use bevy_mod_reqwest::*;
+use common::*;
+
+/// how long the mocked responses take, so the requests stay in flight for a few updates
+const DELAY: Duration = Duration::from_millis(200);
+
+/// sends a `GET` request to every url, in order, with a handler that writes the url to [`Seen`]
+fn send(app: &mut App, urls: &'static [&'static str]) {
+    run(app, move |mut client: BevyReqwest| {
+        for url in urls {
+            let request = client.get(*url).build().unwrap();
+            client
+                .send(request)
+                .on_response(see::<ReqwestResponseEvent>(url));
+        }
+    });
+    // the requests that did not fit are dispatched when the queue is updated
+    app.update();
+}
+
+/// the urls of the requests the mock was sent, in order
+fn sent(mock: &ReqwestMock) -> Vec<String> {
+    let requests = mock.requests();
+    requests.iter().map(|r| r.url.to_string()).collect()
+}
+
+fn queued(app: &mut App) -> usize {
+    let mut queued = app.world_mut().query::<&ReqwestQueued>();
+    queued.iter(app.world()).count()
+}
+
+#[test]
+fn max_inflight_holds_requests_back() {
+    let (mut app, mock) = app(ReqwestPlugin {
+        max_inflight: Some(2),
+        ..default()
+    });
+    mock.route(MockRoute::any(), MockResponse::ok().delay(DELAY));
+
+    send(
+        &mut app,
+        &[
+            "https://a.com/1",
+            "https://a.com/2",
+            "https://b.com/3",
+            "https://b.com/4",
+        ],
+    );
+
+    assert_eq!(mock.requests().len(), 2);
+    assert_eq!(queued(&mut app), 2);
+    let queue = app.world().resource::<ReqwestQueue>();
+    assert_eq!(queue.in_flight(), 2);
+    assert_eq!(queue.queued(), 2);
+    assert_eq!(queue.in_flight_for_host("a.com"), 2);
+    assert_eq!(queue.queued_for_host("b.com"), 2);
+
+    // the queued requests are sent as the first ones finish
+    update_until_seen(&mut app, "https://a.com/1");
+    update_until(&mut app, Duration::from_secs(5), |_| {
+        mock.requests().len() == 4
+    });
+    update_until_seen(&mut app, "https://b.com/4");
+    update_until_seen(&mut app, "https://b.com/3");
+
+    assert_eq!(queued(&mut app), 0);
+    let queue = app.world().resource::<ReqwestQueue>();
+    assert_eq!(queue.in_flight(), 0);
+    assert_eq!(queue.queued(), 0);
+}
+
+#[test]
+fn max_inflight_per_host_holds_requests_back() {
+    let (mut app, mock) = app(ReqwestPlugin {
+        max_inflight_per_host: Some(1),
+        ..default()
+    });
+    mock.route(MockRoute::any(), MockResponse::ok().delay(DELAY));
+
+    send(
+        &mut app,
+        &["https://a.com/1", "https://a.com/2", "https://b.com/3"],
+    );
+
+    // the other host has room of its own
+    assert_eq!(sent(&mock), ["https://a.com/1", "https://b.com/3"]);
+    assert_eq!(queued(&mut app), 1);
+    let queue = app.world().resource::<ReqwestQueue>();
+    assert_eq!(queue.in_flight(), 2);
+    assert_eq!(queue.in_flight_for_host("a.com"), 1);
+    assert_eq!(queue.in_flight_for_host("b.com"), 1);
+    assert_eq!(queue.queued_for_host("a.com"), 1);
+    assert_eq!(queue.queued_for_host("b.com"), 0);
+
+    update_until_seen(&mut app, "https://a.com/1");
+    update_until(&mut app, Duration::from_secs(5), |_| {
+        mock.requests().len() == 3
+    });
+    assert_eq!(app.world().resource::<ReqwestQueue>().queued(), 0);
+    update_until_seen(&mut app, "https://a.com/2");
+    update_until_seen(&mut app, "https://b.com/3");
+    assert_eq!(app.world().resource::<ReqwestQueue>().in_flight(), 0);
+}
+
+#[test]
+fn requests_with_room_are_sent_right_away() {
+    let (mut app, mock) = app(ReqwestPlugin {
+        max_inflight: Some(2),
+        ..default()
+    });
+    mock.route(MockRoute::any(), MockResponse::ok().delay(DELAY));
+
+    app.world_mut()
+        .run_system_once(|mut client: BevyReqwest| {
+            for url in ["https://a.com/1", "https://a.com/2", "https://a.com/3"] {
+                let request = client.get(url).build().unwrap();
+                client.send(request);
+            }
+        })
+        .unwrap();
+
+    // without waiting for the queue to be updated, only the request over the limit is held back
+    assert_eq!(queued(&mut app), 1);
+    assert_eq!(app.world().resource::<ReqwestQueue>().in_flight(), 2);
+
+    // the requests are dropped before the thread of the test ends
+    let mut requests = app
+        .world_mut()
+        .query_filtered::<Entity, With<ReqwestInflight>>();
+    let requests: Vec<_> = requests.iter(app.world()).collect();
+    for entity in requests {
+        app.world_mut().despawn(entity);
+    }
+    app.update();
+}
+
+/// sends a `GET` request to `url` with `priority`, without updating the queue
+fn send_with_priority(app: &mut App, url: &'static str, priority: RequestPriority) {
+    run(app, move |mut client: BevyReqwest| {
+        let request = client.get(url).build().unwrap();
+        client
+            .send(request)
+            .priority(priority)
+            .on_response(see::<ReqwestResponseEvent>(url));
+    });
+}
+
+#[test]
+fn higher_priorities_are_sent_first() {
+    let (mut app, mock) = app(ReqwestPlugin {
+        max_inflight: Some(1),
+        ..default()
+    });
+    mock.route(MockRoute::any(), MockResponse::ok().delay(DELAY));
+    send(&mut app, &["https://a.com/first"]);
+
+    send_with_priority(&mut app, "https://a.com/low", RequestPriority::LOW);
+    send_with_priority(&mut app, "https://a.com/normal", RequestPriority::NORMAL);
+    send_with_priority(
+        &mut app,
+        "https://a.com/critical",
+        RequestPriority::CRITICAL,
+    );
+    update_until_seen(&mut app, "https://a.com/low");
+
+    assert_eq!(
+        sent(&mock),
+        [
+            "https://a.com/first",
+            "https://a.com/critical",
+            "https://a.com/normal",
+            "https://a.com/low",
+        ]
+    );
+}
+
+#[test]
+fn changing_the_priority_of_a_queued_request_reorders_it() {
+    let (mut app, mock) = app(ReqwestPlugin {
+        max_inflight: Some(1),
+        ..default()
+    });
+    mock.route(MockRoute::any(), MockResponse::ok().delay(DELAY));
+    send(
+        &mut app,
+        &[
+            "https://a.com/first",
+            "https://a.com/early",
+            "https://a.com/late",
+        ],
+    );
+    assert_eq!(queued(&mut app), 2);
+
+    // the request that was queued last is needed first
+    let mut names = app.world_mut().query::<(Entity, &Name)>();
+    let late = names
+        .iter(app.world())
+        .find(|(_, name)| name.as_str() == "http: https://a.com/late")
+        .map(|(entity, _)| entity)
+        .unwrap();
+    app.world_mut()
+        .entity_mut(late)
+        .insert(RequestPriority::HIGH);
+    update_until_seen(&mut app, "https://a.com/early");
+
+    assert_eq!(
+        sent(&mock),
+        [
+            "https://a.com/first",
+            "https://a.com/late",
+            "https://a.com/early",
+        ]
+    );
+}
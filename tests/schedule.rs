@@ -0,0 +1,163 @@
+//! Periodic and delayed requests, which wait in `Time<Virtual>`
+
+#![cfg(not(target_family = "wasm"))]
+
+mod common;
+
+use std::time::Duration;
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_mod_reqwest::*;
+use common::*;
+
+const URL: &str = "https://example.com/scores";
+
+/// the time every update of the app advances by
+const FRAME: Duration = Duration::from_millis(100);
+
+/// an app in which every update advances the time by [`FRAME`]
+fn app() -> (App, ReqwestMock) {
+    let (mut app, mock) = common::app(ReqwestPlugin::default());
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(FRAME));
+    // the first update only starts the clock
+    app.update();
+    (app, mock)
+}
+
+/// updates the app `frames` times
+fn frames(app: &mut App, frames: u32) {
+    for _ in 0..frames {
+        app.update();
+    }
+}
+
+/// despawns the periodic requests, and updates the app so the task pool drops their requests in flight,
+/// which it cannot do anymore once the thread of the test is ending
+fn stop(app: &mut App) {
+    let mut periodic = app
+        .world_mut()
+        .query_filtered::<Entity, With<PeriodicRequest>>();
+    let entities: Vec<Entity> = periodic.iter(app.world()).collect();
+    for entity in entities {
+        app.world_mut().despawn(entity);
+    }
+    frames(app, 2);
+}
+
+fn seen(app: &App, event: &str) -> usize {
+    let seen = &app.world().resource::<Seen>().0;
+    seen.iter().filter(|seen| *seen == event).count()
+}
+
+#[test]
+fn does_not_send_while_the_previous_request_is_in_flight() {
+    let (mut app, mock) = app();
+    mock.route(
+        MockRoute::get(URL),
+        MockResponse::ok().delay(Duration::from_millis(500)),
+    );
+
+    run(&mut app, |mut client: BevyReqwest| {
+        let request = client.get(URL).build().unwrap();
+        client
+            .send_periodic(PeriodicRequest::new(request, FRAME))
+            .on_response(see::<ReqwestResponseEvent>("response"));
+    });
+    // many intervals pass in the time of the app, but not the delay of the response
+    frames(&mut app, 10);
+    assert_eq!(mock.requests().len(), 1);
+
+    update_until_seen(&mut app, "response");
+    update_until(&mut app, Duration::from_secs(5), |_| {
+        mock.requests().len() == 2
+    });
+    stop(&mut app);
+}
+
+#[test]
+fn runs_the_observers_for_every_response() {
+    let (mut app, mock) = app();
+    mock.route(MockRoute::get(URL), MockResponse::ok().body("42"));
+
+    run(&mut app, |mut client: BevyReqwest| {
+        let request = client.get(URL).build().unwrap();
+        client
+            .send_periodic(PeriodicRequest::new(request, FRAME * 2))
+            .on_response(see::<ReqwestResponseEvent>("response"));
+    });
+    update_until(&mut app, Duration::from_secs(5), |app| {
+        seen(app, "response") >= 3
+    });
+
+    // every request is sent from the same entity
+    let mut periodic = app.world_mut().query::<&PeriodicRequest>();
+    assert_eq!(periodic.iter(app.world()).count(), 1);
+    assert!(mock.calls(&MockRoute::get(URL)) >= 3);
+    stop(&mut app);
+}
+
+#[test]
+fn waits_for_the_initial_delay() {
+    let (mut app, mock) = app();
+    mock.route(MockRoute::get(URL), MockResponse::ok());
+
+    run(&mut app, |mut client: BevyReqwest| {
+        let request = client.get(URL).build().unwrap();
+        let periodic = PeriodicRequest::new(request, FRAME).with_initial_delay(FRAME * 10);
+        client.send_periodic(periodic);
+    });
+    frames(&mut app, 8);
+    assert!(mock.requests().is_empty());
+
+    frames(&mut app, 2);
+    update_until(&mut app, Duration::from_secs(5), |_| {
+        !mock.requests().is_empty()
+    });
+    stop(&mut app);
+}
+
+#[test]
+fn send_after_waits_while_the_time_is_paused() {
+    let (mut app, mock) = app();
+    mock.route(MockRoute::get(URL), MockResponse::ok());
+
+    run(&mut app, |mut client: BevyReqwest| {
+        let request = client.get(URL).build().unwrap();
+        client
+            .send_after(request, FRAME * 3)
+            .on_response(see::<ReqwestResponseEvent>("response"));
+    });
+    app.world_mut().resource_mut::<Time<Virtual>>().pause();
+    frames(&mut app, 10);
+    assert!(mock.requests().is_empty());
+
+    app.world_mut().resource_mut::<Time<Virtual>>().unpause();
+    update_until_seen(&mut app, "response");
+    assert_eq!(mock.requests().len(), 1);
+}
+
+#[test]
+fn cancelling_a_delayed_request() {
+    let (mut app, mock) = app();
+
+    run(&mut app, |mut client: BevyReqwest| {
+        let request = client.get(URL).build().unwrap();
+        client
+            .send_after(request, FRAME * 3)
+            .on_cancel(see::<ReqwestCancelledEvent>("cancelled"));
+    });
+    let entity = app
+        .world_mut()
+        .query_filtered::<Entity, With<DelayedRequest>>()
+        .single(app.world())
+        .unwrap();
+    run(&mut app, move |mut client: BevyReqwest| {
+        client.cancel(entity)
+    });
+    frames(&mut app, 10);
+
+    assert_eq!(app.world().resource::<Seen>().0, ["cancelled"]);
+    assert!(mock.requests().is_empty());
+    // it was spawned by send_after, so it is despawned like a finished request
+    assert!(app.world().get_entity(entity).is_err());
+}
@@ -0,0 +1,134 @@
+//! How an `SseSubscription` handles a server that refuses the stream, and the ids of its messages
+
+#![cfg(not(target_family = "wasm"))]
+
+mod common;
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_mod_reqwest::*;
+use common::*;
+
+const URL: &str = "https://example.com/events";
+
+/// subscribes to [`URL`] and updates the app until the subscription is closed, and then for a few reconnect
+/// delays more
+fn subscribe(app: &mut App) -> Entity {
+    let entity = app
+        .world_mut()
+        .spawn(SseSubscription::new(URL).with_reconnect_delay(Duration::from_millis(10)))
+        .observe(|trigger: On<SseClosedEvent>, mut seen: ResMut<Seen>| {
+            seen.0.push(format!("closed {:?}", trigger.event().reason));
+        })
+        .observe(see::<SseErrorEvent>("error"))
+        .observe(see::<SseOpenEvent>("open"))
+        .id();
+    update_until(app, Duration::from_secs(5), |app| {
+        !app.world().resource::<Seen>().0.is_empty()
+    });
+    update_for(app, Duration::from_millis(100));
+    entity
+}
+
+#[test]
+fn an_error_status_closes_the_subscription() {
+    let (mut app, mock) = app(ReqwestPlugin::default());
+    mock.route(MockRoute::get(URL), MockResponse::new(401));
+
+    let entity = subscribe(&mut app);
+
+    assert_eq!(app.world().resource::<Seen>().0, ["closed Status(401)"]);
+    assert!(app
+        .world()
+        .get::<SseSubscription>(entity)
+        .unwrap()
+        .is_closed());
+    // it did not reconnect
+    mock.assert_called(&MockRoute::get(URL), 1);
+}
+
+#[test]
+fn another_content_type_closes_the_subscription() {
+    let (mut app, mock) = app(ReqwestPlugin::default());
+    mock.route(
+        MockRoute::get(URL),
+        MockResponse::ok()
+            .header("content-type", "text/html")
+            .body("hi"),
+    );
+
+    let entity = subscribe(&mut app);
+
+    assert_eq!(
+        app.world().resource::<Seen>().0,
+        ["closed ContentType(Some(\"text/html\"))"]
+    );
+    assert!(app
+        .world()
+        .get::<SseSubscription>(entity)
+        .unwrap()
+        .is_closed());
+    mock.assert_called(&MockRoute::get(URL), 1);
+}
+
+#[test]
+fn an_event_stream_with_a_charset_is_opened() {
+    let (mut app, mock) = app(ReqwestPlugin::default());
+    mock.route(
+        MockRoute::get(URL).header("accept", "text/event-stream"),
+        MockResponse::ok().header("content-type", "Text/Event-Stream; charset=utf-8"),
+    );
+
+    let entity = subscribe(&mut app);
+
+    let seen = &app.world().resource::<Seen>().0;
+    assert!(seen.iter().all(|event| event == "open"), "{seen:?}");
+    assert!(!app
+        .world()
+        .get::<SseSubscription>(entity)
+        .unwrap()
+        .is_closed());
+    // the stream ended, so it was opened again
+    assert!(mock.calls(&MockRoute::get(URL)) > 1);
+
+    // the task pool drops the stream that is opening, which it cannot do anymore once the thread of the test
+    // is ending
+    app.world_mut().despawn(entity);
+    app.update();
+    app.update();
+}
+
+#[test]
+fn an_empty_id_resets_the_last_event_id() {
+    let (mut app, mock) = app(ReqwestPlugin::default());
+    mock.route(
+        MockRoute::get(URL),
+        MockResponse::ok()
+            .header("content-type", "text/event-stream")
+            .body("id: 7\ndata: a\n\nid:\ndata: b\n\n"),
+    );
+
+    let entity = app
+        .world_mut()
+        // the messages are only sent once
+        .spawn(SseSubscription::new(URL).with_reconnect_delay(Duration::from_secs(60)))
+        .observe(|trigger: On<SseMessageEvent>, mut seen: ResMut<Seen>| {
+            let message = trigger.event();
+            seen.0.push(format!("{} {:?}", message.data, message.id));
+        })
+        .id();
+    update_until(&mut app, Duration::from_secs(5), |app| {
+        app.world().resource::<Seen>().0.len() == 2
+    });
+
+    assert_eq!(
+        app.world().resource::<Seen>().0,
+        ["a Some(\"7\")", "b None"]
+    );
+    let subscription = app.world().get::<SseSubscription>(entity).unwrap();
+    assert_eq!(subscription.last_event_id(), None);
+    app.world_mut().despawn(entity);
+    app.update();
+    app.update();
+}
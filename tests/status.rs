@@ -0,0 +1,157 @@
+//! Responses with an error status, and the handlers the responses are routed to by their status
+
+#![cfg(not(target_family = "wasm"))]
+
+mod common;
+
+use bevy::prelude::*;
+use bevy_mod_reqwest::*;
+use common::*;
+
+const URL: &str = "https://example.com/scores";
+
+/// sends a `GET` request to `url`, with `error_for_status` set on the request if it is `Some`, and handlers
+/// that write what they received to [`Seen`]
+fn send(app: &mut App, url: &'static str, error_for_status: Option<bool>) {
+    run(app, move |mut client: BevyReqwest| {
+        let request = client.get(url).build().unwrap();
+        let mut builder = client.send(request);
+        if let Some(enabled) = error_for_status {
+            builder = builder.error_for_status(enabled);
+        }
+        builder
+            .on_response(
+                |trigger: On<ReqwestResponseEvent>, mut seen: ResMut<Seen>| {
+                    seen.0
+                        .push(format!("response {}", trigger.event().status()));
+                },
+            )
+            .on_status_error(
+                |trigger: On<ReqwestStatusErrorEvent>, mut seen: ResMut<Seen>| {
+                    let error = trigger.event();
+                    let request_id = &error.response_headers()["x-request-id"];
+                    seen.0.push(format!(
+                        "status error {} {request_id:?} {}",
+                        error.status(),
+                        error.as_str().unwrap()
+                    ));
+                },
+            );
+    });
+    app.update();
+}
+
+fn seen(app: &mut App) -> Vec<String> {
+    update_until(app, std::time::Duration::from_secs(5), |app| {
+        !app.world().resource::<Seen>().0.is_empty()
+    });
+    std::mem::take(&mut app.world_mut().resource_mut::<Seen>().0)
+}
+
+#[test]
+fn error_statuses_trigger_a_status_error_with_the_response() {
+    let (mut app, mock) = app(ReqwestPlugin {
+        error_for_status: true,
+        ..default()
+    });
+    mock.route(
+        MockRoute::get(URL),
+        MockResponse::new(400)
+            .header("x-request-id", "10")
+            .body("bad query"),
+    );
+    mock.route(
+        MockRoute::get("https://example.com/broken"),
+        MockResponse::new(503)
+            .header("x-request-id", "11")
+            .body("maintenance"),
+    );
+
+    send(&mut app, URL, None);
+    assert_eq!(
+        seen(&mut app),
+        ["status error 400 Bad Request \"10\" bad query"]
+    );
+    send(&mut app, "https://example.com/broken", None);
+    assert_eq!(
+        seen(&mut app),
+        ["status error 503 Service Unavailable \"11\" maintenance"]
+    );
+}
+
+#[test]
+fn successful_statuses_are_responses() {
+    let (mut app, mock) = app(ReqwestPlugin {
+        error_for_status: true,
+        ..default()
+    });
+    mock.route(MockRoute::get(URL), MockResponse::new(204));
+
+    send(&mut app, URL, None);
+
+    assert_eq!(seen(&mut app), ["response 204 No Content"]);
+}
+
+#[test]
+fn the_request_overrides_the_plugin() {
+    let (mut app, mock) = app(ReqwestPlugin {
+        error_for_status: true,
+        ..default()
+    });
+    mock.route(MockRoute::get(URL), MockResponse::new(404));
+
+    send(&mut app, URL, Some(false));
+
+    assert_eq!(seen(&mut app), ["response 404 Not Found"]);
+}
+
+#[test]
+fn the_request_can_enable_it_when_the_plugin_does_not() {
+    let (mut app, mock) = app(ReqwestPlugin::default());
+    mock.route(
+        MockRoute::get(URL),
+        MockResponse::new(404)
+            .header("x-request-id", "12")
+            .body("missing"),
+    );
+
+    send(&mut app, URL, Some(true));
+
+    assert_eq!(
+        seen(&mut app),
+        ["status error 404 Not Found \"12\" missing"]
+    );
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn on_json_success_receives_the_deserialized_body() {
+    #[derive(serde::Deserialize)]
+    struct Score {
+        points: u32,
+    }
+
+    let (mut app, mock) = app(ReqwestPlugin::default());
+    mock.route(
+        MockRoute::get(URL),
+        MockResponse::new(201).body(r#"{"points": 3}"#),
+    );
+
+    run(&mut app, |mut client: BevyReqwest| {
+        let request = client.get(URL).build().unwrap();
+        client
+            .send(request)
+            .on_json_success(
+                |trigger: On<JsonStatusResponse<Score>>, mut seen: ResMut<Seen>| {
+                    let response = trigger.event();
+                    seen.0.push(format!(
+                        "success {} {} points",
+                        response.status, response.data.points
+                    ));
+                },
+            )
+            .on_client_error(see::<ReqwestStatusEvent>("client error"));
+    });
+
+    assert_eq!(seen(&mut app), ["success 201 Created 3 points"]);
+}